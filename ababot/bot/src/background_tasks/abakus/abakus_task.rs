@@ -1,4 +1,4 @@
-use std::{sync::Arc, time::Duration};
+use std::sync::Arc;
 
 use rayon::prelude::{IntoParallelIterator, ParallelIterator};
 use reqwest::Client;
@@ -7,50 +7,63 @@ use serenity::{model::prelude::ChannelId, prelude::Context};
 use tokio::time::sleep;
 
 use crate::background_tasks::abakus::types::{ApiEvent, Event};
+use crate::config::{AbakusConfig, Config};
+use crate::error::{BotError, BotResult};
+use crate::monitoring::metrics::Metrics;
 use crate::utils::{
-    get_channel_id,
-    time::{schedule, Interval, Time, DAY_AS_SECONDS},
+    clock::SharedClock, opted_in_channels, supervisor::TaskReporter, time::Interval,
 };
-use chrono_tz::Europe::Oslo;
+use chrono::{DateTime, Local};
+use chrono_tz::{Europe::Oslo, Tz};
 
-const EVENT_URL: &str = "https://abakus.no/events/";
-const REG_URL: &str = "https://lego.abakus.no/api/v1/events/";
-
-const START_TIME: (u8, u8, u8) = (8, 0, 0);
-
-pub async fn run(ctx: Arc<Context>) {
+pub async fn run(ctx: Arc<Context>, task: TaskReporter) {
     //TODO: spawn another thread to watch for reactions to messages
-    let t = Time::new_unchecked(START_TIME.0, START_TIME.1, START_TIME.2).nearest_unchecked();
-    schedule(
-        Interval::EveryDeltaStartAt(Duration::from_secs(DAY_AS_SECONDS), t),
-        || async { fetch_and_send(ctx.clone()).await },
-    )
-    .await;
+    let config = Config::get(&ctx).await;
+    let interval = Interval::Calendar(config.abakus.schedule, config.abakus.days_off);
+    task.schedule(interval, || async { fetch_and_send(ctx.clone()).await })
+        .await;
 }
 
-pub async fn fetch_and_send(ctx: Arc<Context>) {
+pub async fn fetch_and_send(ctx: Arc<Context>) -> BotResult {
     tracing::info!("Posting todays registrations");
-    let channel_id = match get_channel_id("abakus-events", &ctx.http).await {
-        Ok(id) => id,
+    let config = Config::get(&ctx).await;
+    let channels = opted_in_channels(&config.channels.abakus, &ctx).await;
+    if channels.is_empty() {
+        tracing::debug!("No guild has opted in to abakus events");
+        return Ok(());
+    }
+
+    let now = SharedClock::get(&ctx).await.now_in_oslo();
+    let fetched_data = match fetch(&config.abakus, now).await {
+        Ok(v) => v,
         Err(e) => {
-            tracing::error!("Failed to get channel id: {}", e);
-            return;
+            Metrics::get(&ctx).await.fetch_failed("abakus");
+            return Err(e.into());
         }
     };
 
-    let fetched_data = match fetch().await {
-        Ok(v) => v,
+    let all_events = match parse_events(&config.abakus, fetched_data, now).await {
+        Ok(events) => events,
         Err(e) => {
-            tracing::warn!("Could not fetch events: {}", e);
-            return;
+            Metrics::get(&ctx).await.fetch_failed("abakus");
+            return Err(e);
         }
     };
 
-    let all_events = parse_events(fetched_data).await;
-
-    let filtered_events = filter_existing_messages(ctx.clone(), all_events).await;
+    for channel_id in channels {
+        let filtered_events = filter_existing_messages(&ctx, channel_id, &all_events).await;
+        send_events(&ctx, &config.abakus, channel_id, filtered_events).await;
+    }
+    Ok(())
+}
 
-    for event in filtered_events {
+async fn send_events(
+    ctx: &Context,
+    config: &AbakusConfig,
+    channel_id: ChannelId,
+    events: Vec<&Event>,
+) {
+    for event in events {
         let channel_message = channel_id
             .send_message(&ctx.http, |m| {
                 m.embed(|e| {
@@ -60,17 +73,17 @@ pub async fn fetch_and_send(ctx: Arc<Context>) {
                             "Registration",
                             &event
                                 .reg_time
-                                .map(|e| e.to_rfc2822().split('+').next().unwrap().to_owned())
+                                .map(|e| format!("<t:{}:F>", e.timestamp()))
                                 .unwrap_or_else(|| "TBA".to_string()),
                             false,
                         )
                         .field(
                             "When",
-                            event.event_time.to_rfc2822().split('+').next().unwrap(),
+                            format!("<t:{}:F>", event.event_time.timestamp()),
                             false,
                         )
                         .field("Where", &event.event_location, false)
-                        .url(format!("{}{}", EVENT_URL, event.id))
+                        .url(format!("{}{}", config.event_url, event.id))
                         .image(&event.thumbnail)
                         .footer(|f| f.text(event.id))
                 })
@@ -111,43 +124,29 @@ pub async fn fetch_and_send(ctx: Arc<Context>) {
     }
 }
 
-async fn fetch() -> Result<String, Box<dyn std::error::Error>> {
+async fn fetch(config: &AbakusConfig, now: DateTime<Tz>) -> Result<String, reqwest::Error> {
     let client = Client::new();
-    let now = chrono::Utc::now().with_timezone(&Oslo);
     let today = now.date_naive();
-    let url = format!(
-        "https://lego.abakus.no/api/v1/events/?date_after={}",
-        today.format("%Y-%m-%d")
-    );
+    let url = format!("{}?date_after={}", config.api_url, today.format("%Y-%m-%d"));
     tracing::debug!("Fetching events from {}", url);
     let res = client.get(url).send().await?.text().await?;
     Ok(res)
 }
 
-async fn parse_events(events: String) -> Vec<Event> {
-    let v: serde_json::Value = serde_json::from_str(&events).unwrap();
-    let results = v["results"].as_array().unwrap();
-    let events: Vec<ApiEvent> = results
-        .into_par_iter()
-        .map(|e| {
-            let id = e["id"].as_i64().unwrap() as i32;
-            let title = e["title"].as_str().map(|s| s.to_string());
-            let description = e["description"].as_str().map(|s| s.to_string());
-            let event_time = e["startTime"].as_str().map(|s| s.to_string());
-            let event_location = e["location"].as_str().map(|s| s.to_string());
-            let thumbnail = e["cover"].as_str().map(|s| s.to_string());
-            ApiEvent {
-                id,
-                title,
-                description,
-                event_time,
-                event_location,
-                thumbnail,
-            }
+async fn parse_events(
+    config: &AbakusConfig,
+    events: String,
+    now: DateTime<Tz>,
+) -> BotResult<Vec<Event>> {
+    let events = parse_event_list(&events).map_err(BotError::internal)?;
+    let mapped: Vec<Event> = events
+        .into_iter()
+        .filter_map(|e| {
+            Event::try_from(e)
+                .map_err(|e| tracing::debug!("Skipping {}", e))
+                .ok()
         })
         .collect();
-
-    let mapped: Vec<Event> = events.into_iter().map(|e| e.into()).collect();
     let mut registration_times = Vec::with_capacity(mapped.len());
 
     // Clients to send
@@ -155,7 +154,7 @@ async fn parse_events(events: String) -> Vec<Event> {
     let client_ref = Arc::new(client);
 
     for event in &mapped {
-        let url = format!("{}{}", REG_URL, event.id);
+        let url = format!("{}{}", config.api_url, event.id);
         let event_time = get_reg_time(url, client_ref.clone());
         registration_times.push(event_time);
     }
@@ -167,10 +166,7 @@ async fn parse_events(events: String) -> Vec<Event> {
     for (mut event, time) in mapped.into_iter().zip(time_awaited.into_iter()) {
         match time {
             Ok(t) => {
-                let until = t
-                    .date_naive()
-                    .signed_duration_since(chrono::Utc::now().with_timezone(&Oslo).date_naive())
-                    .num_days();
+                let until = days_until(t, now);
                 if until == 0 {
                     tracing::debug!("Event {} added", event.title);
                     event.reg_time = Some(t);
@@ -189,29 +185,70 @@ async fn parse_events(events: String) -> Vec<Event> {
         }
     }
     // Return only events that have registration today
-    actuall_res
+    Ok(actuall_res)
 }
 
-async fn filter_existing_messages(ctx: Arc<Context>, events: Vec<Event>) -> Vec<Event> {
-    let channel_id = match get_channel_id("abakus-events", &ctx.http).await {
-        Ok(id) => id,
+/// The events in a page of the event list. Fails if it is not the list the API used to send,
+/// e.g. an error page or a changed format
+fn parse_event_list(events: &str) -> Result<Vec<ApiEvent>, String> {
+    let v: serde_json::Value =
+        serde_json::from_str(events).map_err(|e| format!("Invalid event list: {}", e))?;
+    let results = v["results"]
+        .as_array()
+        .ok_or("The event list has no results")?;
+    results
+        .into_par_iter()
+        .map(|e| {
+            let id = e["id"]
+                .as_i64()
+                .and_then(|id| i32::try_from(id).ok())
+                .ok_or_else(|| format!("Event without a valid id: {}", e))?;
+            let title = e["title"].as_str().map(|s| s.to_string());
+            let description = e["description"].as_str().map(|s| s.to_string());
+            let event_time = e["startTime"].as_str().map(|s| s.to_string());
+            let event_location = e["location"].as_str().map(|s| s.to_string());
+            let thumbnail = e["cover"].as_str().map(|s| s.to_string());
+            Ok(ApiEvent {
+                id,
+                title,
+                description,
+                event_time,
+                event_location,
+                thumbnail,
+            })
+        })
+        .collect()
+}
+
+/// Days from `now` until the day registration opens, counted on the Oslo calendar
+fn days_until(registration: DateTime<Local>, now: DateTime<Tz>) -> i64 {
+    registration
+        .with_timezone(&Oslo)
+        .date_naive()
+        .signed_duration_since(now.date_naive())
+        .num_days()
+}
+
+async fn filter_existing_messages<'a>(
+    ctx: &Context,
+    channel_id: ChannelId,
+    events: &'a [Event],
+) -> Vec<&'a Event> {
+    let embeds = match channel_id.messages(&ctx.http, |m| m.limit(100)).await {
+        Ok(messages) => messages
+            .into_iter()
+            .flat_map(|m| m.embeds)
+            .filter_map(|e| e.footer)
+            .map(|f| f.text)
+            .collect::<Vec<_>>(),
         Err(e) => {
-            tracing::error!("Failed to get channel id: {}", e);
+            tracing::warn!("Could not fetch messages in {}: {}", channel_id, e);
             return Vec::new();
         }
     };
-    let embeds = ChannelId(channel_id.0)
-        .messages(&ctx.http, |m| m.limit(100))
-        .await
-        .unwrap()
-        .into_iter()
-        .flat_map(|m| m.embeds)
-        .filter_map(|e| e.footer)
-        .map(|f| f.text)
-        .collect::<Vec<_>>();
 
     events
-        .into_par_iter()
+        .iter()
         .filter(|e| !embeds.contains(&e.id.to_string()))
         .collect()
 }
@@ -229,3 +266,53 @@ async fn get_reg_time(
     let time = pools[0]["activationDate"].as_str().ok_or("No time")?;
     Ok(chrono::DateTime::parse_from_rfc3339(time)?.with_timezone(&chrono::Local))
 }
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use super::*;
+
+    fn local(time: &str) -> DateTime<Local> {
+        DateTime::parse_from_rfc3339(time)
+            .unwrap()
+            .with_timezone(&Local)
+    }
+
+    #[test]
+    fn registration_today_follows_the_oslo_calendar() {
+        let morning = Oslo.with_ymd_and_hms(2023, 6, 1, 8, 0, 0).unwrap();
+        assert_eq!(days_until(local("2023-06-01T10:00:00Z"), morning), 0);
+        // 00:30 in Oslo, but still the day before in UTC
+        assert_eq!(days_until(local("2023-05-31T22:30:00Z"), morning), 0);
+        assert_eq!(days_until(local("2023-06-01T22:30:00Z"), morning), 1);
+        assert_eq!(days_until(local("2023-05-31T21:30:00Z"), morning), -1);
+    }
+
+    #[test]
+    fn event_lists_are_checked() {
+        let events = parse_event_list(
+            r#"{"results": [{"id": 3, "title": "Bedpres", "location": "A4"}, {"id": 4}]}"#,
+        )
+        .unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].id, 3);
+        assert_eq!(events[0].title.as_deref(), Some("Bedpres"));
+        assert_eq!(events[1].title, None);
+
+        assert!(parse_event_list("<html>502 Bad Gateway</html>").is_err());
+        assert!(parse_event_list(r#"{"events": []}"#).is_err());
+        assert!(parse_event_list(r#"{"results": [{"title": "No id"}]}"#).is_err());
+    }
+
+    #[test]
+    fn registration_today_just_before_midnight() {
+        let late = Utc
+            .with_ymd_and_hms(2023, 10, 28, 21, 59, 0)
+            .unwrap()
+            .with_timezone(&Oslo);
+        assert_eq!(late.format("%H:%M").to_string(), "23:59");
+        assert_eq!(days_until(local("2023-10-28T06:00:00Z"), late), 0);
+        assert_eq!(days_until(local("2023-10-28T23:00:00Z"), late), 1);
+    }
+}
@@ -21,24 +21,28 @@ pub struct Event {
     pub reg_time: Option<DateTime<Local>>,
 }
 
-impl From<ApiEvent> for Event {
-    fn from(api_event: ApiEvent) -> Self {
-        Event {
+/// Fails for events without a start time, there is nothing sensible to show instead
+impl TryFrom<ApiEvent> for Event {
+    type Error = String;
+
+    fn try_from(api_event: ApiEvent) -> Result<Self, Self::Error> {
+        let event_time = api_event
+            .event_time
+            .as_deref()
+            .and_then(|time| time.parse().ok())
+            .ok_or_else(|| format!("event {} has no valid start time", api_event.id))?;
+        Ok(Event {
             id: api_event.id,
             title: api_event.title.unwrap_or_else(|| "No title".to_string()),
             description: api_event
                 .description
                 .unwrap_or_else(|| "No description".to_string()),
-            event_time: api_event
-                .event_time
-                .unwrap_or_default()
-                .parse()
-                .unwrap_or_else(|_| Local::now()),
+            event_time,
             event_location: api_event
                 .event_location
                 .unwrap_or_else(|| "N/A".to_string()),
             thumbnail: api_event.thumbnail.unwrap_or_else(|| "N/A".to_string()),
             reg_time: None,
-        }
+        })
     }
 }
@@ -1,3 +1,16 @@
+use crate::utils::supervisor::Supervisor;
+
 pub mod abakus;
 pub mod lunch;
+pub mod reminders;
 pub mod yr;
+
+/// Every background task the bot runs
+pub fn supervisor() -> Supervisor {
+    let mut supervisor = Supervisor::new();
+    supervisor.add("abakus", abakus::run);
+    supervisor.add("lunch", lunch::run);
+    supervisor.add("reminders", reminders::run);
+    supervisor.add("yr", yr::run);
+    supervisor
+}
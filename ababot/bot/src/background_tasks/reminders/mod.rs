@@ -0,0 +1,4 @@
+mod queue;
+mod reminders_task;
+pub use queue::Reminders;
+pub use reminders_task::run;
@@ -0,0 +1,203 @@
+use std::{
+    collections::HashSet,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use chrono::{DateTime, Utc};
+use serenity::{
+    model::prelude::UserId,
+    prelude::{Context, TypeMapKey},
+};
+use tokio::sync::Notify;
+
+use crate::storage::{reminders::Reminder, Storage, StorageError};
+
+/// The stored reminders, sent by the reminders background task when they are due.
+/// Changes go through here so the task knows to look again
+pub struct Reminders {
+    storage: Storage,
+    changed: Notify,
+    /// One-off reminders [sent](Reminders::sent) but not yet delivered, that may be restored
+    sending: Mutex<HashSet<i64>>,
+}
+
+impl TypeMapKey for Reminders {
+    type Value = Arc<Reminders>;
+}
+
+impl Reminders {
+    pub fn new(storage: Storage) -> Self {
+        Reminders {
+            storage,
+            changed: Notify::new(),
+            sending: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// Store a new reminder, returning it with its id
+    pub async fn add(&self, reminder: Reminder) -> Result<Reminder, StorageError> {
+        let reminder = self.storage.add_reminder(reminder).await?;
+        self.changed.notify_one();
+        Ok(reminder)
+    }
+
+    pub async fn find(&self, id: i64) -> Result<Option<Reminder>, StorageError> {
+        self.storage.reminder(id).await
+    }
+
+    /// The reminders a user is waiting for, the earliest first
+    pub async fn of_user(&self, user_id: UserId) -> Result<Vec<Reminder>, StorageError> {
+        self.storage.user_reminders(user_id).await
+    }
+
+    /// Replace a stored reminder. Returns `false` if it has already been sent or cancelled
+    pub async fn update(&self, reminder: Reminder) -> Result<bool, StorageError> {
+        let updated = self.storage.update_reminder(reminder).await?;
+        self.changed.notify_one();
+        Ok(updated)
+    }
+
+    /// Returns `false` if the reminder has already been sent or cancelled. One that is being
+    /// sent is not put back if sending it fails
+    pub async fn cancel(&self, id: i64) -> Result<bool, StorageError> {
+        let cancelled = self.storage.delete_reminder(id).await?;
+        if !cancelled {
+            self.sending().remove(&id);
+        }
+        self.changed.notify_one();
+        Ok(cancelled)
+    }
+
+    pub(super) async fn due(&self, now: DateTime<Utc>) -> Result<Vec<Reminder>, StorageError> {
+        self.storage.due_reminders(now).await
+    }
+
+    pub(super) async fn next_due(&self) -> Result<Option<DateTime<Utc>>, StorageError> {
+        self.storage.next_reminder_due().await
+    }
+
+    /// Forget a reminder as it is sent, or move a recurring one to its next occurrence
+    pub(super) async fn sent(
+        &self,
+        reminder: &Reminder,
+        now: DateTime<Utc>,
+    ) -> Result<(), StorageError> {
+        match reminder.next_after(now) {
+            Some(next) => self.storage.update_reminder(next).await.map(|_| ()),
+            None => {
+                self.sending().insert(reminder.id);
+                let deleted = self.storage.delete_reminder(reminder.id).await;
+                if deleted.is_err() {
+                    self.delivered(reminder);
+                }
+                deleted.map(|_| ())
+            }
+        }
+    }
+
+    /// Done with a reminder that was [sent](Reminders::sent), it will not be restored
+    pub(super) fn delivered(&self, reminder: &Reminder) {
+        self.sending().remove(&reminder.id);
+    }
+
+    /// Put a reminder back as it was before it was [sent](Reminders::sent) at `now`, to try it
+    /// again. Left alone if it was cancelled or edited in the meantime
+    pub(super) async fn restore(
+        &self,
+        reminder: &Reminder,
+        now: DateTime<Utc>,
+    ) -> Result<(), StorageError> {
+        match reminder.next_after(now) {
+            Some(next) => self
+                .storage
+                .roll_back_reminder(reminder.clone(), next)
+                .await
+                .map(|_| ()),
+            None if self.sending().remove(&reminder.id) => self
+                .storage
+                .restore_reminder(reminder.clone())
+                .await
+                .map(|_| ()),
+            None => Ok(()),
+        }
+    }
+
+    /// Resolves once a reminder has been added or changed since the last call
+    pub(super) async fn changed(&self) {
+        self.changed.notified().await
+    }
+
+    fn sending(&self) -> MutexGuard<'_, HashSet<i64>> {
+        self.sending.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Get the reminders from the context
+    /// # Panics
+    /// Panics if they have not been inserted at startup
+    pub async fn get(ctx: &Context) -> Arc<Reminders> {
+        ctx.data
+            .read()
+            .await
+            .get::<Reminders>()
+            .expect("Reminders not found in data")
+            .clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use serenity::model::prelude::{ChannelId, UserId};
+
+    use super::Reminders;
+    use crate::storage::{
+        reminders::{Mentions, Reminder, Repeat},
+        Storage,
+    };
+
+    #[tokio::test]
+    async fn failed_reminders_are_restored_unless_changed_meanwhile() {
+        let reminders = Reminders::new(Storage::in_memory().unwrap());
+        let at = |hour| Utc.with_ymd_and_hms(2024, 3, 27, hour, 0, 0).unwrap();
+        let once = |message: &str| Reminder {
+            id: 0,
+            user_id: UserId(1),
+            channel_id: ChannelId(2),
+            message: message.to_string(),
+            due: at(8),
+            public: false,
+            repeat: None,
+            mentions: Mentions::default(),
+        };
+        let daily = |message: &str| Reminder {
+            repeat: Some(Repeat {
+                recurrence: "every day at 08:00".parse().unwrap(),
+                until: None,
+                remaining: None,
+                timezone: chrono_tz::UTC,
+            }),
+            ..once(message)
+        };
+        let kept = reminders.add(once("kept")).await.unwrap();
+        let cancelled = reminders.add(once("cancelled")).await.unwrap();
+        let recurring = reminders.add(daily("recurring")).await.unwrap();
+        let edited = reminders.add(daily("edited")).await.unwrap();
+        for reminder in [&kept, &cancelled, &recurring, &edited] {
+            reminders.sent(reminder, at(9)).await.unwrap();
+        }
+
+        // Already being sent, the user is told it is gone
+        assert!(!reminders.cancel(cancelled.id).await.unwrap());
+        let mut later = reminders.find(edited.id).await.unwrap().unwrap();
+        later.message = "edited later".to_string();
+        assert!(reminders.update(later.clone()).await.unwrap());
+
+        for reminder in [&kept, &cancelled, &recurring, &edited] {
+            reminders.restore(reminder, at(9)).await.unwrap();
+        }
+        assert_eq!(reminders.find(kept.id).await.unwrap(), Some(kept));
+        assert_eq!(reminders.find(cancelled.id).await.unwrap(), None);
+        assert_eq!(reminders.find(recurring.id).await.unwrap(), Some(recurring));
+        assert_eq!(reminders.find(edited.id).await.unwrap(), Some(later));
+    }
+}
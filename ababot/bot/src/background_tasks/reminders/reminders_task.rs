@@ -0,0 +1,206 @@
+use std::{sync::Arc, time::Duration};
+
+use chrono::{DateTime, Utc};
+use serenity::prelude::Context;
+
+use crate::{
+    background_tasks::reminders::Reminders,
+    commands::remindme::reminder_buttons,
+    error::BotResult,
+    storage::reminders::Reminder,
+    utils::{clock::SharedClock, shutdown::Shutdown, supervisor::TaskReporter},
+};
+
+/// A reminder sent later than this after it was due says why, see [late_note]
+const LATE_AFTER: Duration = Duration::from_secs(60);
+/// Look at the stored reminders at least this often, in case the clock jumped
+const MAX_SLEEP: Duration = Duration::from_secs(60 * 60);
+/// Wait before trying again when the database could not be read
+const RETRY_AFTER: Duration = Duration::from_secs(60);
+/// How often a paused task looks for whether it has been resumed
+const PAUSED_SLEEP: Duration = Duration::from_secs(60);
+
+/// Send reminders as they are due. While paused nothing is sent unless a run is asked for,
+/// and resuming takes effect within [PAUSED_SLEEP]
+pub async fn run(ctx: Arc<Context>, task: TaskReporter) {
+    let reminders = Reminders::get(&ctx).await;
+    let clock = SharedClock::get(&ctx).await;
+    let started = clock.now();
+    let mut requested = false;
+    loop {
+        let sleep = if task.paused() && !requested {
+            task.next_run(None::<DateTime<Utc>>);
+            PAUSED_SLEEP
+        } else {
+            task.running();
+            let sent = send_due(&ctx, &reminders, &clock, started).await;
+            task.finished(&sent);
+            match sent {
+                Ok(()) => match reminders.next_due().await {
+                    Ok(next) => {
+                        task.next_run(next);
+                        next.map_or(MAX_SLEEP, |next| {
+                            (next - clock.now())
+                                .to_std()
+                                .unwrap_or_default()
+                                .min(MAX_SLEEP)
+                        })
+                    }
+                    Err(e) => {
+                        tracing::error!("Could not read the next reminder: {}", e);
+                        RETRY_AFTER
+                    }
+                },
+                Err(e) => {
+                    tracing::error!("Could not send due reminders: {}", e);
+                    RETRY_AFTER
+                }
+            }
+        };
+        requested = false;
+        tokio::select! {
+            _ = tokio::time::sleep(sleep) => {}
+            _ = reminders.changed() => {}
+            _ = task.run_requested() => requested = true,
+        }
+    }
+}
+
+/// Send every reminder that is due, forgetting it first so it is never sent twice. Reminders
+/// that failed for a reason that may pass, like Discord being down, are put back and the last
+/// such failure is returned, so they are tried again after [RETRY_AFTER]. Once shutdown has
+/// started the rest are left stored, and sent late after the restart
+async fn send_due(
+    ctx: &Context,
+    reminders: &Reminders,
+    clock: &SharedClock,
+    started: DateTime<Utc>,
+) -> BotResult {
+    let shutdown = Shutdown::get(ctx).await;
+    let now = clock.now();
+    let mut failed = None;
+    for reminder in reminders.due(now).await? {
+        let _in_flight = match shutdown.enter() {
+            Some(in_flight) => in_flight,
+            None => break,
+        };
+        reminders.sent(&reminder, now).await?;
+        match send(ctx, &reminder, started, now).await {
+            Ok(()) => reminders.delivered(&reminder),
+            // E.g. to a user not accepting direct messages, it would fail again on the next try
+            Err(e) if e.is_permanent() => {
+                tracing::warn!(
+                    user = %reminder.user_id,
+                    channel = %reminder.channel_id,
+                    "Dropping reminder {}, it can not be sent: {}",
+                    reminder.id,
+                    e
+                );
+                reminders.delivered(&reminder);
+            }
+            Err(e) => {
+                tracing::warn!(
+                    user = %reminder.user_id,
+                    channel = %reminder.channel_id,
+                    "Failed to send reminder {}, trying again later: {}",
+                    reminder.id,
+                    e
+                );
+                reminders.restore(&reminder, now).await?;
+                failed = Some(e);
+            }
+        }
+    }
+    failed.map_or(Ok(()), Err)
+}
+
+async fn send(
+    ctx: &Context,
+    reminder: &Reminder,
+    started: DateTime<Utc>,
+    now: DateTime<Utc>,
+) -> BotResult {
+    let channel_id = if reminder.public {
+        reminder.channel_id
+    } else {
+        reminder.user_id.create_dm_channel(&ctx.http).await?.id
+    };
+    channel_id
+        .send_message(&ctx.http, |m| {
+            m.embed(|e| {
+                e.title("Reminder")
+                    .description(reminder.message.as_str())
+                    .color(0x00ff00);
+                if let Some(note) = late_note(reminder.due, started, now) {
+                    e.field("Late", note, false);
+                }
+                e
+            })
+            .components(|c| reminder_buttons(c, reminder.user_id));
+            // Only the mentions the reminder was set with ping anyone
+            if !reminder.mentions.is_empty() {
+                m.content(reminder.mentions.to_string())
+                    .allowed_mentions(|a| {
+                        a.empty_parse()
+                            .roles(reminder.mentions.role)
+                            .users(reminder.mentions.users.iter().copied())
+                    });
+            }
+            m
+        })
+        .await?;
+    Ok(())
+}
+
+/// Explains why a reminder comes late, if it does. Reminders due before the task `started`
+/// were missed while the bot was down, later ones are late because sending them failed
+fn late_note(due: DateTime<Utc>, started: DateTime<Utc>, now: DateTime<Utc>) -> Option<String> {
+    let late = (now - due).to_std().ok()?;
+    if late <= LATE_AFTER {
+        return None;
+    }
+    let reason = if due < started {
+        "I was offline then"
+    } else {
+        "I could not send it then"
+    };
+    Some(format!(
+        "This was due <t:{}:f>, but {}",
+        due.timestamp(),
+        reason
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn only_missed_reminders_are_late() {
+        let due = Utc.with_ymd_and_hms(2023, 1, 2, 10, 0, 0).unwrap();
+        let started = due + chrono::Duration::hours(2);
+        let later = due + chrono::Duration::hours(3);
+        assert_eq!(late_note(due, started, due), None);
+        assert_eq!(
+            late_note(due, started, due + chrono::Duration::seconds(5)),
+            None
+        );
+        assert_eq!(
+            late_note(due, started, later),
+            Some("This was due <t:1672653600:f>, but I was offline then".to_string())
+        );
+
+        // Up since before it was due, so sending it failed
+        let before = due - chrono::Duration::hours(1);
+        assert_eq!(
+            late_note(due, before, due + chrono::Duration::seconds(5)),
+            None
+        );
+        assert_eq!(
+            late_note(due, before, later),
+            Some("This was due <t:1672653600:f>, but I could not send it then".to_string())
+        );
+    }
+}
@@ -1,6 +1,6 @@
 use std::error::Error;
 
-use chrono::{DateTime, Local, Timelike};
+use chrono::{DateTime, Duration, DurationRound};
 use chrono_tz::Europe::Oslo;
 use chrono_tz::Tz;
 use image::{
@@ -13,8 +13,24 @@ use super::types::Series;
 
 const IMGX: u32 = 1000;
 const IMGY: u32 = 1000;
+/// Hours of forecast in the image
+const HOURS: usize = 10;
 
-pub fn create_image(data: Vec<Series>) -> Result<RgbaImage, Box<dyn Error>> {
+/// The forecast for the hour `now` is in and the hours after it
+fn upcoming_hours(data: Vec<Series>, now: DateTime<Tz>) -> Vec<Series> {
+    let from = now.duration_trunc(Duration::hours(1)).unwrap_or(now);
+    data.into_iter()
+        .filter(|w| {
+            DateTime::parse_from_rfc3339(&w.time)
+                .map(|time| time >= from)
+                .unwrap_or(false)
+        })
+        .take(HOURS)
+        .collect()
+}
+
+/// Draw the forecast for the hours from `now`, which also dates the image
+pub fn create_image(data: Vec<Series>, now: DateTime<Tz>) -> Result<RgbaImage, Box<dyn Error>> {
     let mut imgbuf = image::ImageBuffer::new(IMGX, IMGY);
 
     for (x, y, pixel) in imgbuf.enumerate_pixels_mut() {
@@ -30,21 +46,10 @@ pub fn create_image(data: Vec<Series>) -> Result<RgbaImage, Box<dyn Error>> {
         }
     }
 
-    let hour_now = Local::now().hour();
-
-    let weather_series_filtered = data
-        .into_iter()
-        .filter(|w| {
-            let hour = DateTime::parse_from_rfc3339(&w.time)
-                .map_err(|e| e.to_string())
-                .unwrap()
-                .hour();
-            hour >= hour_now && hour < hour_now + 10
-        })
-        .collect::<Vec<_>>();
-
-    // Get first 10 hours
-    let weather_series = weather_series_filtered.chunks(10).next().unwrap();
+    let weather_series = upcoming_hours(data, now);
+    if weather_series.len() < HOURS {
+        return Err(format!("Only {} hours of forecast left", weather_series.len()).into());
+    }
 
     let hours: Vec<f32> = weather_series
         .iter()
@@ -77,7 +82,7 @@ pub fn create_image(data: Vec<Series>) -> Result<RgbaImage, Box<dyn Error>> {
     let time_image = add_times_and_temperature(hours, degrees);
     overlay(&mut imgbuf, &time_image, 0, 0);
 
-    let current_date = Local::now().format("%d.%m").to_string();
+    let current_date = now.format("%d.%m").to_string();
     let renderer = TextRenderer::default();
     let date_image =
         renderer.render_text_to_png_data(current_date, 128, Color::new(255, 255, 255))?;
@@ -164,3 +169,52 @@ fn add_times_and_temperature(times: Vec<DateTime<Tz>>, degrees: Vec<f32>) -> Rgb
     }
     imgbuf
 }
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn forecast(from: DateTime<Tz>, hours: i64) -> Vec<Series> {
+        (0..hours)
+            .map(|hour| Series {
+                time: (from + Duration::hours(hour))
+                    .with_timezone(&chrono::Utc)
+                    .to_rfc3339(),
+                ..Series::default()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn forecast_starts_at_the_current_hour() {
+        let midnight = Oslo.with_ymd_and_hms(2023, 6, 1, 0, 0, 0).unwrap();
+        let data = forecast(midnight, 48);
+        let now = Oslo.with_ymd_and_hms(2023, 6, 1, 22, 40, 0).unwrap();
+        let hours = upcoming_hours(data, now);
+        assert_eq!(hours.len(), HOURS);
+        // Runs past midnight, in UTC as yr sends it
+        assert_eq!(hours[0].time, "2023-06-01T20:00:00+00:00");
+        assert_eq!(hours[9].time, "2023-06-02T05:00:00+00:00");
+    }
+
+    #[test]
+    fn forecast_over_the_spring_change() {
+        let start = Oslo.with_ymd_and_hms(2023, 3, 25, 12, 0, 0).unwrap();
+        let now = Oslo.with_ymd_and_hms(2023, 3, 26, 1, 15, 0).unwrap();
+        let hours = upcoming_hours(forecast(start, 48), now);
+        let local: Vec<String> = hours
+            .iter()
+            .map(|w| {
+                DateTime::parse_from_rfc3339(&w.time)
+                    .unwrap()
+                    .with_timezone(&Oslo)
+                    .format("%H")
+                    .to_string()
+            })
+            .collect();
+        assert_eq!(local[..3], ["01", "03", "04"]);
+        assert!(upcoming_hours(forecast(start, 5), now).is_empty());
+    }
+}
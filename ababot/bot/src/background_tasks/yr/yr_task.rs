@@ -1,77 +1,77 @@
-use std::{sync::Arc, time::Duration};
+use std::sync::Arc;
 
 use chrono::DateTime;
 
 use serenity::prelude::Context;
 
-const START_TIME: (u8, u8, u8) = (7, 0, 0);
-const URL: &str =
-    "https://api.met.no/weatherapi/locationforecast/2.0/compact?lat=63.415398&lon=10.395053";
-
 use crate::{
     background_tasks::yr::{
         image::create_image,
         types::{Root, Series},
     },
-    utils::{
-        get_channel_id,
-        time::{schedule, Interval, Time, DAY_AS_SECONDS},
-    },
+    config::Config,
+    error::{BotError, BotResult},
+    monitoring::metrics::Metrics,
+    utils::{clock::SharedClock, opted_in_channels, supervisor::TaskReporter, time::Interval},
 };
-pub async fn run(ctx: Arc<Context>) {
-    let t = Time::new_unchecked(START_TIME.0, START_TIME.1, START_TIME.2).nearest_unchecked();
-    schedule(
-        Interval::EveryDeltaStartAt(Duration::from_secs(DAY_AS_SECONDS), t),
-        || async { execute(ctx.clone()).await },
-    )
-    .await;
+pub async fn run(ctx: Arc<Context>, task: TaskReporter) {
+    let config = Config::get(&ctx).await;
+    let interval = Interval::Calendar(config.yr.schedule, config.yr.days_off);
+    task.schedule(interval, || async { execute(ctx.clone()).await })
+        .await;
 }
 
-async fn execute(ctx: Arc<Context>) {
+async fn execute(ctx: Arc<Context>) -> BotResult {
     tracing::info!("Fetching weather data from yr.no");
-    let channel_id = match get_channel_id("weather", &ctx.http).await {
-        Ok(id) => id,
-        Err(e) => {
-            tracing::error!("Failed to get channel id: {}", e);
-            return;
-        }
-    };
+    let config = Config::get(&ctx).await;
+    let channels = opted_in_channels(&config.channels.weather, &ctx).await;
+    if channels.is_empty() {
+        tracing::debug!("No guild has opted in to the weather report");
+        return Ok(());
+    }
 
-    let weather_serie = match fetch_today_weather().await {
+    let weather_serie = match fetch_today_weather(&config.yr.forecast_url()).await {
         Ok(w) => w,
         Err(e) => {
-            tracing::warn!("Could not fetch weather. Reason: {}", e);
-            return;
+            Metrics::get(&ctx).await.fetch_failed("yr");
+            return Err(BotError::internal(format!(
+                "Could not fetch weather: {}",
+                e
+            )));
         }
     };
 
-    let image_message = match create_image(weather_serie) {
+    let now = SharedClock::get(&ctx).await.now_in_oslo();
+    let image_message = match create_image(weather_serie, now) {
         Ok(i) => i,
         Err(e) => {
-            tracing::warn!("Could not create image. Reason: {}", e);
-            return;
+            return Err(BotError::internal(format!("Could not create image: {}", e)));
         }
     };
 
-    image_message.save("weather.png").unwrap();
-
     let image_path = std::path::Path::new("weather.png");
+    if let Err(e) = image_message.save(image_path) {
+        return Err(BotError::internal(format!("Could not save image: {}", e)));
+    }
 
-    if let Err(e) = channel_id
-        .send_files(&ctx.http, vec![image_path], |m| m)
-        .await
-    {
-        tracing::warn!("Could not send image. Reason: {}", e);
+    for channel_id in channels {
+        if let Err(e) = channel_id
+            .send_files(&ctx.http, vec![image_path], |m| m)
+            .await
+        {
+            tracing::warn!("Could not send image to {}. Reason: {}", channel_id, e);
+        }
     }
 
     // Delete the image after it has been sent
-    std::fs::remove_file(image_path).unwrap();
+    std::fs::remove_file(image_path)?;
+    Ok(())
 }
 
-async fn fetch_today_weather() -> Result<Vec<Series>, String> {
+async fn fetch_today_weather(url: &str) -> Result<Vec<Series>, String> {
     let client = reqwest::Client::new();
     let response = client
-        .get(URL)
+        .get(url)
         .header("Accept", "application/json")
         .header("User-Agent", "DiscordBot")
         .send()
@@ -91,11 +91,13 @@ fn parse_weather(response: String) -> Result<Vec<Series>, String> {
 }
 
 fn get_latest_weather(weather: &mut [Series]) -> Result<Vec<Series>, String> {
-    weather.sort_by(|a, b| {
-        DateTime::parse_from_rfc3339(&a.time)
-            .unwrap()
-            .cmp(&DateTime::parse_from_rfc3339(&b.time).unwrap())
-    });
+    if let Some(series) = weather
+        .iter()
+        .find(|series| DateTime::parse_from_rfc3339(&series.time).is_err())
+    {
+        return Err(format!("Invalid time in forecast: {}", series.time));
+    }
+    weather.sort_by_cached_key(|series| DateTime::parse_from_rfc3339(&series.time).ok());
     Ok(weather.to_vec())
 }
 
@@ -116,3 +118,25 @@ fn degrees_to_cardinal(degrees: f64) -> String {
     let index = ((degrees + 22.5) / 45.0) as usize;
     directions[index].to_string()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn forecasts_are_sorted_or_rejected() {
+        let series = |time: &str| Series {
+            time: time.to_string(),
+            ..Series::default()
+        };
+        let mut weather = vec![
+            series("2023-06-01T10:00:00Z"),
+            series("2023-06-01T09:00:00Z"),
+        ];
+        let sorted = get_latest_weather(&mut weather).unwrap();
+        assert_eq!(sorted[0].time, "2023-06-01T09:00:00Z");
+
+        assert!(get_latest_weather(&mut [series("soon")]).is_err());
+        assert!(parse_weather("<html>502 Bad Gateway</html>".to_string()).is_err());
+    }
+}
@@ -0,0 +1,198 @@
+use chrono::{DateTime, Utc};
+use serenity::{
+    async_trait,
+    builder::{CreateApplicationCommand, CreateEmbed},
+    model::prelude::interaction::{
+        application_command::ApplicationCommandInteraction, autocomplete::AutocompleteInteraction,
+        InteractionResponseType,
+    },
+    prelude::Context,
+};
+
+use crate::config::Config;
+use crate::error::{BotError, BotResult};
+use crate::options::CommandOptions;
+use crate::registry::{focused_option, suggest, Command};
+use crate::utils::supervisor::{Supervisor, TaskState, TaskStatus};
+
+pub struct AdminCommand;
+
+#[derive(CommandOptions)]
+enum AdminOptions {
+    /// Inspect and control the background jobs
+    Jobs(JobsOptions),
+}
+
+#[derive(CommandOptions)]
+enum JobsOptions {
+    /// List the jobs with their schedule and last result
+    List,
+    /// Run a job now, even if it is paused
+    Run(JobOption),
+    /// Skip the scheduled runs of a job until it is resumed
+    Pause(JobOption),
+    /// Resume a paused job
+    Resume(JobOption),
+}
+
+#[derive(CommandOptions)]
+struct JobOption {
+    /// Name of the job
+    #[option(autocomplete)]
+    job: String,
+}
+
+#[async_trait]
+impl Command for AdminCommand {
+    fn name(&self) -> &'static str {
+        "admin"
+    }
+
+    fn register<'a>(
+        &self,
+        command: &'a mut CreateApplicationCommand,
+    ) -> &'a mut CreateApplicationCommand {
+        register(command)
+    }
+
+    async fn run(&self, ctx: &Context, command: &ApplicationCommandInteraction) -> BotResult {
+        run(ctx, command).await
+    }
+
+    async fn autocomplete(&self, ctx: &Context, interaction: &AutocompleteInteraction) {
+        let typed = focused_option(&interaction.data.options)
+            .and_then(|o| o.value.as_ref())
+            .and_then(|v| v.as_str())
+            .unwrap_or_default()
+            .to_lowercase();
+        let names = Supervisor::get(ctx)
+            .await
+            .statuses()
+            .into_iter()
+            .map(|s| s.name)
+            .filter(|name| name.contains(&typed))
+            .map(|name| (name.to_string(), name.to_string()));
+        suggest(ctx, interaction, names).await;
+    }
+}
+
+pub async fn run(ctx: &Context, command: &ApplicationCommandInteraction) -> BotResult {
+    // The command may still be registered somewhere it no longer should be, so check both
+    let config = Config::get(ctx).await;
+    if !config.command_enabled(command.guild_id, "admin")
+        || !config.admin.users.contains(&command.user.id)
+    {
+        return Err(BotError::user(
+            "Only the owners of the bot can use this command",
+        ));
+    }
+
+    let supervisor = Supervisor::get(ctx).await;
+    let AdminOptions::Jobs(options) = AdminOptions::parse(command)?;
+    let (content, statuses) = match options {
+        JobsOptions::List => (String::new(), supervisor.statuses()),
+        JobsOptions::Run(JobOption { job }) => {
+            let status = supervisor.run_now(&job).ok_or_else(|| unknown(&job))?;
+            let content = match status.state {
+                TaskState::Idle => format!("Running {job} now"),
+                TaskState::Running => {
+                    format!("{job} is running, it will run again once it is done")
+                }
+                state => format!("{job} is {}, it will run once it is up", describe(state)),
+            };
+            (content, vec![status])
+        }
+        JobsOptions::Pause(JobOption { job }) => {
+            let status = supervisor
+                .set_paused(&job, true)
+                .ok_or_else(|| unknown(&job))?;
+            (format!("Paused {job}"), vec![status])
+        }
+        JobsOptions::Resume(JobOption { job }) => {
+            let status = supervisor
+                .set_paused(&job, false)
+                .ok_or_else(|| unknown(&job))?;
+            (format!("Resumed {job}"), vec![status])
+        }
+    };
+
+    command
+        .create_interaction_response(&ctx.http, |response| {
+            response
+                .kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|m| {
+                    m.content(content)
+                        .add_embed(jobs_embed(&statuses))
+                        .ephemeral(true)
+                })
+        })
+        .await?;
+    Ok(())
+}
+
+fn unknown(job: &str) -> BotError {
+    BotError::user(format!("There is no job called {job}"))
+}
+
+fn describe(state: TaskState) -> &'static str {
+    match state {
+        TaskState::Pending => "waiting for the bot to connect",
+        TaskState::Idle => "waiting for its next run",
+        TaskState::Running => "running",
+        TaskState::Restarting => "restarting after a crash",
+        TaskState::Stopped => "stopped",
+    }
+}
+
+/// A Discord timestamp, shown in the local time of whoever reads it
+fn timestamp(time: Option<DateTime<Utc>>) -> String {
+    match time {
+        Some(time) => format!("<t:{}:f>", time.timestamp()),
+        None => "never".to_string(),
+    }
+}
+
+fn jobs_embed(statuses: &[TaskStatus]) -> CreateEmbed {
+    let mut embed = CreateEmbed::default();
+    embed.title("Background jobs");
+    for status in statuses {
+        let mut state = describe(status.state).to_string();
+        if status.paused {
+            state.push_str(", paused");
+        }
+        let mut details = format!(
+            "State: {}\nSchedule: {}\nLast run: {}{}\nNext run: {}",
+            state,
+            status.schedule.as_deref().unwrap_or("not scheduled yet"),
+            timestamp(status.last_run),
+            match (&status.last_run, &status.last_failure) {
+                (_, Some(failure)) => format!(", failed: {}", failure),
+                (Some(_), None) => ", succeeded".to_string(),
+                (None, None) => String::new(),
+            },
+            if status.paused {
+                "when resumed".to_string()
+            } else {
+                timestamp(status.next_run)
+            },
+        );
+        if status.restarts > 0 {
+            details.push_str(&format!("\nRestarts: {}", status.restarts));
+        }
+        if let Some(error) = &status.last_error {
+            details.push_str(&format!("\nLast crash: {}", error));
+        }
+        embed.field(status.name, details, false);
+    }
+    embed
+}
+
+pub fn register(command: &mut CreateApplicationCommand) -> &mut CreateApplicationCommand {
+    tracing::debug!("Registering command admin");
+    AdminOptions::register(
+        command
+            .name("admin")
+            .description("Tools for the owners of the bot")
+            .dm_permission(false),
+    )
+}
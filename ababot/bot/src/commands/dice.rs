@@ -1,39 +1,59 @@
 use std::mem::swap;
 
 use serenity::{
+    async_trait,
     builder::CreateApplicationCommand,
-    model::prelude::{
-        command::CommandOptionType,
-        interaction::{
-            application_command::ApplicationCommandInteraction, InteractionResponseType,
-        },
+    model::prelude::interaction::{
+        application_command::ApplicationCommandInteraction, InteractionResponseType,
     },
     prelude::Context,
 };
 
+use crate::error::BotResult;
+use crate::options::CommandOptions;
+use crate::registry::Command;
+
 #[cfg(feature = "dice")]
 use rand::{thread_rng, Rng};
 use tracing::instrument;
 
+pub struct DiceCommand;
+
+#[derive(CommandOptions)]
+struct DiceOptions {
+    /// The minimum value for the number generator
+    #[option(min = 0, max = 99)]
+    min: Option<i64>,
+    /// The maximum value for the number generator
+    #[option(min = 1, max = 100)]
+    max: Option<i64>,
+}
+
+#[async_trait]
+impl Command for DiceCommand {
+    fn name(&self) -> &'static str {
+        "dice"
+    }
+
+    fn register<'a>(
+        &self,
+        command: &'a mut CreateApplicationCommand,
+    ) -> &'a mut CreateApplicationCommand {
+        register(command)
+    }
+
+    async fn run(&self, ctx: &Context, command: &ApplicationCommandInteraction) -> BotResult {
+        run(ctx, command).await
+    }
+}
+
 #[instrument(skip(ctx, command))]
-pub async fn run(ctx: &Context, command: &ApplicationCommandInteraction) {
+pub async fn run(ctx: &Context, command: &ApplicationCommandInteraction) -> BotResult {
     #[cfg(feature = "dice")]
     {
-        let mut min = 0;
-        let mut max = 100;
-
-        for option in &command.data.options {
-            if option.name == "min" {
-                min = option.value.as_ref().and_then(|v| v.as_i64()).unwrap_or(0);
-            }
-            if option.name == "max" {
-                max = option
-                    .value
-                    .as_ref()
-                    .and_then(|v| v.as_i64())
-                    .unwrap_or(100);
-            }
-        }
+        let options = DiceOptions::parse(command)?;
+        let mut min = options.min.unwrap_or(0);
+        let mut max = options.max.unwrap_or(100);
         if max < min {
             swap(&mut max, &mut min);
         }
@@ -43,21 +63,19 @@ pub async fn run(ctx: &Context, command: &ApplicationCommandInteraction) {
             let mut rng = thread_rng();
             rng.gen_range(min..max).to_string()
         };
-        if let Err(why) = command
+        command
             .create_interaction_response(&ctx.http, |response| {
                 response
                     .kind(InteractionResponseType::ChannelMessageWithSource)
                     .interaction_response_data(|message| message.content(dice_roll))
             })
-            .await
-        {
-            tracing::warn!("Failed to run command: {}", why);
-        }
+            .await?;
+        Ok(())
     }
     // Feature noe deprecated, fix before merge
     #[cfg(not(feature = "dice"))]
     {
-        "Command disabled".to_string()
+        Err(crate::error::BotError::user("Command disabled"))
     }
 }
 
@@ -65,27 +83,7 @@ pub fn register(command: &mut CreateApplicationCommand) -> &mut CreateApplicatio
     #[cfg(feature = "dice")]
     {
         tracing::debug!("Registering command dice");
-        command
-            .name("dice")
-            .description("Get a random number")
-            .create_option(|option| {
-                option
-                    .name("min")
-                    .description("The minimum value for the number generator")
-                    .kind(CommandOptionType::Integer)
-                    .min_int_value(0)
-                    .max_int_value(99)
-                    .required(false)
-            })
-            .create_option(|option| {
-                option
-                    .name("max")
-                    .description("The maximum value for the number generator")
-                    .kind(CommandOptionType::Integer)
-                    .min_int_value(1)
-                    .max_int_value(100)
-                    .required(false)
-            })
+        DiceOptions::register(command.name("dice").description("Get a random number"))
     }
     #[cfg(not(feature = "dice"))]
     {
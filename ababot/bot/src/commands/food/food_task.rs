@@ -1,21 +1,83 @@
 use serenity::{
+    async_trait,
     builder::CreateApplicationCommand,
     model::prelude::{
         component::InputTextStyle,
         interaction::{
-            application_command::ApplicationCommandInteraction, InteractionResponseType,
+            application_command::ApplicationCommandInteraction,
+            message_component::MessageComponentInteraction, modal::ModalSubmitInteraction,
+            InteractionResponseType,
         },
     },
     prelude::Context,
 };
 
-pub async fn run(ctx: &Context, command: &ApplicationCommandInteraction) {
-    if let Err(why) = command
+use crate::error::BotResult;
+use crate::registry::{custom_id, Command, CustomId};
+
+use super::{
+    modal_handler::{handle_modal, handle_recipe_select},
+    recipe_response,
+};
+
+pub struct FoodCommand;
+
+#[async_trait]
+impl Command for FoodCommand {
+    fn name(&self) -> &'static str {
+        "food"
+    }
+
+    fn register<'a>(
+        &self,
+        command: &'a mut CreateApplicationCommand,
+    ) -> &'a mut CreateApplicationCommand {
+        register(command)
+    }
+
+    async fn run(&self, ctx: &Context, command: &ApplicationCommandInteraction) -> BotResult {
+        run(ctx, command).await
+    }
+
+    async fn component(
+        &self,
+        ctx: &Context,
+        interaction: &MessageComponentInteraction,
+        id: CustomId<'_>,
+    ) -> BotResult {
+        match id.action {
+            "recipe" => handle_recipe_select(ctx, interaction).await,
+            "publish" => recipe_response::publish(ctx, interaction).await,
+            _ => {
+                tracing::debug!("Component {} not handled", interaction.data.custom_id);
+                Ok(())
+            }
+        }
+    }
+
+    async fn modal(
+        &self,
+        ctx: &Context,
+        submit: &ModalSubmitInteraction,
+        id: CustomId<'_>,
+    ) -> BotResult {
+        match id.action {
+            "search" => handle_modal(ctx, submit).await,
+            _ => {
+                tracing::debug!("Modal {} not handled", submit.data.custom_id);
+                Ok(())
+            }
+        }
+    }
+}
+
+pub async fn run(ctx: &Context, command: &ApplicationCommandInteraction) -> BotResult {
+    command
         .create_interaction_response(&ctx.http, |m| {
             m.kind(InteractionResponseType::Modal)
                 .interaction_response_data(|d| {
                     d.content("Food")
-                        .custom_id("food")
+                        .custom_id(custom_id("food", "search", &[]))
                         .title("Enter ingredients. På norsk :D")
                         .components(|c| {
                             c.create_action_row(|row| {
@@ -66,10 +128,8 @@ pub async fn run(ctx: &Context, command: &ApplicationCommandInteraction) {
                         })
                 })
         })
-        .await
-    {
-        tracing::warn!("Error sending modal: {}", why);
-    }
+        .await?;
+    Ok(())
 }
 
 pub fn register(command: &mut CreateApplicationCommand) -> &mut CreateApplicationCommand {
@@ -1,5 +1,6 @@
 mod food_task;
 pub use food_task::register;
 pub use food_task::run;
+pub use food_task::FoodCommand;
 pub mod modal_handler;
 mod recipe_response;
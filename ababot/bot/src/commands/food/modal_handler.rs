@@ -1,33 +1,38 @@
-use chrono::Duration;
 use rand::seq::SliceRandom;
 use reqwest::Client;
 use scraper::{Html, Selector};
 use serenity::{
     model::prelude::{
         component::ActionRowComponent,
-        interaction::{modal::ModalSubmitInteraction, InteractionResponseType},
+        interaction::{
+            message_component::MessageComponentInteraction, modal::ModalSubmitInteraction,
+            InteractionResponseType,
+        },
     },
     prelude::Context,
 };
 
-use crate::commands::food::recipe_response::create_recipe_post;
+use crate::{
+    commands::food::recipe_response::create_recipe_post,
+    error::{BotError, BotResult},
+    registry::custom_id,
+};
 
 const BASE_URL: &str = "https://www.matoppskrift.no/sider/sokemaskin.asp?valg=kjoleskap&type1=1";
+// Discord limit on the value of a select menu option
+const MAX_OPTION_VALUE_LENGTH: usize = 100;
 #[derive(Clone)]
 struct Food {
     name: String,
     url: String,
 }
 
-pub async fn handle_modal(ctx: &Context, command: &ModalSubmitInteraction) {
-    if let Err(why) = command
+pub async fn handle_modal(ctx: &Context, command: &ModalSubmitInteraction) -> BotResult {
+    command
         .create_interaction_response(&ctx.http, |m| {
             m.kind(InteractionResponseType::DeferredUpdateMessage)
         })
-        .await
-    {
-        tracing::warn!("Error responding to modal: {:?}", why);
-    }
+        .await?;
 
     let user_submitted_ingredients = &command
         .data
@@ -51,118 +56,98 @@ pub async fn handle_modal(ctx: &Context, command: &ModalSubmitInteraction) {
         }
     }
 
-    let mut recipies = match get_recipes(&url).await {
-        Ok(recipies) => recipies,
-        Err(e) => {
-            error(ctx, command, e).await;
-            return;
-        }
-    };
+    let mut recipies = get_recipes(&url).await?;
 
-    create_response(ctx, command, &mut recipies).await;
+    create_response(ctx, command, &mut recipies).await
 }
 
 async fn create_response(
     ctx: &Context,
     command: &ModalSubmitInteraction,
     recipies: &mut Vec<Food>,
-) {
+) -> BotResult {
     recipies.shuffle(&mut rand::thread_rng());
-    let recipe_sublist = if let Some(recipie) = recipies.chunks(5).next() {
-        recipie.to_vec()
-    } else {
-        error(ctx, command, "No recipies found".to_string()).await;
-        return;
-    };
+    // The url is stored as the option value so the selection can be handled without any state
+    let recipe_sublist = recipies
+        .iter()
+        .filter(|recipe| recipe.url.len() <= MAX_OPTION_VALUE_LENGTH)
+        .take(5)
+        .collect::<Vec<_>>();
+    if recipe_sublist.is_empty() {
+        return Err(BotError::user("No recipies found"));
+    }
 
-    let channel_message =
-        match command
-            .create_followup_message(&ctx.http, |m| {
-                m.content(
+    command
+        .create_followup_message(&ctx.http, |m| {
+            m.content(
                 "Here are some recipies you can make with the ingredients you have in your fridge",
             )
-            .components(|c| c.create_action_row(|row| {
-                row.create_select_menu(|menu|{
-                    menu.custom_id("food_recipie")
-                    .placeholder("Select a recipie");
-                    menu.options(|opt|{
-                        recipe_sublist.iter().fold(opt, |opt, reci| {
-                            opt.create_option(|o| {
-                                o.label(&reci.name)
-                                .value(&reci.name)
-                                .description("Click to see the recipie")
+            .components(|c| {
+                c.create_action_row(|row| {
+                    row.create_select_menu(|menu| {
+                        menu.custom_id(custom_id("food", "recipe", &[]))
+                            .placeholder("Select a recipie");
+                        menu.options(|opt| {
+                            recipe_sublist.iter().fold(opt, |opt, reci| {
+                                opt.create_option(|o| {
+                                    o.label(&reci.name)
+                                        .value(&reci.url)
+                                        .description("Click to see the recipie")
+                                })
                             })
                         })
                     })
                 })
-            })).ephemeral(true)
             })
-            .await
-        {
-            Ok(message) => message,
-            Err(why) => {
-                error(ctx, command, why.to_string()).await;
-                return;
-            }
-        };
+            .ephemeral(true)
+        })
+        .await?;
+    Ok(())
+}
 
-    let listener = channel_message
-        .await_component_interaction(ctx)
-        .timeout(Duration::minutes(2).to_std().unwrap())
-        .await;
+/// Show the recipe picked from the list sent by [create_response]
+pub async fn handle_recipe_select(
+    ctx: &Context,
+    interaction: &MessageComponentInteraction,
+) -> BotResult {
+    interaction
+        .create_interaction_response(&ctx.http, |m| {
+            m.kind(InteractionResponseType::DeferredUpdateMessage)
+        })
+        .await?;
 
-    let (selected_recipe, interaction) = if let Some(interaction) = listener {
-        if let Err(why) = interaction
-            .create_interaction_response(&ctx.http, |m| {
-                m.kind(InteractionResponseType::DeferredUpdateMessage)
-            })
-            .await
-        {
-            tracing::warn!("Error responding to modal: {:?}", why);
+    match interaction.data.values.get(0) {
+        Some(url) => create_recipe_post(ctx, interaction, url.clone()).await,
+        None => {
+            tracing::debug!("No recipie selected");
+            Ok(())
         }
-
-        (
-            interaction.data.values.get(0).unwrap().as_str().to_string(),
-            interaction,
-        )
-    } else {
-        error(ctx, command, "No recipie selected".to_string()).await;
-        return;
-    };
-
-    let selected_recipe = if let Some(recipe) = recipe_sublist
-        .into_iter()
-        .find(|reci| reci.name == selected_recipe)
-    {
-        recipe
-    } else {
-        error(ctx, command, "No recipie selected".to_string()).await;
-        return;
-    };
-
-    create_recipe_post(ctx, interaction, selected_recipe.url).await;
+    }
 }
 
-async fn get_recipes(url: &str) -> Result<Vec<Food>, String> {
+async fn get_recipes(url: &str) -> BotResult<Vec<Food>> {
     let client = Client::new();
-    let res = client.get(url).send().await.map_err(|e| e.to_string())?;
-    let document = if res.status().is_success() {
-        let body = res.text().await.map_err(|e| e.to_string())?;
-        Html::parse_document(&body)
-    } else {
-        return Err(format!("Request failed with status code: {}", res.status()));
-    };
-    let primary_selector = Selector::parse("table.display").map_err(|e| e.to_string())?;
-    let tr_selector = Selector::parse("a.resultat_sokemaskin").map_err(|e| e.to_string())?;
+    let res = client.get(url).send().await?.error_for_status()?;
+    let document = Html::parse_document(&res.text().await?);
+    let primary_selector =
+        Selector::parse("table.display").map_err(|e| BotError::internal(e.to_string()))?;
+    let tr_selector =
+        Selector::parse("a.resultat_sokemaskin").map_err(|e| BotError::internal(e.to_string()))?;
     let elements = document
         .select(&primary_selector)
         .next()
-        .ok_or("Fant ingen oppskrift")?;
+        .ok_or_else(|| BotError::user("Fant ingen oppskrift"))?;
 
     let mut recipes = Vec::new();
     for element in elements.select(&tr_selector) {
-        let name = element.value().attr("title").ok_or("No title found")?;
-        let url = element.value().attr("href").ok_or("No href found")?;
+        let name = element
+            .value()
+            .attr("title")
+            .ok_or_else(|| BotError::internal("No title found"))?;
+        let url = element
+            .value()
+            .attr("href")
+            .ok_or_else(|| BotError::internal("No href found"))?;
         recipes.push(Food {
             name: name.to_string(),
             url: url.to_string(),
@@ -170,12 +155,3 @@ async fn get_recipes(url: &str) -> Result<Vec<Food>, String> {
     }
     Ok(recipes)
 }
-
-async fn error(ctx: &Context, command: &ModalSubmitInteraction, error: String) {
-    if let Err(why) = command
-        .create_followup_message(&ctx.http, |m| m.content(format!("Error: {}", error)))
-        .await
-    {
-        tracing::warn!("Error sending error message: {:?}", why);
-    }
-}
@@ -1,7 +1,6 @@
-use std::sync::Arc;
-
 use scraper::{Html, Selector};
 use serenity::{
+    builder::CreateEmbed,
     model::prelude::{
         component::ButtonStyle,
         interaction::{message_component::MessageComponentInteraction, InteractionResponseType},
@@ -9,6 +8,11 @@ use serenity::{
     prelude::Context,
 };
 
+use crate::{
+    error::{BotError, BotResult},
+    registry::custom_id,
+};
+
 struct Ingredient {
     name: String,
     amount: String,
@@ -17,53 +21,22 @@ struct Ingredient {
 
 pub async fn create_recipe_post(
     ctx: &Context,
-    command: Arc<MessageComponentInteraction>,
+    command: &MessageComponentInteraction,
     url: String,
-) {
+) -> BotResult {
     let client = reqwest::Client::new();
-    let response = if let Ok(response) = client.get(&url).send().await {
-        response
-    } else {
-        tracing::error!("Error fetching recipe");
-        error(ctx, command, "Error fetching recipe".to_string()).await;
-        return;
-    };
+    let body = client.get(&url).send().await?.text().await?;
 
-    let body = if let Ok(body) = response.text().await {
-        body
-    } else {
-        tracing::warn!("Error parsing recipe");
-        error(ctx, command, "Error parsing recipe".to_string()).await;
-        return;
-    };
+    let ingredients = get_ingredients(&body).map_err(BotError::internal)?;
+    let steps = get_steps(&body).map_err(BotError::internal)?;
+    let name = get_name(&body).map_err(BotError::internal)?;
 
-    let ingredients = match get_ingredients(&body) {
-        Ok(ingredients) => ingredients,
-        Err(e) => {
-            error(ctx, command, e).await;
-            return;
-        }
-    };
-    let steps = match get_steps(&body) {
-        Ok(steps) => steps,
-        Err(e) => {
-            error(ctx, command, e).await;
-            return;
-        }
-    };
-    let name = match get_name(&body) {
-        Ok(name) => name,
-        Err(e) => {
-            error(ctx, command, e).await;
-            return;
-        }
-    };
-    let channel_message = match command
+    command
         .edit_original_interaction_response(&ctx.http, |m| {
             m.content("\u{AD}")
                 .embed(|e| {
-                    e.title(name.clone())
-                        .field("Fremgangsmåte", steps.clone(), false)
+                    e.title(&name)
+                        .field("Fremgangsmåte", &steps, false)
                         .field("Ingredienser", "\u{AD}", false) // Invisible character in value field to make discord happy
                         .fields(ingredients.iter().map(|i| {
                             (
@@ -78,62 +51,49 @@ pub async fn create_recipe_post(
                         row.create_button(|b| {
                             b.label("Publiser")
                                 .style(ButtonStyle::Success)
-                                .custom_id("publish")
+                                .custom_id(custom_id("food", "publish", &[]))
                         })
                     })
                 })
         })
+        .await?;
+    Ok(())
+}
+
+/// Post the recipe shown in the private message the "Publiser" button is attached to.
+/// The recipe is read back from the message itself, so this works long after it was sent.
+pub async fn publish(ctx: &Context, interaction: &MessageComponentInteraction) -> BotResult {
+    interaction
+        .create_interaction_response(&ctx.http, |r| {
+            r.kind(InteractionResponseType::DeferredUpdateMessage)
+        })
+        .await?;
+    if let Err(why) = interaction
+        .delete_original_interaction_response(&ctx.http)
         .await
     {
-        Ok(channel_message) => channel_message,
-        Err(why) => {
-            tracing::warn!("Error sending recipe message: {:?}", why);
-            return;
-        }
-    };
-
-    let listener = channel_message.await_component_interaction(ctx).await;
+        tracing::warn!("Error sending publish message: {:?}", why);
+    }
 
-    if let Some(listener) = listener {
-        if listener.data.custom_id == "publish" {
-            if let Err(why) = listener
-                .create_interaction_response(&ctx.http, |r| {
-                    r.kind(InteractionResponseType::DeferredUpdateMessage)
-                })
-                .await
-            {
-                tracing::warn!("Error sending publish message: {:?}", why);
-                return;
-            }
-            if let Err(why) = listener
-                .delete_original_interaction_response(&ctx.http)
-                .await
-            {
-                tracing::warn!("Error sending publish message: {:?}", why);
-                return;
-            }
-        }
-        if let Err(why) = command
-            .channel_id
-            .send_message(&ctx.http, |m| {
-                m.embed(|e| {
-                    e.title(name.clone())
-                        .field("Fremgangsmåte", steps.clone(), false)
-                        .field("Ingredienser", "\u{AD}", false) // Invisible character in value field to make discord happy
-                        .fields(ingredients.iter().map(|i| {
-                            (
-                                i.name.clone(),
-                                format!("{} {}", i.amount.clone(), i.unit.clone()),
-                                true,
-                            )
-                        }))
-                })
-            })
-            .await
-        {
-            tracing::warn!("Error sending recipe message: {:?}", why);
-        }
+    let embeds = interaction
+        .message
+        .embeds
+        .iter()
+        .cloned()
+        .map(CreateEmbed::from)
+        .collect::<Vec<_>>();
+    if embeds.is_empty() {
+        return Err(BotError::internal(format!(
+            "No recipe found in message {}",
+            interaction.message.id
+        )));
     }
+
+    interaction
+        .channel_id
+        .send_message(&ctx.http, |m| m.set_embeds(embeds))
+        .await?;
+    Ok(())
 }
 
 fn get_name(body: &str) -> Result<String, String> {
@@ -187,12 +147,3 @@ fn get_ingredients(body: &str) -> Result<Vec<Ingredient>, String> {
     }
     Ok(ingredients)
 }
-
-async fn error(ctx: &Context, command: Arc<MessageComponentInteraction>, error: String) {
-    if let Err(why) = command
-        .create_followup_message(&ctx.http, |m| m.content(format!("Error: {}", error)))
-        .await
-    {
-        tracing::warn!("Error sending error message: {:?}", why);
-    }
-}
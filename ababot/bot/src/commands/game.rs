@@ -1,204 +1,195 @@
+use std::{sync::Arc, time::Duration};
+
 use serenity::{
+    async_trait,
     builder::CreateApplicationCommand,
+    cache::Cache,
+    http::Http,
     model::prelude::{
         interaction::{
-            application_command::ApplicationCommandInteraction, InteractionResponseType,
+            application_command::ApplicationCommandInteraction,
+            autocomplete::AutocompleteInteraction, InteractionResponseType,
         },
-        ChannelId,
+        Channel, ChannelId, ChannelType,
     },
     prelude::Context,
 };
 use tracing::instrument;
 
-#[instrument(skip(ctx, command))]
-pub async fn run(ctx: &Context, command: &ApplicationCommandInteraction) {
-    let mut duration = None;
-    let mut unit = None;
-    let mut target_channel = None;
-
-    for option in &command.data.options {
-        if option.name == "duration" {
-            duration = option.value.as_ref().and_then(|v| v.as_f64())
-        }
-        if option.name == "unit" {
-            unit = option
-                .value
-                .as_ref()
-                .and_then(|v| v.as_str().map(|s| s.to_string()))
-        }
+use crate::error::{BotError, BotResult};
+use crate::options::CommandOptions;
+use crate::registry::{focused_option, suggest, Command};
+use crate::utils::{clock::SharedClock, time::Scheduler};
 
-        if option.name == "channel" {
-            target_channel = option.value.as_ref().and_then(|v| v.as_str())
-        }
+const MAX_DURATION: Duration = Duration::from_secs(24 * 60 * 60);
+
+pub struct GameCommand;
+
+#[derive(CommandOptions)]
+struct GameOptions {
+    /// How long should the gaming session be, at most a day
+    #[option(min = 0.0, max = 1440.0)]
+    duration: f64,
+    /// Time unit for duration
+    #[option(choice("minutes", "m"), choice("hours", "h"))]
+    unit: String,
+    /// The channel to game in
+    #[option(autocomplete)]
+    channel: String,
+}
+
+#[async_trait]
+impl Command for GameCommand {
+    fn name(&self) -> &'static str {
+        "game"
     }
 
-    match (duration, unit, target_channel) {
-        (Some(d), Some(u), Some(t)) => {
-            let text_response = format!("Moving you back in {}{}", d, u);
-            let d = if u == "m" { d * 60.0 } else { d * 60.0 * 60.0 };
-            match move_channel_users(d, t, command, ctx).await {
-                Ok(_) => {
-                    if let Err(why) = command
-                        .create_interaction_response(&ctx.http, |response| {
-                            response
-                                .kind(InteractionResponseType::ChannelMessageWithSource)
-                                .interaction_response_data(|message| message.content(text_response))
-                        })
-                        .await
-                    {
-                        tracing::warn!("Failed to run command: {}", why);
-                    }
-                }
-                Err(u) => {
-                    let text = match u {
-                        true => "Something went wrong, are you in a VC I have access to?",
-                        false => "Something went wrong while executing the command",
-                    };
-
-                    if let Err(why) = command
-                        .create_interaction_response(&ctx.http, |response| {
-                            response
-                                .kind(InteractionResponseType::ChannelMessageWithSource)
-                                .interaction_response_data(|message| {
-                                    message.content(text).ephemeral(true)
-                                })
-                        })
-                        .await
-                    {
-                        tracing::warn!("Failed to run command: {}", why);
-                    }
-                }
-            }
-        }
-        _ => {
-            tracing::debug!("Ignoring malformed request, missing duration or unit");
-            if let Err(why) = command
-                .create_interaction_response(&ctx.http, |response| {
-                    response
-                        .kind(InteractionResponseType::ChannelMessageWithSource)
-                        .interaction_response_data(|message| {
-                            message
-                                .content("Both duration and unit are required to use this command")
-                                .ephemeral(true)
-                        })
-                })
-                .await
-            {
-                tracing::warn!("Failed to run command: {}", why);
-            }
-        }
+    fn register<'a>(
+        &self,
+        command: &'a mut CreateApplicationCommand,
+    ) -> &'a mut CreateApplicationCommand {
+        register(command)
+    }
+
+    async fn run(&self, ctx: &Context, command: &ApplicationCommandInteraction) -> BotResult {
+        run(ctx, command).await
+    }
+
+    async fn autocomplete(&self, ctx: &Context, interaction: &AutocompleteInteraction) {
+        autocomplete(ctx, interaction).await
     }
 }
 
-type UserError = bool;
+#[instrument(skip(ctx, command))]
+pub async fn run(ctx: &Context, command: &ApplicationCommandInteraction) -> BotResult {
+    let GameOptions {
+        duration,
+        unit,
+        channel,
+    } = GameOptions::parse(command)?;
 
+    let text_response = format!("Moving you back in {}{}", duration, unit);
+    let seconds = if unit == "m" {
+        duration * 60.0
+    } else {
+        duration * 60.0 * 60.0
+    };
+    let duration = Duration::try_from_secs_f64(seconds)
+        .ok()
+        .filter(|duration| *duration <= MAX_DURATION)
+        .ok_or_else(|| BotError::user("A gaming session can last at most a day"))?;
+    move_channel_users(duration, &channel, command, ctx).await?;
+    command
+        .create_interaction_response(&ctx.http, |response| {
+            response
+                .kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|message| message.content(text_response))
+        })
+        .await?;
+    Ok(())
+}
+
+/// Move everyone in the voice channel of the user to the target channel,
+/// and schedule moving everyone there back once the session is over
 #[instrument(skip(command, ctx))]
 async fn move_channel_users(
-    d: f64,
+    duration: Duration,
     target_channel: &str,
     command: &ApplicationCommandInteraction,
     ctx: &Context,
-) -> Result<(), UserError> {
-    let cache = ctx.cache.clone();
-    let http = ctx.http.clone();
+) -> BotResult {
     let guild = command
         .guild_id
-        .ok_or_else(|| {
-            tracing::warn!("Could not retreive guild id");
-            false
-        })?
-        .to_guild_cached(&cache)
-        .ok_or_else(|| {
-            tracing::warn!("Could not retreive guild struct");
-            false
-        })?;
+        .ok_or_else(|| BotError::user("Gaming sessions only work in a server"))?
+        .to_guild_cached(&ctx.cache)
+        .ok_or_else(|| BotError::internal("Could not retreive guild struct"))?;
 
     let original_channel = guild
         .voice_states
         .get(&command.user.id)
-        .ok_or_else(|| {
-            tracing::debug!("User is not in a VC");
-            true
-        })?
-        .channel_id
-        .ok_or(false)?;
-    let target_channel = ChannelId(target_channel.parse().map_err(|e| {
-        tracing::error!("Failed to parse channel ID: {:?}", e);
-        false
-    })?);
-
-    tokio::spawn(async move {
-        for user in original_channel
-            .to_channel((&cache, http.as_ref()))
-            .await
-            .unwrap()
-            .guild()
-            .unwrap()
-            .members(&cache)
-            .await
-            .unwrap()
-        {
-            let r = user.move_to_voice_channel(&http, target_channel).await;
-            if let Err(e) = r {
-                tracing::warn!(
-                    "Failed to move user {:?} to {:?}: {}",
-                    user.nick,
-                    target_channel,
-                    e
-                );
-            }
-        }
+        .and_then(|state| state.channel_id)
+        .ok_or_else(|| BotError::user("You have to be in a VC I have access to"))?;
+    let target_channel = ChannelId(
+        target_channel
+            .parse()
+            .map_err(|_| BotError::user("Pick one of the suggested channels"))?,
+    );
 
-        tokio::time::sleep(std::time::Duration::from_secs_f64(d)).await;
-
-        for user in target_channel
-            .to_channel((&cache, http.as_ref()))
-            .await
-            .unwrap()
-            .guild()
-            .unwrap()
-            .members(&cache)
-            .await
-            .unwrap()
-        {
-            let r = user.move_to_voice_channel(&http, original_channel).await;
-            if r.is_err() {
-                tracing::warn!("Failed to move user {:?}", user.nick)
-            }
-        }
+    move_members(&ctx.cache, &ctx.http, original_channel, target_channel).await?;
+
+    let cache = ctx.cache.clone();
+    let http = ctx.http.clone();
+    Scheduler::new(SharedClock::get(ctx).await).once(duration, move || {
+        let cache = cache.clone();
+        let http = http.clone();
+        async move { move_members(&cache, &http, target_channel, original_channel).await }
     });
+    Ok(())
+}
 
+/// Move everyone in one voice channel to another. Members who can not be moved are left behind
+async fn move_members(
+    cache: &Arc<Cache>,
+    http: &Http,
+    from: ChannelId,
+    to: ChannelId,
+) -> BotResult {
+    let channel = from
+        .to_channel((cache, http))
+        .await?
+        .guild()
+        .ok_or_else(|| BotError::internal(format!("{} is not a channel in a server", from)))?;
+    for member in channel.members(cache).await? {
+        if let Err(e) = member.move_to_voice_channel(http, to).await {
+            tracing::warn!("Failed to move {} to {}: {}", member.user.tag(), to, e);
+        }
+    }
     Ok(())
 }
 
+/// Suggest the voice channels of the guild, the value being the channel id
+#[instrument(skip(ctx, interaction))]
+pub async fn autocomplete(ctx: &Context, interaction: &AutocompleteInteraction) {
+    let typed = focused_option(&interaction.data.options)
+        .and_then(|o| o.value.as_ref())
+        .and_then(|v| v.as_str())
+        .unwrap_or_default()
+        .to_lowercase();
+
+    let mut channels = match interaction
+        .guild_id
+        .and_then(|g| g.to_guild_cached(&ctx.cache))
+    {
+        Some(guild) => guild
+            .channels
+            .into_values()
+            .filter_map(|c| match c {
+                Channel::Guild(c) if c.kind == ChannelType::Voice => Some(c),
+                _ => None,
+            })
+            .filter(|c| c.name.to_lowercase().contains(&typed))
+            .collect::<Vec<_>>(),
+        None => {
+            tracing::warn!("Could not retreive guild struct");
+            Vec::new()
+        }
+    };
+    channels.sort_by_key(|c| c.position);
+
+    suggest(
+        ctx,
+        interaction,
+        channels.into_iter().map(|c| (c.name, c.id.to_string())),
+    )
+    .await;
+}
+
 #[instrument(skip(command))]
 pub fn register(command: &mut CreateApplicationCommand) -> &mut CreateApplicationCommand {
     tracing::debug!("Registering command game");
-    command
-        .name("game")
-        .description("Play a game for a limited duration")
-        .create_option(|option| {
-            option
-                .name("duration")
-                .description("How long should the gaming session be")
-                .kind(serenity::model::prelude::command::CommandOptionType::Number)
-                .min_number_value(0.0)
-                .required(true)
-        })
-        .create_option(|option| {
-            option
-                .name("unit")
-                .description("Time unit for duration")
-                .kind(serenity::model::prelude::command::CommandOptionType::String)
-                .add_string_choice("minutes", "m")
-                .add_string_choice("hours", "h")
-                .required(true)
-        })
-        .create_option(|option| {
-            option
-                .name("channel")
-                .description("The channel to game in")
-                .kind(serenity::model::prelude::command::CommandOptionType::Channel)
-                .required(true)
-        })
+    GameOptions::register(
+        command
+            .name("game")
+            .description("Play a game for a limited duration"),
+    )
 }
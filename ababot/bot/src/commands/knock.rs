@@ -1,4 +1,5 @@
 use serenity::{
+    async_trait,
     builder::CreateApplicationCommand,
     model::prelude::interaction::{
         application_command::ApplicationCommandInteraction, InteractionResponseType,
@@ -7,19 +8,40 @@ use serenity::{
 };
 use tracing::instrument;
 
+use crate::error::BotResult;
+use crate::registry::Command;
+
+pub struct KnockCommand;
+
+#[async_trait]
+impl Command for KnockCommand {
+    fn name(&self) -> &'static str {
+        "knock"
+    }
+
+    fn register<'a>(
+        &self,
+        command: &'a mut CreateApplicationCommand,
+    ) -> &'a mut CreateApplicationCommand {
+        register(command)
+    }
+
+    async fn run(&self, ctx: &Context, command: &ApplicationCommandInteraction) -> BotResult {
+        run(ctx, command).await
+    }
+}
+
 #[instrument(skip(ctx, command))]
-pub async fn run(ctx: &Context, command: &ApplicationCommandInteraction) {
+pub async fn run(ctx: &Context, command: &ApplicationCommandInteraction) -> BotResult {
     let ans = "Come in".to_string();
-    if let Err(why) = command
+    command
         .create_interaction_response(&ctx.http, |response| {
             response
                 .kind(InteractionResponseType::ChannelMessageWithSource)
                 .interaction_response_data(|message| message.content(ans))
         })
-        .await
-    {
-        tracing::warn!("Failed to run command: {}", why);
-    }
+        .await?;
+    Ok(())
 }
 
 pub fn register(command: &mut CreateApplicationCommand) -> &mut CreateApplicationCommand {
@@ -9,24 +9,26 @@ use serenity::{
 };
 use tokio::fs::read;
 
+use crate::error::{BotError, BotResult};
+use crate::utils::clock::SharedClock;
+
 use super::{types::Annonfile, upload::local_parse};
 
 const URL: &str = "https://api.anonfiles.com/upload";
 
-pub async fn get(ctx: &Context, command: &ApplicationCommandInteraction, file_str: &str) {
-    if let Err(why) = command
+pub async fn get(
+    ctx: &Context,
+    command: &ApplicationCommandInteraction,
+    file_str: &str,
+) -> BotResult {
+    command
         .create_interaction_response(&ctx.http, |response| {
             response.kind(InteractionResponseType::DeferredChannelMessageWithSource)
         })
-        .await
-    {
-        tracing::warn!("Not able to ack interaction: {:?}", why);
-        return;
-    }
+        .await?;
 
     if file_str.is_empty() || file_str.contains('/') {
-        error(ctx, command, "Illegal path").await;
-        return;
+        return Err(BotError::user("Illegal path"));
     }
 
     let mut base = String::from("kok/");
@@ -38,30 +40,15 @@ pub async fn get(ctx: &Context, command: &ApplicationCommandInteraction, file_st
 
     let file_path = Path::new(&base);
 
-    let file = match read(&file_path).await {
-        Ok(file) => file,
-        Err(_) => {
-            error(ctx, command, "Invalid file id").await;
-            return;
-        }
-    };
+    let file = read(&file_path)
+        .await
+        .map_err(|_| BotError::user(format!("There is no kok called {}", file_str)))?;
     // If file is smaller than 8MB, send it as an attachment
     if file.len() < 8_388_608 {
-        match get_small(ctx, command, file_path).await {
-            Ok(_) => (),
-            Err(_) => {
-                error(ctx, command, "Error sending file").await;
-            }
-        }
+        get_small(ctx, command, file_path).await
     } else {
         tracing::debug!("Handling big file");
-        match get_big(ctx, command, file, base).await {
-            // Allowed unwrap() because file_path is properly handled in saving process
-            Ok(_) => (),
-            Err(_) => {
-                error(ctx, command, "Error uploading file").await;
-            }
-        }
+        get_big(ctx, command, file, base).await
     }
 }
 
@@ -69,11 +56,10 @@ async fn get_small(
     ctx: &Context,
     command: &ApplicationCommandInteraction,
     file: &Path,
-) -> Result<(), String> {
+) -> BotResult {
     command
         .create_followup_message(&ctx.http, |m| m.add_file(file))
-        .await
-        .map_err(|_| String::from("Error sending file"))?;
+        .await?;
     Ok(())
 }
 
@@ -82,7 +68,7 @@ async fn get_big(
     command: &ApplicationCommandInteraction,
     file: Vec<u8>,
     name: String,
-) -> Result<(), String> {
+) -> BotResult {
     let file_part = Part::bytes(file)
         .file_name(name.clone())
         .mime_str("application/pdf")
@@ -90,46 +76,19 @@ async fn get_big(
 
     let form = Form::new().part("file", file_part);
     let client = reqwest::Client::new();
-    let response = match client.post(URL).multipart(form).send().await {
-        Ok(response) => response,
-        Err(_) => {
-            error(ctx, command, "Error uploading file").await;
-            return Err(String::from("Error uploading file"));
-        }
-    };
+    let response = client.post(URL).multipart(form).send().await?;
 
     // Respone from Annonfile
-    let parsed: Annonfile = match serde_json::from_str(
-        &response
-            .text()
-            .await
-            .unwrap_or_else(|_| "no response".to_string()),
-    ) {
-        // I have assumed that some text will be there to be parsed. Unwrap ok above
-        Ok(parsed) => parsed,
-        Err(_) => {
-            error(ctx, command, "Error uploading file").await;
-            return Err(String::from("Error uploading file"));
-        }
-    };
-
-    let page = match reqwest::get(parsed.data.file.url.full).await {
-        Ok(page) => match page.text().await {
-            Ok(page) => page,
-            Err(_) => {
-                return Err(String::from("Error uploading file"));
-            }
-        },
-        Err(_) => {
-            return Err(String::from("Error uploading file"));
-        }
-    };
-    let url = if let Some(url) = local_parse(page) {
-        url
-    } else {
-        return Err(String::from("Error uploading file"));
-    };
+    let parsed: Annonfile = serde_json::from_str(&response.text().await?)?;
+
+    let page = reqwest::get(parsed.data.file.url.full)
+        .await?
+        .text()
+        .await?;
+    let url = local_parse(page)
+        .ok_or_else(|| BotError::internal("No download url on the anonfiles page"))?;
 
+    let expires = SharedClock::get(ctx).await.now() + chrono::Duration::days(7);
     command
         .create_followup_message(&ctx.http, |m| {
             m.embed(|e| {
@@ -137,25 +96,12 @@ async fn get_big(
                     .url(url)
                     .field(
                         "May not be valid after",
-                        chrono::Utc::now()
-                            .checked_add_signed(chrono::Duration::days(7))
-                            .unwrap()
-                            .format("%d/%m"),
+                        format!("<t:{}:D>", expires.timestamp()),
                         false,
                     )
             })
         })
-        .await
-        .map_err(|_| String::from("Error sending file"))?;
+        .await?;
 
     Ok(())
 }
-
-async fn error(ctx: &Context, command: &ApplicationCommandInteraction, error: &str) {
-    if let Err(why) = command
-        .create_followup_message(&ctx.http, |m| m.content(format!("Error: {}", error)))
-        .await
-    {
-        tracing::warn!("Not able to send error message: {:?}", why);
-    }
-}
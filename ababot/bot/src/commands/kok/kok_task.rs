@@ -1,140 +1,153 @@
 use serenity::{
+    async_trait,
     builder::CreateApplicationCommand,
-    model::prelude::{
-        command::CommandOptionType,
-        interaction::{
-            application_command::{ApplicationCommandInteraction, CommandDataOptionValue},
+    model::{
+        channel::Attachment,
+        prelude::interaction::{
+            application_command::ApplicationCommandInteraction,
+            autocomplete::AutocompleteInteraction, modal::ModalSubmitInteraction,
             InteractionResponseType,
         },
     },
     prelude::Context,
 };
+use tokio::fs::read_dir;
+
+use crate::error::{BotError, BotResult};
+use crate::options::CommandOptions;
+use crate::registry::{focused_option, suggest, Command, CustomId};
 
 use super::{
     download::get,
-    upload::{create_modal, save_small},
+    upload::{create_modal, save_big, save_small},
 };
 
-pub async fn run(ctx: &Context, command: &ApplicationCommandInteraction) {
-    for option in &command.data.options {
-        if option.name == "get" {
-            for sub_option in &option.options {
-                if sub_option.name == "name" {
-                    let name = sub_option.value.as_ref().unwrap().as_str().unwrap_or("kok");
-                    get(ctx, command, name).await;
-                    return;
-                }
-            }
-        } else if option.name == "save" {
-            for sub_option in &option.options {
-                if sub_option.name == "small" {
-                    let mut name = String::new();
-                    for sub_sub_option in &sub_option.options {
-                        if sub_sub_option.name == "name" {
-                            name = sub_sub_option
-                                .value
-                                .as_ref()
-                                .unwrap() // Handled by Discord that this is a string
-                                .as_str()
-                                .unwrap_or("kok")
-                                .to_string();
-                        } else if sub_sub_option.name == "file" {
-                            if let CommandDataOptionValue::Attachment(file) =
-                                sub_sub_option.resolved.as_ref().unwrap()
-                            // Handled by Discord that this is a file
-                            {
-                                let file_bytes = if let Ok(file) = file.download().await {
-                                    file
-                                } else {
-                                    tracing::warn!("Not able to download file");
-                                    if let Err(why) = command
-                                        .create_interaction_response(&ctx.http, |m| {
-                                            m.kind(
-                                                InteractionResponseType::ChannelMessageWithSource,
-                                            )
-                                            .interaction_response_data(|m| {
-                                                m.content("Not able to download file")
-                                            })
-                                        })
-                                        .await
-                                    {
-                                        tracing::warn!("Not able to respond to user: {:?}", why);
-                                    }
-                                    return;
-                                };
-                                save_small(ctx, command, name.as_str(), file_bytes).await;
-                            }
-
-                            return;
-                        }
-                    }
-                } else if sub_option.name == "big" {
-                    create_modal(ctx, command).await;
-                    return;
-                }
+pub struct KokCommand;
+
+#[derive(CommandOptions)]
+enum KokOptions {
+    /// Add your contribution to the kok
+    Save(SaveOptions),
+    /// Get a kok
+    Get(GetOptions),
+    /// How to use kok
+    Help,
+}
+
+#[derive(CommandOptions)]
+enum SaveOptions {
+    /// Files smaller than 8MB
+    Small(SmallFile),
+    /// Files bigger than 8MB. Limit 20GB
+    Big,
+}
+
+#[derive(CommandOptions)]
+struct SmallFile {
+    /// Name of the kok
+    name: String,
+    /// File to save
+    file: Attachment,
+}
+
+#[derive(CommandOptions)]
+struct GetOptions {
+    /// Name of the kok
+    #[option(autocomplete)]
+    name: String,
+}
+
+#[async_trait]
+impl Command for KokCommand {
+    fn name(&self) -> &'static str {
+        "kok"
+    }
+
+    fn register<'a>(
+        &self,
+        command: &'a mut CreateApplicationCommand,
+    ) -> &'a mut CreateApplicationCommand {
+        register(command)
+    }
+
+    async fn run(&self, ctx: &Context, command: &ApplicationCommandInteraction) -> BotResult {
+        run(ctx, command).await
+    }
+
+    async fn autocomplete(&self, ctx: &Context, interaction: &AutocompleteInteraction) {
+        autocomplete(ctx, interaction).await
+    }
+
+    async fn modal(
+        &self,
+        ctx: &Context,
+        submit: &ModalSubmitInteraction,
+        id: CustomId<'_>,
+    ) -> BotResult {
+        match id.action {
+            "save" => save_big(ctx, submit).await,
+            _ => {
+                tracing::debug!("Modal {} not handled", submit.data.custom_id);
+                Ok(())
             }
-        } else if option.name == "help" {
-            how_to(ctx, command).await;
+        }
+    }
+}
+
+pub async fn run(ctx: &Context, command: &ApplicationCommandInteraction) -> BotResult {
+    match KokOptions::parse(command)? {
+        KokOptions::Get(GetOptions { name }) => get(ctx, command, &name).await,
+        KokOptions::Save(SaveOptions::Small(SmallFile { name, file })) => {
+            let file_bytes = file
+                .download()
+                .await
+                .map_err(|_| BotError::user("Not able to download file"))?;
+            save_small(ctx, command, name.as_str(), file_bytes).await
+        }
+        KokOptions::Save(SaveOptions::Big) => create_modal(ctx, command).await,
+        KokOptions::Help => how_to(ctx, command).await,
+    }
+}
+
+/// Suggest saved koks matching what has been typed so far
+pub async fn autocomplete(ctx: &Context, interaction: &AutocompleteInteraction) {
+    let typed = focused_option(&interaction.data.options)
+        .and_then(|o| o.value.as_ref())
+        .and_then(|v| v.as_str())
+        .unwrap_or_default()
+        .to_lowercase();
+
+    let mut entries = match read_dir("kok").await {
+        Ok(entries) => entries,
+        Err(why) => {
+            tracing::warn!("Not able to read kok folder: {:?}", why);
             return;
         }
+    };
+    let mut names = Vec::new();
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        if let Some(name) = entry
+            .file_name()
+            .to_str()
+            .and_then(|name| name.strip_suffix(".pdf"))
+        {
+            if name.to_lowercase().contains(&typed) {
+                names.push(name.to_string());
+            }
+        }
     }
+    names.sort();
+
+    suggest(ctx, interaction, names.into_iter().map(|n| (n.clone(), n))).await;
 }
 
 pub fn register(command: &mut CreateApplicationCommand) -> &mut CreateApplicationCommand {
-    command
-        .name("kok")
-        .description("Kok")
-        .create_option(|group| {
-            group
-                .name("save")
-                .description("Add your contribution to the kok")
-                .kind(CommandOptionType::SubCommandGroup)
-                .create_sub_option(|sub| {
-                    sub.name("small")
-                        .description("Files smaller than 8MB")
-                        .kind(CommandOptionType::SubCommand)
-                        .create_sub_option(|opt| {
-                            opt.name("name")
-                                .description("Name of the kok")
-                                .kind(CommandOptionType::String)
-                                .required(true)
-                        })
-                        .create_sub_option(|opt| {
-                            opt.name("file")
-                                .description("File to save")
-                                .kind(CommandOptionType::Attachment)
-                                .required(true)
-                        })
-                })
-                .create_sub_option(|sub| {
-                    sub.name("big")
-                        .description("Files bigger than 8MB. Limit 20GB")
-                        .kind(CommandOptionType::SubCommand)
-                })
-        })
-        .create_option(|option| {
-            option
-                .name("get")
-                .description("Get a kok")
-                .kind(CommandOptionType::SubCommand)
-                .create_sub_option(|opt| {
-                    opt.name("name")
-                        .description("Name of the kok")
-                        .kind(CommandOptionType::String)
-                        .required(true)
-                })
-        })
-        .create_option(|option| {
-            option
-                .name("help")
-                .description("How to use kok")
-                .kind(CommandOptionType::SubCommand)
-        })
+    KokOptions::register(command.name("kok").description("Kok"))
 }
 
 const URL: &str = "https://anonfiles.com";
-async fn how_to(ctx: &Context, command: &ApplicationCommandInteraction) {
-    if let Err(why) = command
+async fn how_to(ctx: &Context, command: &ApplicationCommandInteraction) -> BotResult {
+    command
         .create_interaction_response(&ctx.http, |m| {
             m.kind(InteractionResponseType::ChannelMessageWithSource)
                 .interaction_response_data(|m| {
@@ -155,8 +168,6 @@ That's it!",
                     })
                 })
         })
-        .await
-    {
-        tracing::warn!("Not able to send help: {:?}", why);
-    }
+        .await?;
+    Ok(())
 }
@@ -1,7 +1,7 @@
 mod kok_task;
 pub use kok_task::register;
 pub use kok_task::run;
-pub use upload::save_big;
+pub use kok_task::KokCommand;
 mod download;
 mod types;
 mod upload;
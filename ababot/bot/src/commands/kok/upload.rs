@@ -1,7 +1,4 @@
-use std::env;
-
 use serenity::{
-    futures::StreamExt,
     model::{
         prelude::{
             component::{ActionRowComponent, InputTextStyle},
@@ -17,24 +14,26 @@ use serenity::{
 };
 use tokio::{fs::File, io::AsyncWriteExt};
 
-use crate::utils::get_channel_id;
+use crate::{
+    config::Config,
+    error::{BotError, BotResult},
+    registry::custom_id,
+    storage::{kok::KokFile, Storage},
+    utils::{clock::SharedClock, get_channel_id},
+};
 
 pub async fn save_small(
     ctx: &Context,
     command: &ApplicationCommandInteraction,
     name: &str,
     bytes: Vec<u8>,
-) {
+) -> BotResult {
     // Acknowledge user that file is being processed
-    if let Err(why) = command
+    command
         .create_interaction_response(&ctx.http, |m| {
             m.kind(InteractionResponseType::DeferredChannelMessageWithSource)
         })
-        .await
-    {
-        tracing::warn!("Not able to ack Modal: {:?}", why);
-        return;
-    }
+        .await?;
 
     // File is small. Save the pdf
     let mut path = String::from("kok/");
@@ -44,125 +43,73 @@ pub async fn save_small(
         path.push_str(name);
         path.push_str(".pdf");
     };
-    let mut file = File::create(path).await.unwrap();
-    file.write_all(&bytes).await.unwrap();
+    let mut file = File::create(path).await?;
+    file.write_all(&bytes).await?;
 
     // Respond to user
-    if let Err(why) = command
+    command
         .create_followup_message(&ctx.http, |m| {
             m.content(format!("Saved file as {}.pdf", name))
         })
-        .await
-    {
-        tracing::warn!("Not able to respond to user: {:?}", why);
-    }
+        .await?;
 
     // Save file
-    file.sync_all().await.unwrap();
-    update_kok_catalogue(ctx, name.to_string(), &command.user).await;
+    file.sync_all().await?;
+    if let Some(guild_id) = command.guild_id {
+        update_kok_catalogue(ctx, guild_id, name.to_string(), &command.user).await;
+    }
+    Ok(())
 }
 
-pub async fn save_big(ctx: &Context, command: &ModalSubmitInteraction) {
-    if let Err(why) = command
+pub async fn save_big(ctx: &Context, command: &ModalSubmitInteraction) -> BotResult {
+    command
         .create_interaction_response(&ctx.http, |m| {
             m.kind(InteractionResponseType::DeferredUpdateMessage)
         })
-        .await
-    {
-        tracing::warn!("Not able to ack Modal: {:?}", why);
-        return;
-    }
+        .await?;
 
     let string_answers = command
         .data
         .components
         .iter()
         .filter_map(|row| row.components.get(0))
-        .map(|comp| match comp {
-            ActionRowComponent::InputText(input) => input.value.clone(),
-            _ => panic!("Not an input text"), // This means the modal is changed in a way that is not supported
+        .filter_map(|comp| match comp {
+            ActionRowComponent::InputText(input) => Some(input.value.clone()),
+            _ => None,
         })
         .collect::<Vec<String>>();
 
     let (name, url) = match string_answers.get(0..2) {
         Some([name, url]) => (name, url),
-        _ => {
-            error(ctx, command).await;
-            return;
-        }
+        _ => return Err(BotError::internal("Kok modal is missing its inputs")),
     };
 
+    // page provided by user
     let page = match reqwest::get(url).await {
-        Ok(page) => {
-            if let Ok(page) = page.text().await {
-                page
-            } else {
-                error(ctx, command).await;
-                return;
-            }
-        }
-        Err(_) => {
-            error(ctx, command).await;
-            return;
-        }
-    }; // page provided by user
-    let download_url = if let Some(url) = local_parse(page) {
-        url
-    } else {
-        error(ctx, command).await;
-        return;
-    }; // The actuall download url
-    let download_file = match reqwest::get(&download_url).await {
-        Ok(file) => {
-            if let Ok(file) = file.bytes().await {
-                file
-            } else {
-                error(ctx, command).await;
-                return;
-            }
-        }
-        Err(_) => {
-            error(ctx, command).await;
-            return;
-        }
-    }; // The actuall file
-
-    // Create file
-    let mut file = if let Ok(file) = File::create(format!("kok/{}.pdf", name)).await {
-        file
-    } else {
-        error(ctx, command).await;
-        return;
+        Ok(page) => page.text().await?,
+        Err(_) => return Err(BotError::user(format!("Not able to open {}", url))),
     };
+    // The actuall download url
+    let download_url = local_parse(page)
+        .ok_or_else(|| BotError::user(format!("No download link found on {}", url)))?;
+    // The actuall file
+    let download_file = reqwest::get(&download_url).await?.bytes().await?;
 
-    // Save file
-    if let Err(why) = file.write_all(download_file.as_ref()).await {
-        tracing::warn!("Not able to write to file: {:?}", why);
-        return;
-    }
+    // Create and save file
+    let mut file = File::create(format!("kok/{}.pdf", name)).await?;
+    file.write_all(download_file.as_ref()).await?;
 
-    if let Err(why) = command
+    command
         .create_followup_message(&ctx.http, |m| {
             m.content(format!("Saved file as {}.pdf", name))
         })
-        .await
-    {
-        tracing::warn!("Not able to ack Modal: {:?}", why);
-    }
-    file.sync_all().await.unwrap(); // Very unliklely to fail at this point. It's to ensure file is actually saved.
-
-    update_kok_catalogue(ctx, name.to_string(), &command.user).await;
-}
+        .await?;
+    file.sync_all().await?; // Very unliklely to fail at this point. It's to ensure file is actually saved.
 
-async fn error(ctx: &Context, command: &ModalSubmitInteraction) {
-    if let Err(why) = command
-        .create_followup_message(&ctx.http, |m| {
-            m.content("Something went wrong. Please try again")
-        })
-        .await
-    {
-        tracing::warn!("Not able to complete kok: {:?}", why);
+    if let Some(guild_id) = command.guild_id {
+        update_kok_catalogue(ctx, guild_id, name.to_string(), &command.user).await;
     }
+    Ok(())
 }
 
 pub fn local_parse(page: String) -> Option<String> {
@@ -175,75 +122,47 @@ pub fn local_parse(page: String) -> Option<String> {
         .map(|s| s.to_string())
 }
 
-async fn update_kok_catalogue(ctx: &Context, name: String, user: &User) {
-    let channel_id = if let Ok(channel) = get_channel_id("suppekjøkkenet", &ctx.http).await {
-        channel
+async fn update_kok_catalogue(ctx: &Context, guild_id: GuildId, name: String, user: &User) {
+    let config = Config::get(ctx).await;
+    let channel_id =
+        if let Ok(channel) = get_channel_id(guild_id, &config.channels.kok, &ctx.http).await {
+            channel
+        } else {
+            tracing::warn!("Not able to get channel id");
+            return;
+        };
+
+    // Remove .pdf from name if it exists
+    let name = if name.ends_with(".pdf") {
+        name[..name.len() - 4].to_string()
     } else {
-        tracing::warn!("Not able to get channel id");
-        return;
+        name
     };
 
-    let mut bot_messages = Vec::with_capacity(100);
-    let mut messages = channel_id.messages_iter(&ctx.http).boxed();
-    while let Some(message) = messages.next().await {
-        let message = match message {
-            Ok(message) => message,
-            Err(why) => {
-                tracing::warn!("Not able to get message: {:?}", why);
-                continue;
-            }
-        };
-        if message.author.id == ctx.cache.current_user_id() {
-            bot_messages.push(message);
+    let storage = Storage::get(ctx).await;
+    let previous = match storage.kok_file(guild_id, name.clone()).await {
+        Ok(previous) => previous,
+        Err(why) => {
+            tracing::warn!("Not able to look up kok file: {}", why);
+            None
         }
-    }
-    bot_messages = bot_messages
-        .into_iter()
-        .filter_map(|m| {
-            if let Some(embed) = m.embeds.get(0) {
-                if embed.title.as_ref().unwrap_or(&"".to_string()) == &name {
-                    Some(m)
-                } else {
-                    None
-                }
-            } else {
-                None
-            }
-        })
-        .collect();
-    // Find first
-    let message_id = bot_messages.get(0).map(|m| m.id);
-    let mut updated = false;
-    if let Some(message_id) = message_id {
-        match channel_id.delete_message(&ctx.http, message_id).await {
-            Ok(_) => updated = true,
-            Err(why) => tracing::warn!("Not able to delete message: {:?}", why),
+    };
+    let updated = previous.is_some();
+    if let Some(message_id) = previous.and_then(|file| file.catalogue_message) {
+        if let Err(why) = channel_id.delete_message(&ctx.http, message_id).await {
+            tracing::warn!("Not able to delete message: {:?}", why);
         }
     }
 
-    let guild_id = GuildId(
-        env::var("GUILD_ID")
-            .expect("Expected GUILD_ID in environment")
-            .parse()
-            .expect("GUILD_ID must be an integer"),
-    ); // Maybe store guildID in the cache?
-
     let user_name = user
         .nick_in(&ctx.http, guild_id)
         .await
         .unwrap_or_else(|| user.name.clone());
 
-    // Remove .pdf from name if it exists
-    let name = if name.ends_with(".pdf") {
-        name[..name.len() - 4].to_string()
-    } else {
-        name
-    };
-
-    if let Err(why) = channel_id
+    let message = channel_id
         .send_message(&ctx.http, |m| {
             m.embed(|e| {
-                e.title(name)
+                e.title(&name)
                     .description(if updated {
                         format!("{} has updated the file", user_name)
                     } else {
@@ -252,21 +171,35 @@ async fn update_kok_catalogue(ctx: &Context, name: String, user: &User) {
                     .color(0x00ff00)
             })
         })
-        .await
-    {
-        tracing::warn!("Not able to send message: {:?}", why);
+        .await;
+    let catalogue_message = match message {
+        Ok(message) => Some(message.id),
+        Err(why) => {
+            tracing::warn!("Not able to send message: {:?}", why);
+            None
+        }
+    };
+
+    let file = KokFile {
+        name,
+        uploaded_by: user.id,
+        catalogue_message,
+        updated_at: SharedClock::get(ctx).await.now().timestamp(),
+    };
+    if let Err(why) = storage.save_kok_file(guild_id, file).await {
+        tracing::warn!("Not able to save kok file: {}", why);
     }
 }
 
-// Interaction handling to this Modal is handled in lib.rs file in the interaction_create function
-// before it is sent back down here to save_big function.
-pub async fn create_modal(ctx: &Context, command: &ApplicationCommandInteraction) {
-    match command
+// Submissions of this Modal are routed by their custom id to KokCommand::modal
+// before they are sent back down here to the save_big function.
+pub async fn create_modal(ctx: &Context, command: &ApplicationCommandInteraction) -> BotResult {
+    command
         .create_interaction_response(&ctx.http, |m| {
             m.kind(InteractionResponseType::Modal)
                 .interaction_response_data(|d| {
                     d.content("Please select the file you want to download")
-                        .custom_id("kok")
+                        .custom_id(custom_id("kok", "save", &[]))
                         .title("Download")
                         .components(|c| {
                             c.create_action_row(|row| {
@@ -288,13 +221,7 @@ pub async fn create_modal(ctx: &Context, command: &ApplicationCommandInteraction
                         })
                 })
         })
-        .await
-    {
-        Ok(m) => m,
-        Err(e) => {
-            tracing::warn!("Not able to create modal: {:?}", e);
-        }
-    };
+        .await?;
     if let Err(why) = command
         .create_followup_message(&ctx.http, |m| {
             m.content("Please wait while I save the file")
@@ -303,4 +230,5 @@ pub async fn create_modal(ctx: &Context, command: &ApplicationCommandInteraction
     {
         tracing::warn!("Not able to respond to user: {:?}", why);
     }
+    Ok(())
 }
@@ -1,3 +1,7 @@
+use crate::registry::CommandRegistry;
+
+pub mod admin;
+#[cfg(feature = "dice")]
 pub mod dice;
 pub mod food;
 pub mod game;
@@ -6,4 +10,23 @@ pub mod kok;
 pub mod ping;
 pub mod quiz;
 pub mod remindme;
+pub mod settings;
 pub mod stonk;
+
+/// Every command the bot offers
+pub fn registry() -> CommandRegistry {
+    let mut registry = CommandRegistry::new();
+    registry.add(admin::AdminCommand);
+    registry.add(food::FoodCommand);
+    registry.add(game::GameCommand);
+    registry.add(knock::KnockCommand);
+    registry.add(kok::KokCommand);
+    registry.add(ping::PingCommand);
+    registry.add(quiz::QuizCommand);
+    registry.add(remindme::RemindMeCommand);
+    registry.add(settings::SettingsCommand);
+    registry.add(stonk::StonkCommand);
+    #[cfg(feature = "dice")]
+    registry.add(dice::DiceCommand);
+    registry
+}
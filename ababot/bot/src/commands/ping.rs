@@ -1,4 +1,5 @@
 use serenity::{
+    async_trait,
     builder::CreateApplicationCommand,
     model::prelude::interaction::{
         application_command::ApplicationCommandInteraction, InteractionResponseType,
@@ -7,19 +8,40 @@ use serenity::{
 };
 use tracing::instrument;
 
+use crate::error::BotResult;
+use crate::registry::Command;
+
+pub struct PingCommand;
+
+#[async_trait]
+impl Command for PingCommand {
+    fn name(&self) -> &'static str {
+        "ping"
+    }
+
+    fn register<'a>(
+        &self,
+        command: &'a mut CreateApplicationCommand,
+    ) -> &'a mut CreateApplicationCommand {
+        register(command)
+    }
+
+    async fn run(&self, ctx: &Context, command: &ApplicationCommandInteraction) -> BotResult {
+        run(ctx, command).await
+    }
+}
+
 #[instrument(skip(ctx, command))]
-pub async fn run(ctx: &Context, command: &ApplicationCommandInteraction) {
+pub async fn run(ctx: &Context, command: &ApplicationCommandInteraction) -> BotResult {
     let pong = "Pong!".to_string();
-    if let Err(why) = command
+    command
         .create_interaction_response(&ctx.http, |response| {
             response
                 .kind(InteractionResponseType::ChannelMessageWithSource)
                 .interaction_response_data(|message| message.content(pong))
         })
-        .await
-    {
-        tracing::warn!("Failed to run command: {}", why);
-    }
+        .await?;
+    Ok(())
 }
 
 pub fn register(command: &mut CreateApplicationCommand) -> &mut CreateApplicationCommand {
@@ -1,5 +1,8 @@
 mod quiz_task;
+mod session;
 mod types;
 
 pub use quiz_task::register;
 pub use quiz_task::run;
+pub use quiz_task::QuizCommand;
+pub use session::QuizSessions;
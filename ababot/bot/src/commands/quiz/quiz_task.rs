@@ -1,73 +1,105 @@
-use std::{collections::HashMap, error::Error, sync::Arc, time::Duration};
+use std::time::Duration;
 
 use rand::seq::SliceRandom;
 use serenity::{
+    async_trait,
     builder::CreateApplicationCommand,
-    futures::StreamExt,
     model::prelude::{
-        command::CommandOptionType,
         component::ButtonStyle,
         interaction::{
-            application_command::ApplicationCommandInteraction, InteractionResponseType,
+            application_command::ApplicationCommandInteraction,
+            message_component::MessageComponentInteraction, InteractionResponseType,
         },
+        MessageId,
     },
-    prelude::{Context, RwLock},
+    prelude::{Context, TypeMapKey},
 };
-use tokio::time::timeout;
 
-use crate::utils::get_channel_id;
+use crate::config::Config;
+use crate::error::{BotError, BotResult};
+use crate::options::CommandOptions;
+use crate::registry::{custom_id, Command, CustomId};
+use crate::storage::Storage;
+use crate::utils::{clock::SharedClock, get_channel_id, shutdown::Shutdown};
 
-use super::types::Quiz;
+use super::{
+    session::{QuizSession, QuizSessions},
+    types::Quiz,
+};
 
-const API_URL: &str = "https://the-trivia-api.com/api/questions?limit=5";
+const LEADERBOARD_SIZE: usize = 5;
 
-pub async fn run(ctx: &Context, command: &ApplicationCommandInteraction) {
-    let response = match fetch(API_URL).await {
-        Ok(response) => response,
-        Err(e) => {
-            tracing::error!("Error fetching quiz: {}", e);
-            return;
-        }
-    };
-    let quiz = match parse(response) {
-        Ok(quiz) => quiz,
-        Err(e) => {
-            tracing::error!("Error parsing quiz: {}", e);
-            return;
-        }
-    };
+pub struct QuizCommand;
 
-    let channel_id = match get_channel_id("quiz", &ctx.http).await {
-        Ok(channel_id) => channel_id,
-        Err(e) => {
-            tracing::error!("Error getting channel id: {}", e);
-            return;
-        }
-    };
+#[derive(CommandOptions)]
+struct QuizOptions {
+    /// Amount of time for the quiz
+    #[option(min = 1, max = 5)]
+    time: Option<i64>,
+}
+
+#[async_trait]
+impl Command for QuizCommand {
+    fn name(&self) -> &'static str {
+        "quiz"
+    }
+
+    fn register<'a>(
+        &self,
+        command: &'a mut CreateApplicationCommand,
+    ) -> &'a mut CreateApplicationCommand {
+        register(command)
+    }
+
+    async fn run(&self, ctx: &Context, command: &ApplicationCommandInteraction) -> BotResult {
+        run(ctx, command).await
+    }
+
+    async fn component(
+        &self,
+        ctx: &Context,
+        interaction: &MessageComponentInteraction,
+        id: CustomId<'_>,
+    ) -> BotResult {
+        handle_component(ctx, interaction, id).await
+    }
+}
+
+pub async fn run(ctx: &Context, command: &ApplicationCommandInteraction) -> BotResult {
+    let options = QuizOptions::parse(command)?;
 
-    if let Err(why) = command
+    let guild_id = command
+        .guild_id
+        .ok_or_else(|| BotError::user("The quiz can only be played in a server"))?;
+
+    let config = Config::get(ctx).await;
+    let quiz = parse(fetch(&config.quiz.api_url).await?)?;
+
+    let channel_id = get_channel_id(guild_id, &config.channels.quiz, &ctx.http)
+        .await
+        .map_err(BotError::internal)?;
+
+    let sessions = sessions(ctx).await?;
+
+    command
         .create_interaction_response(&ctx.http, |response| {
             response
                 .kind(InteractionResponseType::ChannelMessageWithSource)
                 .interaction_response_data(|message| message.content("Quiz time!"))
         })
-        .await
-    {
-        tracing::warn!("Failed to run command: {}", why);
-        return;
-    }
+        .await?;
 
     let mut question_string = String::new();
     for (i, question) in quiz.iter().enumerate() {
         question_string.push_str(&format!("{}. {}\n", i + 1, question.question));
     }
-    let channel_message = match channel_id
+    let channel_message = channel_id
         .send_message(&ctx.http, |m| {
             m.content(question_string).components(|c| {
                 quiz.iter().fold(c, |c, question| {
                     c.create_action_row(|row| {
                         row.create_select_menu(|menu| {
-                            menu.custom_id(&question.id.to_string())
+                            menu.custom_id(custom_id("quiz", "answer", &[&question.id]))
                                 .placeholder("Select an answer");
                             menu.options(|f| {
                                 let mut options: Vec<String> = question.incorrect_answers.clone();
@@ -82,24 +114,12 @@ pub async fn run(ctx: &Context, command: &ApplicationCommandInteraction) {
                 })
             })
         })
-        .await
-    {
-        Ok(m) => m,
-        Err(e) => {
-            tracing::error!("Error sending quiz: {}", e);
-            return;
-        }
-    };
+        .await?;
 
-    let mut collected_answers: HashMap<String, Vec<String>> = HashMap::new();
-    let mut answer: HashMap<String, Vec<String>> = HashMap::new();
+    let quiz_time_limit = options.time.unwrap_or(3) as u64;
 
-    let mut quiz_time_limit = 3;
-    for option in &command.data.options {
-        if option.name == "time" {
-            quiz_time_limit = option.value.as_ref().and_then(|v| v.as_u64()).unwrap_or(3);
-        }
-    }
+    let (session, stopped) = QuizSession::new(quiz);
+    sessions.lock().await.insert(channel_message.id, session);
 
     let button_message = match channel_id
         .send_message(&ctx.http, |m| {
@@ -107,125 +127,55 @@ pub async fn run(ctx: &Context, command: &ApplicationCommandInteraction) {
                 .components(|c| {
                     c.create_action_row(|row| {
                         row.create_button(|b| {
-                            b.label("Stop").style(ButtonStyle::Danger).custom_id("stop")
+                            b.label("Stop")
+                                .style(ButtonStyle::Danger)
+                                .custom_id(custom_id(
+                                    "quiz",
+                                    "stop",
+                                    &[&channel_message.id.to_string()],
+                                ))
                         })
                     })
                 })
         })
         .await
     {
-        Ok(m) => m,
+        Ok(m) => Some(m),
         Err(e) => {
             tracing::error!("Error sending quiz: {}", e);
-            return;
+            None
         }
     };
 
-    let interaction_stream = Arc::new(RwLock::new(
-        channel_message
-            .await_component_interactions(ctx)
-            .timeout(Duration::from_secs(quiz_time_limit * 60))
-            .build(),
-    ));
-
-    let mut button_stream = button_message
-        .await_component_interactions(ctx)
-        .timeout(Duration::from_secs(quiz_time_limit * 60))
-        .build();
-
-    let stream_clone = Arc::clone(&interaction_stream);
-    let ctx_clone = ctx.clone();
-    tokio::spawn(async move {
-        if let Some(interaction) = button_stream.next().await {
-            if let Err(why) = interaction
-                .create_interaction_response(&ctx_clone.http, |response| {
-                    response.kind(InteractionResponseType::DeferredUpdateMessage)
-                })
-                .await
-            {
-                tracing::warn!("Failed to ACK button: {}", why);
-            }
-        }
-        tracing::debug!("Stopping quiz early");
-        drop(stream_clone);
-    });
-
-    while Arc::strong_count(&interaction_stream) != 1 {
-        let other_stream_clone = Arc::clone(&interaction_stream);
-        let interaction = match timeout(
-            Duration::from_secs(1),
-            other_stream_clone.write().await.next(),
-        )
-        .await
-        {
-            Ok(interaction) => interaction,
-            Err(_) => continue,
-        };
-
-        if let Some(interaction) = interaction {
-            let local_answer = interaction
-                .data
-                .values
-                .get(0)
-                .unwrap_or(&String::from("No answer"))
-                .to_string();
-
-            let who_answered = interaction
-                .member
-                .as_ref()
-                .unwrap()
-                .nick
-                .clone()
-                .unwrap_or_else(|| interaction.user.name.clone());
-
-            collected_answers
-                .entry(who_answered.clone())
-                .or_insert_with(Vec::new)
-                .push(local_answer);
-
-            if let Err(why) = interaction
-                .create_interaction_response(&ctx, |r| {
-                    r.kind(InteractionResponseType::DeferredUpdateMessage)
-                })
-                .await
-            {
-                tracing::error!("Error responding to interaction: {:?}", why);
-            }
-        }
-    }
-    for question in &quiz {
-        for (name, values) in &collected_answers {
-            let filtered_list = values
-                .iter()
-                .filter(|&x| {
-                    question.incorrect_answers.contains(x) || question.correct_answer == *x
-                })
-                .collect::<Vec<_>>();
-            if filtered_list.last().unwrap_or(&&String::from("")) == &&question.correct_answer {
-                answer
-                    .entry(name.clone())
-                    .or_insert_with(Vec::new)
-                    .push("🟢".to_string());
-            } else {
-                answer
-                    .entry(name.clone())
-                    .or_insert_with(Vec::new)
-                    .push("🔴".to_string());
-            }
-        }
+    let shutdown = Shutdown::get(ctx).await;
+    tokio::select! {
+        _ = tokio::time::sleep(Duration::from_secs(quiz_time_limit * 60)) => {}
+        _ = stopped => tracing::debug!("Stopping quiz early"),
+        // Finish now so the scores are saved before the bot exits
+        _ = shutdown.stopped() => tracing::info!("Ending quiz early for shutdown"),
     }
 
+    let session = sessions
+        .lock()
+        .await
+        .remove(&channel_message.id)
+        .ok_or_else(|| {
+            BotError::internal(format!("Quiz session {} disappeared", channel_message.id))
+        })?;
+
     if let Err(why) = channel_message.delete(&ctx.http).await {
         tracing::error!("Error deleting quiz: {:?}", why);
     }
-    if let Err(why) = button_message.delete(&ctx.http).await {
-        tracing::error!("Error deleting quiz: {:?}", why);
+    if let Some(button_message) = button_message {
+        if let Err(why) = button_message.delete(&ctx.http).await {
+            tracing::error!("Error deleting quiz: {:?}", why);
+        }
     }
 
     if let Err(why) = channel_id
         .send_message(&ctx.http, |m| {
             m.embed(|e| {
-                quiz.iter().fold(e, |e, question| {
+                session.quiz.iter().fold(e, |e, question| {
                     e.field(
                         question.question.clone(),
                         question.correct_answer.clone(),
@@ -240,44 +190,149 @@ pub async fn run(ctx: &Context, command: &ApplicationCommandInteraction) {
         tracing::error!("Error sending answers: {:?}", why);
     }
 
+    let storage = Storage::get(ctx).await;
+    let now = SharedClock::get(ctx).await.now();
+    if let Err(why) = storage
+        .record_quiz(guild_id, session.scores(), session.quiz.len(), now)
+        .await
+    {
+        tracing::error!("Error saving quiz scores: {}", why);
+    }
+
+    let results = session.results();
     if let Err(why) = channel_id
         .send_message(&ctx.http, |m| {
             m.embed(|e| {
-                answer.keys().fold(e, |e, k| {
-                    let user_answers = answer.get(k).unwrap();
-                    let result_string = user_answers.join(" ");
-                    e.field(k, result_string, false).title("Results")
-                })
+                results
+                    .iter()
+                    .fold(e, |e, (name, result)| e.field(name, result, false))
+                    .title("Results")
             })
         })
         .await
     {
         tracing::error!("Error sending results: {:?}", why);
     }
+
+    let leaderboard = storage.quiz_leaderboard(guild_id, LEADERBOARD_SIZE).await?;
+    let standings = leaderboard
+        .iter()
+        .enumerate()
+        .map(|(i, s)| {
+            format!(
+                "{}. <@{}> {}/{} over {} quizzes",
+                i + 1,
+                s.user_id,
+                s.correct,
+                s.questions,
+                s.quizzes
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    if let Err(why) = channel_id
+        .send_message(&ctx.http, |m| {
+            m.embed(|e| e.title("Leaderboard").description(standings))
+        })
+        .await
+    {
+        tracing::error!("Error sending leaderboard: {:?}", why);
+    }
+    Ok(())
+}
+
+async fn sessions(ctx: &Context) -> BotResult<<QuizSessions as TypeMapKey>::Value> {
+    ctx.data
+        .read()
+        .await
+        .get::<QuizSessions>()
+        .cloned()
+        .ok_or_else(|| BotError::internal("QuizSessions not found in data"))
+}
+
+/// Record answers and stop requests for running quizzes.
+/// Interactions with a quiz that has ended are answered with a notice instead of failing.
+async fn handle_component(
+    ctx: &Context,
+    interaction: &MessageComponentInteraction,
+    id: CustomId<'_>,
+) -> BotResult {
+    let sessions = sessions(ctx).await?;
+
+    let handled = {
+        let mut sessions = sessions.lock().await;
+        match id.action {
+            "answer" => {
+                let participant = interaction
+                    .member
+                    .as_ref()
+                    .and_then(|m| m.nick.clone())
+                    .unwrap_or_else(|| interaction.user.name.clone());
+                match (
+                    sessions.get_mut(&interaction.message.id),
+                    id.arg(0),
+                    interaction.data.values.get(0),
+                ) {
+                    (Some(session), Some(question_id), Some(answer)) => {
+                        session.answer(
+                            interaction.user.id,
+                            participant,
+                            question_id,
+                            answer.clone(),
+                        );
+                        true
+                    }
+                    _ => false,
+                }
+            }
+            "stop" => match id
+                .arg(0)
+                .and_then(|m| m.parse().ok())
+                .and_then(|m| sessions.get_mut(&MessageId(m)))
+            {
+                Some(session) => {
+                    session.stop();
+                    true
+                }
+                None => false,
+            },
+            _ => false,
+        }
+    };
+
+    if handled {
+        interaction
+            .create_interaction_response(&ctx.http, |r| {
+                r.kind(InteractionResponseType::DeferredUpdateMessage)
+            })
+            .await?;
+    } else {
+        interaction
+            .create_interaction_response(&ctx.http, |r| {
+                r.kind(InteractionResponseType::ChannelMessageWithSource)
+                    .interaction_response_data(|m| m.content("This quiz is over").ephemeral(true))
+            })
+            .await?;
+    }
+    Ok(())
 }
 
 pub fn register(command: &mut CreateApplicationCommand) -> &mut CreateApplicationCommand {
     tracing::debug!("Registering command quiz");
-    command
-        .name("quiz")
-        .description("Answer these fiver questions in a limited amount of time")
-        .create_option(|opt| {
-            opt.name("time")
-                .description("Amount of time for the quiz")
-                .kind(CommandOptionType::Integer)
-                .min_int_value(1)
-                .max_int_value(5)
-                .required(false)
-        })
+    QuizOptions::register(
+        command
+            .name("quiz")
+            .description("Answer these fiver questions in a limited amount of time"),
+    )
 }
 
-async fn fetch(url: &str) -> Result<String, Box<dyn Error>> {
+async fn fetch(url: &str) -> reqwest::Result<String> {
     let response = reqwest::get(url).await?;
     let body = response.text().await?;
     Ok(body)
 }
 
-fn parse(response: String) -> Result<Quiz, Box<dyn Error>> {
+fn parse(response: String) -> serde_json::Result<Quiz> {
     let quiz: Quiz = serde_json::from_str(&response)?;
     Ok(quiz)
 }
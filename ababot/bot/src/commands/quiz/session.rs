@@ -0,0 +1,145 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+};
+
+use serenity::{
+    model::prelude::{MessageId, UserId},
+    prelude::{Mutex, TypeMapKey},
+};
+use tokio::sync::oneshot;
+
+use super::types::{Question, Quiz};
+
+/// A quiz that is still accepting answers
+pub struct QuizSession {
+    pub quiz: Quiz,
+    /// Latest answer to each question id, per participant
+    answers: HashMap<UserId, HashMap<String, String>>,
+    /// Display name of each participant
+    names: HashMap<UserId, String>,
+    stop: Option<oneshot::Sender<()>>,
+}
+
+impl QuizSession {
+    /// Create a session along with a receiver that resolves if the quiz is stopped early
+    pub fn new(quiz: Quiz) -> (Self, oneshot::Receiver<()>) {
+        let (stop, stopped) = oneshot::channel();
+        (
+            QuizSession {
+                quiz,
+                answers: HashMap::new(),
+                names: HashMap::new(),
+                stop: Some(stop),
+            },
+            stopped,
+        )
+    }
+
+    pub fn answer(&mut self, participant: UserId, name: String, question_id: &str, answer: String) {
+        self.names.insert(participant, name);
+        self.answers
+            .entry(participant)
+            .or_insert_with(HashMap::new)
+            .insert(question_id.to_string(), answer);
+    }
+
+    pub fn stop(&mut self) {
+        if let Some(stop) = self.stop.take() {
+            // The quiz may already be wrapping up, in which case nobody is listening
+            let _ = stop.send(());
+        }
+    }
+
+    fn is_correct(&self, answers: &HashMap<String, String>, question: &Question) -> bool {
+        matches!(answers.get(&question.id), Some(answer) if *answer == question.correct_answer)
+    }
+
+    /// Number of correct answers for every participant
+    pub fn scores(&self) -> Vec<(UserId, usize)> {
+        self.answers
+            .iter()
+            .map(|(participant, answers)| {
+                let correct = self
+                    .quiz
+                    .iter()
+                    .filter(|question| self.is_correct(answers, question))
+                    .count();
+                (*participant, correct)
+            })
+            .collect()
+    }
+
+    /// A green or red circle per question for every participant
+    pub fn results(&self) -> BTreeMap<String, String> {
+        self.answers
+            .iter()
+            .map(|(participant, answers)| {
+                let result = self
+                    .quiz
+                    .iter()
+                    .map(|question| {
+                        if self.is_correct(answers, question) {
+                            "🟢"
+                        } else {
+                            "🔴"
+                        }
+                    })
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                (self.names[participant].clone(), result)
+            })
+            .collect()
+    }
+}
+
+/// Running quizzes keyed by the message holding the questions
+pub struct QuizSessions;
+
+impl TypeMapKey for QuizSessions {
+    type Value = Arc<Mutex<HashMap<MessageId, QuizSession>>>;
+}
+
+#[cfg(test)]
+mod tests {
+    use serenity::model::prelude::UserId;
+
+    use crate::commands::quiz::types::Question;
+
+    use super::QuizSession;
+
+    fn question(id: &str, correct: &str) -> Question {
+        Question {
+            id: id.to_string(),
+            correct_answer: correct.to_string(),
+            incorrect_answers: vec!["wrong".to_string()],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn grades_latest_answer_per_question() {
+        let (mut session, _stopped) =
+            QuizSession::new(vec![question("1", "a"), question("2", "b")]);
+        session.answer(UserId(1), "ola".to_string(), "1", "wrong".to_string());
+        session.answer(UserId(1), "ola".to_string(), "1", "a".to_string());
+        session.answer(UserId(2), "kari".to_string(), "2", "b".to_string());
+        session.answer(UserId(2), "kari".to_string(), "1", "a".to_string());
+
+        let results = session.results();
+        assert_eq!(results["ola"], "🟢 🔴");
+        assert_eq!(results["kari"], "🟢 🟢");
+
+        let mut scores = session.scores();
+        scores.sort();
+        assert_eq!(scores, vec![(UserId(1), 1), (UserId(2), 2)]);
+    }
+
+    #[test]
+    fn stopping_notifies_receiver_once() {
+        let (mut session, mut stopped) = QuizSession::new(Vec::new());
+        session.stop();
+        session.stop();
+        assert!(stopped.try_recv().is_ok());
+    }
+}
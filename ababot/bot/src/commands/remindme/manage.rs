@@ -0,0 +1,349 @@
+use chrono::Utc;
+use serenity::{
+    builder::{CreateComponents, CreateEmbed},
+    model::prelude::{
+        component::ButtonStyle,
+        interaction::{
+            application_command::ApplicationCommandInteraction,
+            autocomplete::AutocompleteInteraction, message_component::MessageComponentInteraction,
+            InteractionResponseType,
+        },
+        UserId,
+    },
+    prelude::Context,
+};
+
+use crate::background_tasks::reminders::Reminders;
+use crate::error::{BotError, BotResult};
+use crate::registry::{custom_id, focused_option, suggest, CustomId};
+use crate::storage::{reminders::Reminder, settings::UserSettings};
+use crate::utils::clock::SharedClock;
+
+use super::remindme_task::{format_due, format_due_in, parse_time};
+
+/// Reminders shown on one page of `/remindme list`
+const PAGE_SIZE: usize = 10;
+/// Discord limit on the length of an autocomplete suggestion
+const MAX_SUGGESTION_LENGTH: usize = 100;
+/// Messages in the list are cut off after this many characters
+const MAX_MESSAGE_LENGTH: usize = 80;
+
+pub async fn list(ctx: &Context, command: &ApplicationCommandInteraction) -> BotResult {
+    let reminders = Reminders::get(ctx).await.of_user(command.user.id).await?;
+    let (embed, components) = page(&reminders, 0);
+    command
+        .create_interaction_response(&ctx.http, |response| {
+            response
+                .kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|m| {
+                    m.set_embed(embed)
+                        .set_components(components)
+                        .ephemeral(true)
+                })
+        })
+        .await?;
+    Ok(())
+}
+
+/// Turn the list the buttons are attached to to another page
+pub async fn show_page(
+    ctx: &Context,
+    interaction: &MessageComponentInteraction,
+    id: CustomId<'_>,
+) -> BotResult {
+    let number = id.arg(0).and_then(|n| n.parse().ok()).unwrap_or(0);
+    let reminders = Reminders::get(ctx)
+        .await
+        .of_user(interaction.user.id)
+        .await?;
+    let (embed, components) = page(&reminders, number);
+    interaction
+        .create_interaction_response(&ctx.http, |response| {
+            response
+                .kind(InteractionResponseType::UpdateMessage)
+                .interaction_response_data(|m| m.set_embed(embed).set_components(components))
+        })
+        .await?;
+    Ok(())
+}
+
+/// One page of the reminders, with buttons to the previous and next pages.
+/// Reminders may have been sent since the list was shown, so the page is clamped
+fn page(reminders: &[Reminder], number: usize) -> (CreateEmbed, CreateComponents) {
+    let pages = reminders.chunks(PAGE_SIZE).count().max(1);
+    let number = number.min(pages - 1);
+
+    let mut embed = CreateEmbed::default();
+    embed.title("Your reminders");
+    if reminders.is_empty() {
+        embed.description("You have no pending reminders");
+    } else {
+        let lines: Vec<String> = reminders
+            .iter()
+            .skip(number * PAGE_SIZE)
+            .take(PAGE_SIZE)
+            .map(|r| {
+                let mut details = Vec::new();
+                if let Some(repeat) = &r.repeat {
+                    details.push(repeat.recurrence.to_string());
+                }
+                if !r.mentions.is_empty() {
+                    details.push(format!("for {}", r.mentions));
+                }
+                if r.public {
+                    details.push(format!("in <#{}>", r.channel_id));
+                }
+                format!(
+                    "`#{}` {}{} · {}",
+                    r.id,
+                    format_due(r.due),
+                    if details.is_empty() {
+                        String::new()
+                    } else {
+                        format!(" ({})", details.join(", "))
+                    },
+                    shorten(&r.message, MAX_MESSAGE_LENGTH)
+                )
+            })
+            .collect();
+        embed
+            .description(lines.join("\n"))
+            .footer(|f| f.text(format!("Page {} of {}", number + 1, pages)));
+    }
+
+    let mut components = CreateComponents::default();
+    if pages > 1 {
+        components.create_action_row(|row| {
+            row.create_button(|b| {
+                b.label("Previous")
+                    .style(ButtonStyle::Secondary)
+                    .disabled(number == 0)
+                    .custom_id(custom_id(
+                        "remindme",
+                        "page",
+                        &[&number.saturating_sub(1).to_string()],
+                    ))
+            })
+            .create_button(|b| {
+                b.label("Next")
+                    .style(ButtonStyle::Secondary)
+                    .disabled(number + 1 == pages)
+                    .custom_id(custom_id("remindme", "page", &[&(number + 1).to_string()]))
+            })
+        });
+    }
+    (embed, components)
+}
+
+pub async fn cancel(
+    ctx: &Context,
+    command: &ApplicationCommandInteraction,
+    reminder: &str,
+) -> BotResult {
+    let reminders = Reminders::get(ctx).await;
+    let reminder = own_reminder(&reminders, command.user.id, reminder).await?;
+    if !reminders.cancel(reminder.id).await? {
+        return Err(BotError::user("That reminder has already been sent"));
+    }
+    command
+        .create_interaction_response(&ctx.http, |response| {
+            response
+                .kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|m| {
+                    m.embed(|e| {
+                        e.title("Reminder cancelled").field(
+                            "Message",
+                            reminder.message.as_str(),
+                            false,
+                        )
+                    })
+                    .ephemeral(true)
+                })
+        })
+        .await?;
+    Ok(())
+}
+
+pub async fn edit(
+    ctx: &Context,
+    command: &ApplicationCommandInteraction,
+    reminder: &str,
+    message: Option<String>,
+    time: Option<String>,
+    public: Option<bool>,
+) -> BotResult {
+    if message.is_none() && time.is_none() && public.is_none() {
+        return Err(BotError::user(
+            "Give a new message, time or visibility to change",
+        ));
+    }
+    let reminders = Reminders::get(ctx).await;
+    let reminder = own_reminder(&reminders, command.user.id, reminder).await?;
+    if public == Some(false) && !reminder.mentions.is_empty() {
+        return Err(BotError::user("Reminders mentioning others are public"));
+    }
+    let edited = Reminder {
+        message: message.unwrap_or(reminder.message),
+        due: match time {
+            Some(time) => {
+                let settings = UserSettings::of(ctx, command.user.id).await?;
+                let now = SharedClock::get(ctx).await.now_in(settings.timezone);
+                parse_time(&time, now, settings.language)
+                    .map_err(BotError::user)?
+                    .at
+                    .with_timezone(&Utc)
+            }
+            None => reminder.due,
+        },
+        public: public.unwrap_or(reminder.public),
+        ..reminder
+    };
+    if !reminders.update(edited.clone()).await? {
+        return Err(BotError::user("That reminder has already been sent"));
+    }
+    command
+        .create_interaction_response(&ctx.http, |response| {
+            response
+                .kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|m| {
+                    m.embed(|e| {
+                        e.title("Reminder updated")
+                            .description(where_and_when(&edited))
+                            .field("Message", edited.message.as_str(), false)
+                    })
+                    .ephemeral(true)
+                })
+        })
+        .await?;
+    Ok(())
+}
+
+fn where_and_when(reminder: &Reminder) -> String {
+    let due = format_due(reminder.due);
+    let who = if reminder.mentions.is_empty() {
+        "you".to_string()
+    } else {
+        reminder.mentions.to_string()
+    };
+    if reminder.public {
+        format!(
+            "I will remind {} at {} in <#{}>",
+            who, due, reminder.channel_id
+        )
+    } else {
+        format!("I will remind {} at {}", who, due)
+    }
+}
+
+/// The pending reminder picked by the user, which has to be one of their own
+async fn own_reminder(
+    reminders: &Reminders,
+    user_id: UserId,
+    picked: &str,
+) -> Result<Reminder, BotError> {
+    let reminder = match picked.trim().trim_start_matches('#').parse() {
+        Ok(id) => reminders.find(id).await?,
+        Err(_) => None,
+    };
+    reminder
+        .filter(|r| r.user_id == user_id)
+        .ok_or_else(|| BotError::user("You have no pending reminder like that, see /remindme list"))
+}
+
+/// Suggest the pending reminders of the user matching what has been typed so far
+pub async fn autocomplete(ctx: &Context, interaction: &AutocompleteInteraction) {
+    let typed = focused_option(&interaction.data.options)
+        .and_then(|o| o.value.as_ref())
+        .and_then(|v| v.as_str())
+        .unwrap_or_default()
+        .to_lowercase();
+
+    let reminders = match Reminders::get(ctx).await.of_user(interaction.user.id).await {
+        Ok(reminders) => reminders,
+        Err(why) => {
+            tracing::warn!("Not able to read reminders: {}", why);
+            return;
+        }
+    };
+    let timezone = match UserSettings::of(ctx, interaction.user.id).await {
+        Ok(settings) => settings.timezone,
+        Err(why) => {
+            tracing::warn!("Not able to read settings: {}", why);
+            return;
+        }
+    };
+    let choices = reminders
+        .into_iter()
+        .filter(|r| {
+            r.id.to_string().starts_with(typed.trim_start_matches('#'))
+                || r.message.to_lowercase().contains(&typed)
+        })
+        .map(|r| {
+            let name = format!(
+                "#{} · {} · {}",
+                r.id,
+                format_due_in(r.due, timezone),
+                r.message
+            );
+            (shorten(&name, MAX_SUGGESTION_LENGTH), r.id.to_string())
+        });
+    suggest(ctx, interaction, choices).await;
+}
+
+/// Cut the text off at `max` characters, marking that it was
+fn shorten(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        return text.to_string();
+    }
+    let mut shortened: String = text.chars().take(max - 1).collect();
+    shortened.push('…');
+    shortened
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use serenity::model::prelude::ChannelId;
+
+    use crate::storage::reminders::Mentions;
+
+    use super::*;
+
+    #[test]
+    fn long_text_is_shortened() {
+        assert_eq!(shorten("Buy milk", 8), "Buy milk");
+        assert_eq!(shorten("Buy milk", 5), "Buy …");
+        assert_eq!(shorten("Kjøp brød", 4).chars().count(), 4);
+    }
+
+    #[test]
+    fn pages_are_clamped() {
+        let reminders: Vec<Reminder> = (1..=12)
+            .map(|id| Reminder {
+                id,
+                user_id: UserId(1),
+                channel_id: ChannelId(2),
+                message: format!("Reminder {id}"),
+                due: Utc.with_ymd_and_hms(2023, 1, 2, 10, 0, 0).unwrap(),
+                public: false,
+                repeat: None,
+                mentions: Mentions::default(),
+            })
+            .collect();
+        let description = |number| {
+            let (embed, _) = page(&reminders, number);
+            embed.0["description"].as_str().unwrap().to_string()
+        };
+        assert_eq!(description(0).lines().count(), PAGE_SIZE);
+        assert_eq!(description(1).lines().count(), 2);
+        assert_eq!(description(1), description(5));
+        assert!(description(1).starts_with("`#11`"));
+
+        let (empty, buttons) = page(&[], 3);
+        assert_eq!(
+            empty.0["description"].as_str(),
+            Some("You have no pending reminders")
+        );
+        assert!(buttons.0.is_empty());
+    }
+}
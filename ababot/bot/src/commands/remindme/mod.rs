@@ -0,0 +1,9 @@
+mod manage;
+mod recurring;
+mod remindme_task;
+mod snooze;
+mod target;
+pub use remindme_task::register;
+pub use remindme_task::run;
+pub use remindme_task::RemindMeCommand;
+pub use snooze::reminder_buttons;
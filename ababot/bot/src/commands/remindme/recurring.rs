@@ -0,0 +1,117 @@
+use chrono::{DateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use serenity::{
+    model::prelude::interaction::{
+        application_command::ApplicationCommandInteraction, InteractionResponseType,
+    },
+    prelude::Context,
+};
+
+use crate::background_tasks::reminders::Reminders;
+use crate::error::{BotError, BotResult};
+use crate::storage::{
+    reminders::{Reminder, Repeat},
+    settings::{Language, UserSettings},
+};
+use crate::utils::{
+    clock::SharedClock,
+    time::{natural, recurrence::Recurrence},
+};
+
+use super::{remindme_task::recipients, target::Target};
+
+/// Occurrences listed when a recurring reminder is set
+const SHOWN_OCCURRENCES: usize = 5;
+
+pub async fn set_recurring(
+    ctx: &Context,
+    command: &ApplicationCommandInteraction,
+    message: String,
+    schedule: &str,
+    until: Option<String>,
+    times: Option<i64>,
+    target: Target,
+) -> BotResult {
+    let recurrence: Recurrence = schedule
+        .parse()
+        .map_err(|e| BotError::user(format!("I did not understand how often\n{}", e)))?;
+    let settings = UserSettings::of(ctx, command.user.id).await?;
+    let now = SharedClock::get(ctx).await.now_in(settings.timezone);
+    let until = match until {
+        Some(until) => Some(end_of_day(&until, now, settings.language).map_err(BotError::user)?),
+        None => None,
+    };
+    let first = recurrence
+        .next_after(now)
+        .map(|first| first.with_timezone(&Utc))
+        .filter(|first| !matches!(until, Some(until) if *first > until))
+        .ok_or_else(|| BotError::user("That would never happen before the end date"))?;
+
+    let reminder = Reminders::get(ctx)
+        .await
+        .add(Reminder {
+            id: 0,
+            user_id: command.user.id,
+            channel_id: target.channel_id,
+            message,
+            due: first,
+            public: target.public,
+            repeat: Some(Repeat {
+                recurrence,
+                until,
+                remaining: times.map(|times| u32::try_from(times).unwrap_or(u32::MAX)),
+                timezone: settings.timezone,
+            }),
+            mentions: target.mentions,
+        })
+        .await?;
+    let occurrences: Vec<String> = reminder
+        .occurrences(SHOWN_OCCURRENCES)
+        .into_iter()
+        .map(|due| format!("<t:{}:F>", due.timestamp()))
+        .collect();
+    let ends = match (times, until) {
+        (Some(times), Some(until)) => Some(format!(
+            "After {} times, or <t:{}:D> at the latest",
+            times,
+            until.timestamp()
+        )),
+        (Some(times), None) => Some(format!("After {} times", times)),
+        (None, Some(until)) => Some(format!("After <t:{}:D>", until.timestamp())),
+        (None, None) => None,
+    };
+
+    command
+        .create_interaction_response(&ctx.http, |response| {
+            response
+                .kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|m| {
+                    m.embed(|e| {
+                        e.title("Remind me")
+                            .description(format!(
+                                "I will remind {} {}",
+                                recipients(&reminder, command.channel_id),
+                                recurrence
+                            ))
+                            .field("Next times", occurrences.join("\n"), false);
+                        if let Some(ends) = &ends {
+                            e.field("Ends", ends, false);
+                        }
+                        e.field("Message", reminder.message.as_str(), false)
+                    })
+                    .ephemeral(!reminder.public)
+                })
+        })
+        .await?;
+    Ok(())
+}
+
+/// The end of the day the user typed on the wall clock of `now`, so a reminder can still come
+/// back that day
+fn end_of_day(date: &str, now: DateTime<Tz>, language: Language) -> Result<DateTime<Utc>, String> {
+    let date = natural::parse(date, now, language)?.at.date_naive();
+    date.and_hms_opt(23, 59, 59)
+        .and_then(|end| now.timezone().from_local_datetime(&end).latest())
+        .map(|end| end.with_timezone(&Utc))
+        .ok_or_else(|| format!("{} has no end", date))
+}
@@ -0,0 +1,342 @@
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use serenity::{
+    async_trait,
+    builder::CreateApplicationCommand,
+    model::{
+        channel::PartialChannel,
+        guild::Role,
+        prelude::{
+            interaction::{
+                application_command::ApplicationCommandInteraction,
+                autocomplete::AutocompleteInteraction,
+                message_component::MessageComponentInteraction, InteractionResponseType,
+            },
+            ChannelId, Mentionable,
+        },
+    },
+    prelude::Context,
+};
+
+use crate::background_tasks::reminders::Reminders;
+use crate::error::{BotError, BotResult};
+use crate::options::CommandOptions;
+use crate::registry::{Command, CustomId};
+use crate::storage::{
+    reminders::Reminder,
+    settings::{Language, UserSettings},
+};
+use crate::utils::{
+    clock::SharedClock,
+    time::natural::{self, When},
+};
+
+use super::{
+    manage::{autocomplete, cancel, edit, list, show_page},
+    recurring::set_recurring,
+    snooze::{done, snooze},
+    target::{Target, TargetOptions},
+};
+
+pub struct RemindMeCommand;
+
+#[derive(CommandOptions)]
+enum RemindMeOptions {
+    /// Just fill in the fields
+    Form(FormOptions),
+    /// Parse time from string
+    String(StringOptions),
+    /// Remind you again and again
+    Recurring(RecurringOptions),
+    /// List your pending reminders
+    List,
+    /// Cancel one of your pending reminders
+    Cancel(CancelOptions),
+    /// Change one of your pending reminders
+    Edit(EditOptions),
+}
+
+#[derive(CommandOptions)]
+struct FormOptions {
+    /// The message to remind you of
+    message: String,
+    /// Days from now
+    #[option(min = 0, max = 36500)]
+    day: Option<i64>,
+    /// Hours from now
+    #[option(min = 0, max = 876000)]
+    hour: Option<i64>,
+    /// Minutes from now
+    #[option(min = 0, max = 52560000)]
+    minute: Option<i64>,
+    /// Whether to send the reminder in a public channel
+    public: Option<bool>,
+    /// A channel or thread to send the reminder to
+    channel: Option<PartialChannel>,
+    /// A role to mention in the reminder
+    role: Option<Role>,
+    /// Users to mention in the reminder
+    users: Option<String>,
+}
+
+#[derive(CommandOptions)]
+struct StringOptions {
+    /// The message to remind you of
+    message: String,
+    /// When, e.g. "i morgen kl 8", "on friday 14:00" or "in 3 hours"
+    time: String,
+    /// Whether to send the reminder in a public channel
+    public: Option<bool>,
+    /// A channel or thread to send the reminder to
+    channel: Option<PartialChannel>,
+    /// A role to mention in the reminder
+    role: Option<Role>,
+    /// Users to mention in the reminder
+    users: Option<String>,
+}
+
+#[derive(CommandOptions)]
+struct RecurringOptions {
+    /// The message to remind you of
+    message: String,
+    /// How often, e.g. "every weekday at 08:15" or "every 2 weeks on thursday" (at 09:00)
+    schedule: String,
+    /// The last day to remind you
+    until: Option<String>,
+    /// How many times to remind you
+    #[option(min = 1)]
+    times: Option<i64>,
+    /// Whether to send the reminder in a public channel
+    public: Option<bool>,
+    /// A channel or thread to send the reminder to
+    channel: Option<PartialChannel>,
+    /// A role to mention in the reminder
+    role: Option<Role>,
+    /// Users to mention in the reminder
+    users: Option<String>,
+}
+
+#[derive(CommandOptions)]
+struct CancelOptions {
+    /// The reminder to cancel
+    #[option(autocomplete)]
+    reminder: String,
+}
+
+#[derive(CommandOptions)]
+struct EditOptions {
+    /// The reminder to change
+    #[option(autocomplete)]
+    reminder: String,
+    /// The new message
+    message: Option<String>,
+    /// The new time to remind you of
+    time: Option<String>,
+    /// Whether to send the reminder in a public channel
+    public: Option<bool>,
+}
+
+#[async_trait]
+impl Command for RemindMeCommand {
+    fn name(&self) -> &'static str {
+        "remindme"
+    }
+
+    fn register<'a>(
+        &self,
+        command: &'a mut CreateApplicationCommand,
+    ) -> &'a mut CreateApplicationCommand {
+        register(command)
+    }
+
+    async fn run(&self, ctx: &Context, command: &ApplicationCommandInteraction) -> BotResult {
+        run(ctx, command).await
+    }
+
+    async fn autocomplete(&self, ctx: &Context, interaction: &AutocompleteInteraction) {
+        autocomplete(ctx, interaction).await
+    }
+
+    async fn component(
+        &self,
+        ctx: &Context,
+        interaction: &MessageComponentInteraction,
+        id: CustomId<'_>,
+    ) -> BotResult {
+        match id.action {
+            "page" => show_page(ctx, interaction, id).await,
+            "snooze" => snooze(ctx, interaction, id).await,
+            "done" => done(ctx, interaction, id).await,
+            _ => {
+                tracing::debug!("Component {} not handled", interaction.data.custom_id);
+                Ok(())
+            }
+        }
+    }
+}
+
+pub async fn run(ctx: &Context, command: &ApplicationCommandInteraction) -> BotResult {
+    let settings = UserSettings::of(ctx, command.user.id).await?;
+    let now = SharedClock::get(ctx).await.now_in(settings.timezone);
+    let (due, message, target, reading) = match RemindMeOptions::parse(command)? {
+        RemindMeOptions::Form(options) => {
+            let due = now
+                .checked_add_signed(
+                    chrono::Duration::days(options.day.unwrap_or(0))
+                        + chrono::Duration::hours(options.hour.unwrap_or(0))
+                        + chrono::Duration::minutes(options.minute.unwrap_or(0)),
+                )
+                .ok_or_else(|| BotError::user("That is too far into the future"))?;
+            if due <= now {
+                return Err(BotError::user("Give a time in the future"));
+            }
+            (
+                due.with_timezone(&Utc),
+                options.message,
+                TargetOptions {
+                    channel: options.channel,
+                    role: options.role,
+                    users: options.users,
+                    public: options.public,
+                },
+                None,
+            )
+        }
+        RemindMeOptions::String(options) => {
+            let when = parse_time(&options.time, now, settings.language).map_err(BotError::user)?;
+            (
+                when.at.with_timezone(&Utc),
+                options.message,
+                TargetOptions {
+                    channel: options.channel,
+                    role: options.role,
+                    users: options.users,
+                    public: options.public,
+                },
+                Some(format!("\"{}\" as {}", options.time, when.reading)),
+            )
+        }
+        RemindMeOptions::Recurring(options) => {
+            let target = Target::resolve(
+                ctx,
+                command,
+                TargetOptions {
+                    channel: options.channel,
+                    role: options.role,
+                    users: options.users,
+                    public: options.public,
+                },
+            )
+            .await?;
+            return set_recurring(
+                ctx,
+                command,
+                options.message,
+                &options.schedule,
+                options.until,
+                options.times,
+                target,
+            )
+            .await;
+        }
+        RemindMeOptions::List => return list(ctx, command).await,
+        RemindMeOptions::Cancel(options) => return cancel(ctx, command, &options.reminder).await,
+        RemindMeOptions::Edit(options) => {
+            return edit(
+                ctx,
+                command,
+                &options.reminder,
+                options.message,
+                options.time,
+                options.public,
+            )
+            .await
+        }
+    };
+    let target = Target::resolve(ctx, command, target).await?;
+    let public = target.public;
+
+    let reminder = Reminders::get(ctx)
+        .await
+        .add(Reminder {
+            id: 0,
+            user_id: command.user.id,
+            channel_id: target.channel_id,
+            message,
+            due,
+            public,
+            repeat: None,
+            mentions: target.mentions,
+        })
+        .await?;
+    command
+        .create_interaction_response(&ctx.http, |response| {
+            response
+                .kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|m| {
+                    m.embed(|e| {
+                        e.title("Remind me").description(format!(
+                            "I will remind {} at {}",
+                            recipients(&reminder, command.channel_id),
+                            format_due(due)
+                        ));
+                        if let Some(reading) = &reading {
+                            e.field("Understood", reading, false);
+                        }
+                        e.field("Message", reminder.message.as_str(), false)
+                    })
+                    .ephemeral(!public)
+                })
+        })
+        .await?;
+    Ok(())
+}
+
+/// Who a reminder is for, and where if it is not sent to the channel `here`
+pub(super) fn recipients(reminder: &Reminder, here: ChannelId) -> String {
+    let mut recipients = if reminder.mentions.is_empty() {
+        "you".to_string()
+    } else {
+        reminder.mentions.to_string()
+    };
+    if reminder.channel_id != here {
+        recipients.push_str(&format!(" in {}", reminder.channel_id.mention()));
+    }
+    recipients
+}
+
+/// When the reminder is due, from the time the user typed on the wall clock of `now`, which it
+/// has to be after. Fails with a message for the user
+pub(super) fn parse_time(
+    time: &str,
+    now: DateTime<Tz>,
+    language: Language,
+) -> Result<When, String> {
+    let when = natural::parse(time, now, language)
+        .map_err(|e| format!("I was not able to parse time\n{}", e))?;
+    if when.at <= now {
+        return Err(format!("{} has already passed", when.reading));
+    }
+    Ok(when)
+}
+
+/// Shown by Discord in the timezone and language of whoever reads it
+pub(super) fn format_due(due: DateTime<Utc>) -> String {
+    format!("<t:{}:f>", due.timestamp())
+}
+
+/// For text Discord does not show timestamps in, like autocomplete suggestions
+pub(super) fn format_due_in(due: DateTime<Utc>, timezone: Tz) -> String {
+    due.with_timezone(&timezone)
+        .format("%d/%m %H:%M")
+        .to_string()
+}
+
+pub fn register(command: &mut CreateApplicationCommand) -> &mut CreateApplicationCommand {
+    tracing::debug!("Registering command remindme");
+    RemindMeOptions::register(
+        command
+            .name("remindme")
+            .description("Reminds you of something"),
+    )
+}
@@ -0,0 +1,209 @@
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use chrono_tz::Tz;
+use serenity::{
+    builder::CreateComponents,
+    model::prelude::{
+        component::ButtonStyle,
+        interaction::{message_component::MessageComponentInteraction, InteractionResponseType},
+        UserId,
+    },
+    prelude::Context,
+};
+
+use crate::background_tasks::reminders::Reminders;
+use crate::error::{BotError, BotResult};
+use crate::registry::{custom_id, CustomId};
+use crate::storage::{
+    reminders::{Mentions, Reminder},
+    settings::UserSettings,
+};
+use crate::utils::clock::SharedClock;
+
+use super::remindme_task::format_due;
+
+/// The snooze buttons on a sent reminder, as custom id argument and label
+const SNOOZES: [(&str, &str); 3] = [
+    ("10m", "Snooze 10m"),
+    ("1h", "Snooze 1h"),
+    ("tomorrow", "Snooze until tomorrow"),
+];
+
+const NOT_OWNER: &str = "Only the one who set this reminder can do that";
+
+/// Snooze and done buttons for a sent reminder. The user who set it is part of the custom id,
+/// so nobody else can use the buttons on a public reminder
+pub fn reminder_buttons(
+    components: &mut CreateComponents,
+    user_id: UserId,
+) -> &mut CreateComponents {
+    let user = user_id.to_string();
+    components.create_action_row(|row| {
+        for (snooze, label) in SNOOZES {
+            row.create_button(|b| {
+                b.label(label)
+                    .style(ButtonStyle::Secondary)
+                    .custom_id(custom_id("remindme", "snooze", &[&user, snooze]))
+            });
+        }
+        row.create_button(|b| {
+            b.label("Done")
+                .style(ButtonStyle::Success)
+                .custom_id(custom_id("remindme", "done", &[&user]))
+        })
+    })
+}
+
+/// When a reminder snoozed at `now` is due again, on the wall clock of `now`.
+/// `None` for an unknown snooze
+fn snooze_until(snooze: &str, now: DateTime<Tz>) -> Option<DateTime<Tz>> {
+    match snooze {
+        "10m" => Some(now + Duration::minutes(10)),
+        "1h" => Some(now + Duration::hours(1)),
+        // The same time on the wall clock, unless it is skipped by daylight saving time
+        "tomorrow" => {
+            let tomorrow = now.date_naive().succ_opt()?.and_time(now.time());
+            now.timezone()
+                .from_local_datetime(&tomorrow)
+                .earliest()
+                .or_else(|| Some(now + Duration::days(1)))
+        }
+        _ => None,
+    }
+}
+
+/// The buttons on a reminder are only for the user who set it
+fn is_owner(interaction: &MessageComponentInteraction, id: &CustomId<'_>) -> bool {
+    id.arg(0).and_then(|user| user.parse().ok()).map(UserId) == Some(interaction.user.id)
+}
+
+/// Set the reminder the buttons are attached to again. It has already been forgotten, so the
+/// message and mentions are read back from the reminder itself
+pub async fn snooze(
+    ctx: &Context,
+    interaction: &MessageComponentInteraction,
+    id: CustomId<'_>,
+) -> BotResult {
+    if !is_owner(interaction, &id) {
+        return Err(BotError::user(NOT_OWNER));
+    }
+    let settings = UserSettings::of(ctx, interaction.user.id).await?;
+    let now = SharedClock::get(ctx).await.now_in(settings.timezone);
+    let due = id
+        .arg(1)
+        .and_then(|snooze| snooze_until(snooze, now))
+        .ok_or_else(|| {
+            BotError::internal(format!("Unknown snooze {}", interaction.data.custom_id))
+        })?
+        .with_timezone(&Utc);
+    let message = interaction
+        .message
+        .embeds
+        .first()
+        .and_then(|embed| embed.description.clone())
+        .ok_or_else(|| {
+            BotError::internal(format!(
+                "No reminder found in message {}",
+                interaction.message.id
+            ))
+        })?;
+
+    Reminders::get(ctx)
+        .await
+        .add(Reminder {
+            id: 0,
+            user_id: interaction.user.id,
+            channel_id: interaction.channel_id,
+            message,
+            due,
+            // Private reminders are sent as direct messages
+            public: interaction.guild_id.is_some(),
+            repeat: None,
+            mentions: Mentions::parse(&interaction.message.content),
+        })
+        .await?;
+    interaction
+        .create_interaction_response(&ctx.http, |response| {
+            response
+                .kind(InteractionResponseType::UpdateMessage)
+                .interaction_response_data(|m| {
+                    m.content(format!("Snoozed until {}", format_due(due)))
+                        .components(|c| c)
+                })
+        })
+        .await?;
+    Ok(())
+}
+
+/// Take the buttons off a reminder that has been taken care of
+pub async fn done(
+    ctx: &Context,
+    interaction: &MessageComponentInteraction,
+    id: CustomId<'_>,
+) -> BotResult {
+    if !is_owner(interaction, &id) {
+        return Err(BotError::user(NOT_OWNER));
+    }
+    interaction
+        .create_interaction_response(&ctx.http, |response| {
+            response
+                .kind(InteractionResponseType::UpdateMessage)
+                .interaction_response_data(|m| m.content("Done").components(|c| c))
+        })
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono_tz::{America::New_York, Europe::Oslo};
+    use serenity::model::prelude::RoleId;
+
+    use super::*;
+
+    #[test]
+    fn snoozes_follow_the_wall_clock() {
+        let now = Oslo.with_ymd_and_hms(2023, 3, 25, 2, 30, 0).unwrap();
+        assert_eq!(
+            snooze_until("10m", now),
+            Some(Oslo.with_ymd_and_hms(2023, 3, 25, 2, 40, 0).unwrap())
+        );
+        assert_eq!(
+            snooze_until("1h", now),
+            Some(Oslo.with_ymd_and_hms(2023, 3, 25, 3, 30, 0).unwrap())
+        );
+        // 02:30 is skipped when the clocks go forward that night
+        assert_eq!(
+            snooze_until("tomorrow", now),
+            Some(Oslo.with_ymd_and_hms(2023, 3, 26, 3, 30, 0).unwrap())
+        );
+        let evening = Oslo.with_ymd_and_hms(2023, 3, 25, 20, 0, 0).unwrap();
+        assert_eq!(
+            snooze_until("tomorrow", evening),
+            Some(Oslo.with_ymd_and_hms(2023, 3, 26, 20, 0, 0).unwrap())
+        );
+        // The clocks in New York went forward two weeks earlier
+        let new_york = New_York.with_ymd_and_hms(2023, 3, 11, 2, 30, 0).unwrap();
+        assert_eq!(
+            snooze_until("tomorrow", new_york),
+            Some(New_York.with_ymd_and_hms(2023, 3, 12, 3, 30, 0).unwrap())
+        );
+        assert_eq!(snooze_until("forever", now), None);
+    }
+
+    #[test]
+    fn snoozed_reminders_mention_the_same_role_and_users() {
+        let mentions = Mentions {
+            role: Some(RoleId(3)),
+            users: vec![UserId(4), UserId(5)],
+        };
+        assert_eq!(Mentions::parse(&mentions.to_string()), mentions);
+        assert_eq!(
+            Mentions::parse("<@&3> <@!4> hei"),
+            Mentions {
+                role: Some(RoleId(3)),
+                users: vec![UserId(4)],
+            }
+        );
+        assert!(Mentions::parse("").is_empty());
+    }
+}
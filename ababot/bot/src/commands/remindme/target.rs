@@ -0,0 +1,196 @@
+use serenity::{
+    model::{
+        channel::{ChannelType, PartialChannel},
+        guild::Role,
+        prelude::{
+            interaction::application_command::ApplicationCommandInteraction, ChannelId, GuildId,
+            Mentionable, RoleId, UserId,
+        },
+        Permissions,
+    },
+    prelude::Context,
+};
+
+use crate::config::Config;
+use crate::error::BotError;
+use crate::storage::reminders::Mentions;
+
+/// Where to send a reminder and who to mention, as given in the command
+pub struct TargetOptions {
+    pub channel: Option<PartialChannel>,
+    pub role: Option<Role>,
+    pub users: Option<String>,
+    pub public: Option<bool>,
+}
+
+/// Where a reminder is sent and who it mentions, checked against what the user may do
+pub struct Target {
+    pub channel_id: ChannelId,
+    pub public: bool,
+    pub mentions: Mentions,
+}
+
+impl Target {
+    /// Reminders for others are public, and have to be set in a server. Only members allowed to
+    /// mention @everyone can mention @everyone, roles that are not mentionable, roles with more
+    /// than `max_role_members` members, or more than `max_users` users
+    pub async fn resolve(
+        ctx: &Context,
+        command: &ApplicationCommandInteraction,
+        options: TargetOptions,
+    ) -> Result<Target, BotError> {
+        if options.channel.is_none() && options.role.is_none() && options.users.is_none() {
+            return Ok(Target {
+                channel_id: command.channel_id,
+                public: options.public.unwrap_or(false),
+                mentions: Mentions::default(),
+            });
+        }
+        if options.public == Some(false) {
+            return Err(BotError::user(
+                "Reminders sent to a channel or mentioning others are public",
+            ));
+        }
+        let guild_id = command
+            .guild_id
+            .ok_or_else(|| BotError::user("Reminders for others can only be set in a server"))?;
+
+        // Resolved channels come with the permissions of the user in them
+        let (channel_id, permissions) = match &options.channel {
+            Some(channel) => {
+                let needed = match channel.kind {
+                    ChannelType::Text | ChannelType::News => Permissions::SEND_MESSAGES,
+                    ChannelType::PublicThread
+                    | ChannelType::PrivateThread
+                    | ChannelType::NewsThread => Permissions::SEND_MESSAGES_IN_THREADS,
+                    _ => {
+                        return Err(BotError::user(
+                            "Reminders can only be sent to text channels and threads",
+                        ))
+                    }
+                };
+                let permissions = channel.permissions.unwrap_or_else(Permissions::empty);
+                if !permissions.contains(Permissions::VIEW_CHANNEL | needed) {
+                    return Err(BotError::user(format!(
+                        "You can not send messages in {}",
+                        channel.id.mention()
+                    )));
+                }
+                (channel.id, permissions)
+            }
+            None => (
+                command.channel_id,
+                command
+                    .member
+                    .as_ref()
+                    .and_then(|member| member.permissions)
+                    .unwrap_or_else(Permissions::empty),
+            ),
+        };
+        let unlimited = permissions.contains(Permissions::MENTION_EVERYONE);
+        let config = Config::get(ctx).await;
+        let limits = &config.reminders;
+
+        if let Some(role) = &options.role {
+            if !unlimited {
+                check_role(ctx, guild_id, role, limits.max_role_members).map_err(BotError::user)?;
+            }
+        }
+        let users = match &options.users {
+            Some(users) => parse_users(users).map_err(BotError::user)?,
+            None => Vec::new(),
+        };
+        if !unlimited && users.len() > limits.max_users {
+            return Err(BotError::user(format!(
+                "A reminder can mention at most {} users",
+                limits.max_users
+            )));
+        }
+
+        Ok(Target {
+            channel_id,
+            public: true,
+            mentions: Mentions {
+                role: options.role.map(|role| role.id),
+                users,
+            },
+        })
+    }
+}
+
+/// Whether a member who may not mention @everyone may mention the role.
+/// Fails with a message for the user
+fn check_role(
+    ctx: &Context,
+    guild_id: GuildId,
+    role: &Role,
+    max_members: usize,
+) -> Result<(), String> {
+    if role.id.0 == guild_id.0 {
+        return Err("You are not allowed to mention @everyone".to_string());
+    }
+    if !role.mentionable {
+        return Err(format!("{} can not be mentioned", role.name));
+    }
+    // Members not in the cache are not counted, a role in a guild that is not cached is too big
+    let members = role_members(ctx, guild_id, role.id).unwrap_or(usize::MAX);
+    if members > max_members {
+        return Err(format!(
+            "{} is too big to mention, it can have at most {} members",
+            role.name, max_members
+        ));
+    }
+    Ok(())
+}
+
+fn role_members(ctx: &Context, guild_id: GuildId, role_id: RoleId) -> Option<usize> {
+    ctx.cache.guild_field(guild_id, |guild| {
+        guild
+            .members
+            .values()
+            .filter(|member| member.roles.contains(&role_id))
+            .count()
+    })
+}
+
+/// Users written as mentions or ids separated by spaces or commas, each user once
+fn parse_users(users: &str) -> Result<Vec<UserId>, String> {
+    let mut parsed = Vec::new();
+    for user in users.split(|c: char| c.is_whitespace() || c == ',') {
+        if user.is_empty() {
+            continue;
+        }
+        let id = user
+            .strip_prefix("<@")
+            .and_then(|id| id.strip_suffix('>'))
+            .map(|id| id.trim_start_matches('!'))
+            .unwrap_or(user);
+        let id = match id.parse() {
+            Ok(id) => UserId(id),
+            Err(_) => return Err(format!("{} is not a user, mention them with @", user)),
+        };
+        if !parsed.contains(&id) {
+            parsed.push(id);
+        }
+    }
+    if parsed.is_empty() {
+        return Err("Mention the users to remind with @".to_string());
+    }
+    Ok(parsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn users_are_read_from_mentions() {
+        assert_eq!(
+            parse_users("<@1> <@!2>, 3 <@1>"),
+            Ok(vec![UserId(1), UserId(2), UserId(3)])
+        );
+        assert!(parse_users("<@&4>").is_err());
+        assert!(parse_users("@everyone").is_err());
+        assert!(parse_users(" , ").is_err());
+    }
+}
@@ -0,0 +1,163 @@
+use chrono_tz::{Tz, TZ_VARIANTS};
+use serenity::{
+    async_trait,
+    builder::CreateApplicationCommand,
+    model::prelude::interaction::{
+        application_command::ApplicationCommandInteraction, autocomplete::AutocompleteInteraction,
+        InteractionResponseType,
+    },
+    prelude::Context,
+};
+use tracing::instrument;
+
+use crate::error::{BotError, BotResult};
+use crate::options::CommandOptions;
+use crate::registry::{focused_option, suggest, Command};
+use crate::storage::Storage;
+use crate::utils::clock::SharedClock;
+
+pub struct SettingsCommand;
+
+#[derive(CommandOptions)]
+struct SettingsOptions {
+    /// Your timezone, e.g. Europe/Oslo
+    #[option(autocomplete)]
+    timezone: Option<String>,
+    /// Language to read and echo times in
+    #[option(choice("English", "en"), choice("Norsk", "no"))]
+    language: Option<String>,
+}
+
+#[async_trait]
+impl Command for SettingsCommand {
+    fn name(&self) -> &'static str {
+        "settings"
+    }
+
+    fn register<'a>(
+        &self,
+        command: &'a mut CreateApplicationCommand,
+    ) -> &'a mut CreateApplicationCommand {
+        register(command)
+    }
+
+    async fn run(&self, ctx: &Context, command: &ApplicationCommandInteraction) -> BotResult {
+        run(ctx, command).await
+    }
+
+    async fn autocomplete(&self, ctx: &Context, interaction: &AutocompleteInteraction) {
+        autocomplete(ctx, interaction).await
+    }
+}
+
+/// Change the settings that are given, and show them all
+#[instrument(skip(ctx, command))]
+pub async fn run(ctx: &Context, command: &ApplicationCommandInteraction) -> BotResult {
+    let options = SettingsOptions::parse(command)?;
+    let storage = Storage::get(ctx).await;
+    let mut settings = storage.user_settings(command.user.id).await?;
+    if let Some(timezone) = &options.timezone {
+        settings.timezone = timezone_named(timezone).ok_or_else(|| {
+            BotError::user(format!(
+                "Unknown timezone {}, pick one of the suggestions",
+                timezone
+            ))
+        })?;
+    }
+    if let Some(language) = &options.language {
+        settings.language = language.parse().map_err(BotError::user)?;
+    }
+    let changed = options.timezone.is_some() || options.language.is_some();
+    if changed {
+        storage.set_user_settings(command.user.id, settings).await?;
+    }
+
+    let now = SharedClock::get(ctx).await.now_in(settings.timezone);
+    command
+        .create_interaction_response(&ctx.http, |response| {
+            response
+                .kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|m| {
+                    m.embed(|e| {
+                        e.title(if changed {
+                            "Settings saved"
+                        } else {
+                            "Your settings"
+                        })
+                        .field(
+                            "Timezone",
+                            format!(
+                                "{}, where it is {}",
+                                settings.timezone.name(),
+                                now.format("%H:%M")
+                            ),
+                            false,
+                        )
+                        .field("Language", settings.language, false)
+                    })
+                    .ephemeral(true)
+                })
+        })
+        .await?;
+    Ok(())
+}
+
+/// The timezone with the given name, ignoring case
+fn timezone_named(name: &str) -> Option<Tz> {
+    let name = name.trim().replace(' ', "_");
+    TZ_VARIANTS
+        .iter()
+        .find(|timezone| timezone.name().eq_ignore_ascii_case(&name))
+        .copied()
+}
+
+/// Timezones containing what has been typed, the ones starting with it first
+fn matching_timezones(typed: &str) -> Vec<&'static str> {
+    let typed = typed.trim().to_lowercase().replace(' ', "_");
+    let mut matching: Vec<&'static str> = TZ_VARIANTS
+        .iter()
+        .map(|timezone| timezone.name())
+        .filter(|name| name.to_lowercase().contains(&typed))
+        .collect();
+    matching.sort_by_key(|name| !name.to_lowercase().starts_with(&typed));
+    matching
+}
+
+pub async fn autocomplete(ctx: &Context, interaction: &AutocompleteInteraction) {
+    let typed = focused_option(&interaction.data.options)
+        .and_then(|o| o.value.as_ref())
+        .and_then(|v| v.as_str())
+        .unwrap_or_default();
+    let choices = matching_timezones(typed)
+        .into_iter()
+        .map(|name| (name.to_string(), name.to_string()));
+    suggest(ctx, interaction, choices).await;
+}
+
+pub fn register(command: &mut CreateApplicationCommand) -> &mut CreateApplicationCommand {
+    tracing::debug!("Registering command settings");
+    SettingsOptions::register(
+        command
+            .name("settings")
+            .description("Your timezone, and the language you write times in"),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono_tz::{America::New_York, Europe::Oslo};
+
+    use super::*;
+
+    #[test]
+    fn timezones_by_name() {
+        assert_eq!(timezone_named("Europe/Oslo"), Some(Oslo));
+        assert_eq!(timezone_named("america/new york"), Some(New_York));
+        assert_eq!(timezone_named("Oslo"), None);
+        let matching = matching_timezones("oslo");
+        assert_eq!(matching, vec!["Europe/Oslo"]);
+        let matching = matching_timezones("europe/");
+        assert!(matching.len() > 25);
+        assert!(matching.iter().all(|name| name.starts_with("Europe/")));
+    }
+}
@@ -2,3 +2,4 @@ mod stonk_task;
 mod types;
 pub use stonk_task::register;
 pub use stonk_task::run;
+pub use stonk_task::StonkCommand;
@@ -1,67 +1,115 @@
 use crate::commands::stonk::types::Stonk;
+use crate::error::BotResult;
+use crate::options::CommandOptions;
+use crate::registry::{focused_option, suggest, Command};
 use serenity::{
+    async_trait,
     builder::CreateApplicationCommand,
-    model::prelude::{
-        command::CommandOptionType,
-        interaction::{
-            application_command::ApplicationCommandInteraction, InteractionResponseType,
-        },
+    model::prelude::interaction::{
+        application_command::ApplicationCommandInteraction, autocomplete::AutocompleteInteraction,
+        InteractionResponseType,
     },
     prelude::Context,
 };
 use tracing::instrument;
 use yahoo_finance_api as yahoo;
 
+pub struct StonkCommand;
+
+#[derive(CommandOptions)]
+struct StonkOptions {
+    /// The symbol of the stonk you want to get
+    #[option(autocomplete)]
+    ticker: String,
+}
+
+#[async_trait]
+impl Command for StonkCommand {
+    fn name(&self) -> &'static str {
+        "stonk"
+    }
+
+    fn register<'a>(
+        &self,
+        command: &'a mut CreateApplicationCommand,
+    ) -> &'a mut CreateApplicationCommand {
+        register(command)
+    }
+
+    async fn run(&self, ctx: &Context, command: &ApplicationCommandInteraction) -> BotResult {
+        run(ctx, command).await
+    }
+
+    async fn autocomplete(&self, ctx: &Context, interaction: &AutocompleteInteraction) {
+        autocomplete(ctx, interaction).await
+    }
+}
+
 #[instrument(skip(ctx, command))]
-pub async fn run(ctx: &Context, command: &ApplicationCommandInteraction) {
-    let mut stonk: String = String::new();
-    for opt in &command.data.options {
-        if opt.name == "ticker" {
-            let ticker = opt
-                .value
-                .as_ref()
-                .and_then(|v| v.as_str())
-                .unwrap_or("AAPL");
-            let stonk_history = get_last_stonk(ticker).await;
-            let first: String = match stonk_history {
-                Ok(stonk) => format!("{:.2}", stonk.close),
-                Err(e) => {
-                    tracing::debug!("Could not find stonk {}: {}", ticker, e);
-                    "No stonks found".to_string()
-                }
-            };
-            stonk = format!("{}: {}", opt.value.as_ref().unwrap(), first);
+pub async fn run(ctx: &Context, command: &ApplicationCommandInteraction) -> BotResult {
+    let StonkOptions { ticker } = StonkOptions::parse(command)?;
+    let stonk_history = get_last_stonk(&ticker).await;
+    let first: String = match stonk_history {
+        Ok(stonk) => format!("{:.2}", stonk.close),
+        Err(e) => {
+            tracing::debug!("Could not find stonk {}: {}", ticker, e);
+            "No stonks found".to_string()
         }
-    }
-    if let Err(why) = command
+    };
+    let stonk = format!("{}: {}", ticker, first);
+    command
         .create_interaction_response(&ctx.http, |response| {
             response
                 .kind(InteractionResponseType::ChannelMessageWithSource)
                 .interaction_response_data(|message| message.content(stonk))
         })
-        .await
-    {
-        tracing::warn!("Failed to run command: {}", why);
+        .await?;
+    Ok(())
+}
+
+/// Suggest ticker symbols matching the company or symbol typed so far
+#[instrument(skip(ctx, interaction))]
+pub async fn autocomplete(ctx: &Context, interaction: &AutocompleteInteraction) {
+    let typed = focused_option(&interaction.data.options)
+        .and_then(|o| o.value.as_ref())
+        .and_then(|v| v.as_str())
+        .unwrap_or_default();
+    if typed.is_empty() {
+        suggest(ctx, interaction, Vec::new()).await;
+        return;
     }
+
+    let provider = yahoo::YahooConnector::new();
+    let symbols = match provider.search_ticker(typed).await {
+        Ok(result) => result
+            .quotes
+            .into_iter()
+            .map(|quote| {
+                (
+                    format!("{} ({})", quote.symbol, quote.short_name),
+                    quote.symbol,
+                )
+            })
+            .collect::<Vec<_>>(),
+        Err(e) => {
+            tracing::debug!("Could not search for stonk {}: {}", typed, e);
+            Vec::new()
+        }
+    };
+
+    suggest(ctx, interaction, symbols).await;
 }
 
 pub fn register(command: &mut CreateApplicationCommand) -> &mut CreateApplicationCommand {
     tracing::debug!("Registering command stonk");
-    command
-        .name("stonk")
-        .description("When you need stonk")
-        .create_option(|option| {
-            option
-                .name("ticker")
-                .description("The symbol of the stonk you want to get")
-                .kind(CommandOptionType::String)
-                .required(true)
-        })
+    StonkOptions::register(command.name("stonk").description("When you need stonk"))
 }
 
 #[instrument(level = "debug")]
 #[allow(dead_code)]
-pub async fn get_latest_stonks(stonk_name: &str) -> Result<Vec<Stonk>, Box<dyn std::error::Error>> {
+pub async fn get_latest_stonks(
+    stonk_name: &str,
+) -> Result<Vec<Stonk>, Box<dyn std::error::Error + Send + Sync>> {
     let provider = yahoo::YahooConnector::new();
     let resp = provider
         .get_latest_quotes(stonk_name, "1m")
@@ -74,7 +122,9 @@ pub async fn get_latest_stonks(stonk_name: &str) -> Result<Vec<Stonk>, Box<dyn s
 }
 
 #[instrument(level = "debug")]
-pub async fn get_last_stonk(stonk_name: &str) -> Result<Stonk, Box<dyn std::error::Error>> {
+pub async fn get_last_stonk(
+    stonk_name: &str,
+) -> Result<Stonk, Box<dyn std::error::Error + Send + Sync>> {
     let provider = yahoo::YahooConnector::new();
     let resp = provider.get_latest_quotes(stonk_name, "1m").await?;
     let quote = resp.last_quote()?;
@@ -0,0 +1,548 @@
+use std::{collections::HashMap, env, fmt, fs, io, net::SocketAddr, path::Path, sync::Arc};
+
+use reqwest::Url;
+use serde::Deserialize;
+use serenity::{
+    model::prelude::{ChannelId, GuildId, UserId},
+    prelude::{Context, TypeMapKey},
+};
+
+use crate::utils::{
+    cron::Cron,
+    time::{holidays::DaysOff, Time},
+};
+
+const DEFAULT_PATH: &str = "config.toml";
+const MAX_CHANNEL_NAME_LENGTH: usize = 100;
+
+/// Settings shared by every command and background task.
+/// Loaded once at startup and stored in the [TypeMap](serenity::prelude::TypeMap)
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub channels: Channels,
+    pub abakus: AbakusConfig,
+    pub lunch: LunchConfig,
+    pub yr: YrConfig,
+    pub quiz: QuizConfig,
+    pub reminders: RemindersConfig,
+    pub storage: StorageConfig,
+    pub admin: AdminConfig,
+    pub shutdown: ShutdownConfig,
+    pub scheduler: SchedulerConfig,
+    pub monitoring: MonitoringConfig,
+    /// Settings for single guilds, by guild id. TOML keys are strings, see [Config::validate]
+    pub guilds: HashMap<String, GuildConfig>,
+}
+
+/// Names of the channels the bot posts to.
+/// Guilds opt in to a background task by creating its channel
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Channels {
+    pub abakus: String,
+    pub lunch: String,
+    pub weather: String,
+    pub quiz: String,
+    pub kok: String,
+}
+
+impl Default for Channels {
+    fn default() -> Self {
+        Channels {
+            abakus: "abakus-events".to_string(),
+            lunch: "lunch".to_string(),
+            weather: "weather".to_string(),
+            quiz: "quiz".to_string(),
+            kok: "suppekjøkkenet".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AbakusConfig {
+    /// When to post, `HH:MM` or a [Cron] expression
+    #[serde(alias = "post_time")]
+    pub schedule: Cron,
+    /// What to do about posts due on weekends and public holidays
+    pub days_off: DaysOff,
+    /// Public page of an event, the event id is appended
+    pub event_url: String,
+    /// Event API, the event id is appended
+    pub api_url: String,
+}
+
+impl Default for AbakusConfig {
+    fn default() -> Self {
+        AbakusConfig {
+            schedule: Cron::daily(Time::new_unchecked(8, 0, 0)),
+            days_off: DaysOff::Skip,
+            event_url: "https://abakus.no/events/".to_string(),
+            api_url: "https://lego.abakus.no/api/v1/events/".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LunchConfig {
+    /// When to post, `HH:MM` or a [Cron] expression
+    #[serde(alias = "post_time")]
+    pub schedule: Cron,
+    /// What to do about posts due on weekends and public holidays
+    pub days_off: DaysOff,
+    /// Comic API, the date is appended as YYYY-MM-DD
+    pub url: String,
+}
+
+impl Default for LunchConfig {
+    fn default() -> Self {
+        LunchConfig {
+            schedule: Cron::daily(Time::new_unchecked(7, 0, 0)),
+            days_off: DaysOff::Skip,
+            url: "https://api.e24.no/content/v1/comics/".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct YrConfig {
+    /// When to post, `HH:MM` or a [Cron] expression
+    #[serde(alias = "post_time")]
+    pub schedule: Cron,
+    /// What to do about posts due on weekends and public holidays
+    pub days_off: DaysOff,
+    pub url: String,
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+impl YrConfig {
+    /// The forecast url for the configured location
+    pub fn forecast_url(&self) -> String {
+        format!("{}?lat={}&lon={}", self.url, self.latitude, self.longitude)
+    }
+}
+
+impl Default for YrConfig {
+    fn default() -> Self {
+        YrConfig {
+            schedule: Cron::daily(Time::new_unchecked(7, 0, 0)),
+            days_off: DaysOff::Run,
+            url: "https://api.met.no/weatherapi/locationforecast/2.0/compact".to_string(),
+            latitude: 63.415398,
+            longitude: 10.395053,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct QuizConfig {
+    pub api_url: String,
+}
+
+impl Default for QuizConfig {
+    fn default() -> Self {
+        QuizConfig {
+            api_url: "https://the-trivia-api.com/api/questions?limit=5".to_string(),
+        }
+    }
+}
+
+/// Limits on who reminders can ping, lifted for members allowed to mention @everyone
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RemindersConfig {
+    /// Largest role a reminder can mention
+    pub max_role_members: usize,
+    /// Most users a reminder can mention
+    pub max_users: usize,
+}
+
+impl Default for RemindersConfig {
+    fn default() -> Self {
+        RemindersConfig {
+            max_role_members: 30,
+            max_users: 10,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StorageConfig {
+    /// SQLite database file, created if it does not exist
+    pub path: String,
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        StorageConfig {
+            path: "ababot.db".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AdminConfig {
+    /// Channel failures are reported to, nothing is reported if it is not set
+    pub channel: Option<ChannelId>,
+    /// The only guild `/admin` is registered in, it is left out if it is not set
+    pub guild: Option<GuildId>,
+    /// Users allowed to use `/admin`. Jobs are shared by every guild,
+    /// so being an admin of a guild is not enough
+    pub users: Vec<UserId>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ShutdownConfig {
+    /// Seconds running commands get to finish after SIGTERM.
+    /// Keep it below the `stop_grace_period` of the container
+    pub grace_period: u64,
+}
+
+impl Default for ShutdownConfig {
+    fn default() -> Self {
+        ShutdownConfig { grace_period: 20 }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SchedulerConfig {
+    /// Seconds after a scheduled run it is still made up for when the bot was down at the time.
+    /// 0 never makes up for missed runs
+    pub catch_up_window: u64,
+    /// Seconds a failed run is retried for before it is reported to the admin channel.
+    /// Retries always stop before the next run is due
+    pub retry_deadline: u64,
+}
+
+impl Default for SchedulerConfig {
+    fn default() -> Self {
+        SchedulerConfig {
+            catch_up_window: 3 * 60 * 60,
+            retry_deadline: 60 * 60,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MonitoringConfig {
+    pub enabled: bool,
+    /// Where `/healthz` and `/metrics` are served
+    pub address: SocketAddr,
+}
+
+impl Default for MonitoringConfig {
+    fn default() -> Self {
+        MonitoringConfig {
+            enabled: true,
+            address: SocketAddr::from(([0, 0, 0, 0], 9100)),
+        }
+    }
+}
+
+/// Which commands a guild is offered, all of them by default
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GuildConfig {
+    /// Only these commands are offered, when set
+    pub commands: Option<Vec<String>>,
+    /// Commands that are not offered
+    pub disabled: Vec<String>,
+}
+
+impl GuildConfig {
+    fn command_enabled(&self, name: &str) -> bool {
+        let listed = match &self.commands {
+            Some(commands) => commands.iter().any(|command| command == name),
+            None => true,
+        };
+        listed && !self.disabled.iter().any(|command| command == name)
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(io::Error),
+    Parse(toml::de::Error),
+    Invalid(Vec<String>),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(e) => write!(f, "Could not read config: {}", e),
+            ConfigError::Parse(e) => write!(f, "Could not parse config: {}", e),
+            ConfigError::Invalid(errors) => {
+                write!(f, "Invalid config: {}", errors.join(", "))
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl TypeMapKey for Config {
+    type Value = Arc<Config>;
+}
+
+impl Config {
+    /// Load the config from the path in `CONFIG_PATH`, or `config.toml` if it is not set.
+    /// A missing `config.toml` gives the default config, a missing `CONFIG_PATH` is an error
+    pub fn from_env() -> Result<Config, ConfigError> {
+        match env::var("CONFIG_PATH") {
+            Ok(path) => fs::read_to_string(&path)
+                .map_err(|e| ConfigError::Io(io::Error::new(e.kind(), format!("{path}: {e}"))))?
+                .parse(),
+            Err(_) => Config::load(DEFAULT_PATH),
+        }
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Config, ConfigError> {
+        let path = path.as_ref();
+        match fs::read_to_string(path) {
+            Ok(content) => content.parse(),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                tracing::warn!("No config found at {}, using defaults", path.display());
+                let config = Config::default();
+                config.validate()?;
+                Ok(config)
+            }
+            Err(e) => Err(ConfigError::Io(e)),
+        }
+    }
+
+    /// Check the values that can not be expressed by the types alone.
+    /// Every problem is reported, not just the first
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let mut errors = Vec::new();
+
+        for (key, name) in [
+            ("channels.abakus", &self.channels.abakus),
+            ("channels.lunch", &self.channels.lunch),
+            ("channels.weather", &self.channels.weather),
+            ("channels.quiz", &self.channels.quiz),
+            ("channels.kok", &self.channels.kok),
+        ] {
+            if let Err(e) = validate_channel_name(name) {
+                errors.push(format!("{key}: {e}"));
+            }
+        }
+
+        for (key, url) in [
+            ("abakus.event_url", &self.abakus.event_url),
+            ("abakus.api_url", &self.abakus.api_url),
+            ("lunch.url", &self.lunch.url),
+            ("yr.url", &self.yr.url),
+            ("quiz.api_url", &self.quiz.api_url),
+        ] {
+            if let Err(e) = Url::parse(url) {
+                errors.push(format!("{key}: {e}"));
+            }
+        }
+
+        if !(-90.0..=90.0).contains(&self.yr.latitude) {
+            errors.push("yr.latitude: must be between -90 and 90".to_string());
+        }
+        if !(-180.0..=180.0).contains(&self.yr.longitude) {
+            errors.push("yr.longitude: must be between -180 and 180".to_string());
+        }
+
+        for id in self.guilds.keys() {
+            if id.parse::<u64>().is_err() {
+                errors.push(format!("guilds.{id}: must be a guild id"));
+            }
+        }
+
+        if self.storage.path.trim().is_empty() {
+            errors.push("storage.path: must not be empty".to_string());
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigError::Invalid(errors))
+        }
+    }
+
+    /// Whether a command is offered in a guild, or in every guild without settings of its own
+    /// when `guild` is `None`. `/admin` is only offered in the admin guild
+    pub fn command_enabled(&self, guild: Option<GuildId>, name: &str) -> bool {
+        if name == "admin" && (guild.is_none() || guild != self.admin.guild) {
+            return false;
+        }
+        match guild.and_then(|guild| self.guilds.get(&guild.to_string())) {
+            Some(settings) => settings.command_enabled(name),
+            None => true,
+        }
+    }
+
+    /// Get the config from the context
+    /// # Panics
+    /// Panics if the config has not been inserted at startup
+    pub async fn get(ctx: &Context) -> Arc<Config> {
+        ctx.data
+            .read()
+            .await
+            .get::<Config>()
+            .expect("Config not found in data")
+            .clone()
+    }
+}
+
+impl std::str::FromStr for Config {
+    type Err = ConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let config: Config = toml::from_str(s).map_err(ConfigError::Parse)?;
+        config.validate()?;
+        Ok(config)
+    }
+}
+
+/// Discord only allows lowercase text channel names without whitespace
+fn validate_channel_name(name: &str) -> Result<(), &'static str> {
+    if name.is_empty() || name.chars().count() > MAX_CHANNEL_NAME_LENGTH {
+        return Err("channel names must be between 1 and 100 characters");
+    }
+    if name.chars().any(|c| c.is_whitespace() || c.is_uppercase()) {
+        return Err("channel names must be lowercase without whitespace");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_config_is_default() {
+        let config: Config = "".parse().unwrap();
+        assert_eq!(config.channels.lunch, "lunch");
+        assert_eq!(
+            config.abakus.schedule,
+            Cron::daily(Time::new_unchecked(8, 0, 0))
+        );
+    }
+
+    #[test]
+    fn partial_sections_keep_defaults() {
+        let config: Config = r#"
+            [lunch]
+            schedule = "30 6 * * mon-fri"
+
+            [channels]
+            weather = "vær"
+        "#
+        .parse()
+        .unwrap();
+        assert_eq!(config.lunch.schedule, "30 6 * * 1-5".parse().unwrap());
+        assert_eq!(config.lunch.url, LunchConfig::default().url);
+        assert_eq!(config.channels.weather, "vær");
+        assert_eq!(config.channels.quiz, "quiz");
+    }
+
+    #[test]
+    fn days_off_are_configurable() {
+        let config: Config = "[yr]\ndays_off = \"shift\"".parse().unwrap();
+        assert_eq!(config.yr.days_off, DaysOff::Shift);
+        assert_eq!(config.lunch.days_off, DaysOff::Skip);
+
+        let result = "[lunch]\ndays_off = \"never\"".parse::<Config>();
+        assert!(matches!(result, Err(ConfigError::Parse(_))));
+    }
+
+    #[test]
+    fn admin_channel_is_optional() {
+        let config: Config = "".parse().unwrap();
+        assert_eq!(config.admin.channel, None);
+
+        let config: Config = "[admin]\nchannel = 1046082446853967942".parse().unwrap();
+        assert_eq!(config.admin.channel, Some(ChannelId(1046082446853967942)));
+    }
+
+    #[test]
+    fn admin_is_only_offered_in_the_admin_guild() {
+        let config: Config = "".parse().unwrap();
+        assert!(!config.command_enabled(None, "admin"));
+        assert!(!config.command_enabled(Some(GuildId(1)), "admin"));
+
+        let config: Config = "[admin]\nguild = 1\nusers = [2]".parse().unwrap();
+        assert_eq!(config.admin.users, vec![UserId(2)]);
+        assert!(!config.command_enabled(None, "admin"));
+        assert!(!config.command_enabled(Some(GuildId(3)), "admin"));
+        assert!(config.command_enabled(Some(GuildId(1)), "admin"));
+        assert!(config.command_enabled(None, "ping"));
+    }
+
+    #[test]
+    fn commands_are_enabled_per_guild() {
+        let config: Config = r#"
+            [guilds.1]
+            commands = ["ping", "quiz"]
+            disabled = ["quiz"]
+
+            [guilds.2]
+            disabled = ["game"]
+        "#
+        .parse()
+        .unwrap();
+        let enabled = |guild, name| config.command_enabled(guild, name);
+        assert!(enabled(Some(GuildId(1)), "ping"));
+        assert!(!enabled(Some(GuildId(1)), "quiz"));
+        assert!(!enabled(Some(GuildId(1)), "game"));
+        assert!(enabled(Some(GuildId(2)), "quiz"));
+        assert!(!enabled(Some(GuildId(2)), "game"));
+        assert!(enabled(Some(GuildId(3)), "game"));
+        assert!(enabled(None, "game"));
+
+        let result = "[guilds.abakus]\ndisabled = [\"game\"]".parse::<Config>();
+        assert!(matches!(result, Err(ConfigError::Invalid(_))));
+    }
+
+    #[test]
+    fn post_time_is_still_accepted() {
+        let config: Config = "[yr]\npost_time = \"06:45\"".parse().unwrap();
+        assert_eq!(
+            config.yr.schedule,
+            Cron::daily(Time::new_unchecked(6, 45, 0))
+        );
+    }
+
+    #[test]
+    fn rejects_illegal_time() {
+        let result = "[yr]\npost_time = \"25:00\"".parse::<Config>();
+        assert!(matches!(result, Err(ConfigError::Parse(_))));
+    }
+
+    #[test]
+    fn rejects_unknown_keys() {
+        let result = "[lunch]\ntime = \"07:00\"".parse::<Config>();
+        assert!(matches!(result, Err(ConfigError::Parse(_))));
+    }
+
+    #[test]
+    fn reports_every_invalid_value() {
+        let result = r#"
+            [channels]
+            quiz = "Quiz Time"
+
+            [yr]
+            latitude = 100.0
+            url = "not a url"
+        "#
+        .parse::<Config>();
+        match result {
+            Err(ConfigError::Invalid(errors)) => assert_eq!(errors.len(), 3),
+            other => panic!("Expected validation errors, got {:?}", other),
+        }
+    }
+}
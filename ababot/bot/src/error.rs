@@ -0,0 +1,304 @@
+use std::{fmt, io};
+
+use serenity::{
+    http::HttpError,
+    model::prelude::{
+        interaction::{
+            application_command::ApplicationCommandInteraction,
+            message_component::MessageComponentInteraction, modal::ModalSubmitInteraction,
+            InteractionResponseType,
+        },
+        ChannelId, GuildId,
+    },
+    model::user::User,
+    prelude::Context,
+};
+
+use crate::{config::Config, options::OptionError, storage::StorageError};
+
+pub type BotResult<T = ()> = Result<T, BotError>;
+
+const TOO_MANY_REQUESTS: u16 = 429;
+
+/// Everything that can make a command, component or modal fail.
+///
+/// Returned from the handlers in [Command](crate::registry::Command), the registry
+/// then answers the interaction with [BotError::user_message] through [handle].
+#[derive(Debug)]
+pub enum BotError {
+    /// The user asked for something that can not be done, the message is shown as is
+    User(String),
+    /// The options sent do not match the registered ones
+    Options(OptionError),
+    Discord(serenity::Error),
+    Http(reqwest::Error),
+    Json(serde_json::Error),
+    Io(io::Error),
+    Storage(StorageError),
+    /// A bug or broken assumption in the bot, the message is only logged
+    Internal(String),
+}
+
+impl BotError {
+    pub fn user<S: Into<String>>(message: S) -> Self {
+        BotError::User(message.into())
+    }
+
+    pub fn internal<S: Into<String>>(message: S) -> Self {
+        BotError::Internal(message.into())
+    }
+
+    /// What the user who triggered the interaction is told
+    pub fn user_message(&self) -> String {
+        match self {
+            BotError::User(message) => message.clone(),
+            BotError::Options(e) => format!(
+                "I did not understand that command: {}. It may have changed recently, try again in a minute",
+                e
+            ),
+            BotError::Discord(_) => "Discord did not accept my response, please try again".to_string(),
+            BotError::Http(_) | BotError::Json(_) => {
+                "A service I depend on did not answer properly, please try again later".to_string()
+            }
+            BotError::Io(_) | BotError::Storage(_) | BotError::Internal(_) => {
+                "Something went wrong on my side, please try again later".to_string()
+            }
+        }
+    }
+
+    /// Whether trying again can not help, because Discord refused the request itself, e.g.
+    /// a user not accepting direct messages or a channel the bot can no longer see.
+    /// Rate limits, errors on Discord's side and lost connections are worth another try
+    pub fn is_permanent(&self) -> bool {
+        match self {
+            BotError::Discord(serenity::Error::Http(e)) => match e.as_ref() {
+                HttpError::UnsuccessfulRequest(response) => {
+                    response.status_code.is_client_error()
+                        && response.status_code.as_u16() != TOO_MANY_REQUESTS
+                }
+                _ => false,
+            },
+            BotError::Discord(serenity::Error::Model(_)) => true,
+            _ => false,
+        }
+    }
+
+    /// Whether an admin should look at this, as opposed to the user having made a mistake
+    pub fn is_internal(&self) -> bool {
+        !matches!(self, BotError::User(_) | BotError::Options(_))
+    }
+}
+
+impl fmt::Display for BotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BotError::User(message) => write!(f, "{}", message),
+            BotError::Options(e) => write!(f, "Invalid options: {}", e),
+            BotError::Discord(e) => write!(f, "Discord error: {}", e),
+            BotError::Http(e) => write!(f, "HTTP error: {}", e),
+            BotError::Json(e) => write!(f, "Invalid JSON: {}", e),
+            BotError::Io(e) => write!(f, "IO error: {}", e),
+            BotError::Storage(e) => write!(f, "{}", e),
+            BotError::Internal(message) => write!(f, "Internal error: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for BotError {}
+
+impl From<OptionError> for BotError {
+    fn from(e: OptionError) -> Self {
+        BotError::Options(e)
+    }
+}
+
+impl From<serenity::Error> for BotError {
+    fn from(e: serenity::Error) -> Self {
+        BotError::Discord(e)
+    }
+}
+
+impl From<reqwest::Error> for BotError {
+    fn from(e: reqwest::Error) -> Self {
+        BotError::Http(e)
+    }
+}
+
+impl From<serde_json::Error> for BotError {
+    fn from(e: serde_json::Error) -> Self {
+        BotError::Json(e)
+    }
+}
+
+impl From<io::Error> for BotError {
+    fn from(e: io::Error) -> Self {
+        BotError::Io(e)
+    }
+}
+
+impl From<StorageError> for BotError {
+    fn from(e: StorageError) -> Self {
+        BotError::Storage(e)
+    }
+}
+
+/// The interaction a failure happened in
+#[derive(Clone, Copy)]
+pub enum Origin<'a> {
+    Command(&'a ApplicationCommandInteraction),
+    Component(&'a MessageComponentInteraction),
+    Modal(&'a ModalSubmitInteraction),
+}
+
+impl Origin<'_> {
+    /// Command name or custom id, whichever identifies the handler
+    pub fn name(&self) -> &str {
+        match self {
+            Origin::Command(command) => &command.data.name,
+            Origin::Component(component) => &component.data.custom_id,
+            Origin::Modal(modal) => &modal.data.custom_id,
+        }
+    }
+
+    pub fn user(&self) -> &User {
+        match self {
+            Origin::Command(command) => &command.user,
+            Origin::Component(component) => &component.user,
+            Origin::Modal(modal) => &modal.user,
+        }
+    }
+
+    pub fn guild_id(&self) -> Option<GuildId> {
+        match self {
+            Origin::Command(command) => command.guild_id,
+            Origin::Component(component) => component.guild_id,
+            Origin::Modal(modal) => modal.guild_id,
+        }
+    }
+
+    /// Show an ephemeral message to the user.
+    /// Handlers may already have acknowledged the interaction, in which case a followup is sent
+    async fn reply(&self, ctx: &Context, content: &str) -> serenity::Result<()> {
+        macro_rules! reply {
+            ($interaction:expr) => {{
+                let initial = $interaction
+                    .create_interaction_response(&ctx.http, |response| {
+                        response
+                            .kind(InteractionResponseType::ChannelMessageWithSource)
+                            .interaction_response_data(|m| m.content(content).ephemeral(true))
+                    })
+                    .await;
+                match initial {
+                    Ok(()) => Ok(()),
+                    Err(_) => $interaction
+                        .create_followup_message(&ctx.http, |m| m.content(content).ephemeral(true))
+                        .await
+                        .map(|_| ()),
+                }
+            }};
+        }
+
+        match self {
+            Origin::Command(command) => reply!(command),
+            Origin::Component(component) => reply!(component),
+            Origin::Modal(modal) => reply!(modal),
+        }
+    }
+}
+
+/// Log the error, tell the user what went wrong and report internal errors to the admins
+pub async fn handle(ctx: &Context, origin: Origin<'_>, error: BotError) {
+    let user = origin.user();
+    if error.is_internal() {
+        tracing::error!(
+            user = %user.id,
+            guild = ?origin.guild_id(),
+            "{} failed: {:?}",
+            origin.name(),
+            error
+        );
+    } else {
+        tracing::debug!(user = %user.id, "{} rejected: {}", origin.name(), error);
+    }
+
+    if let Err(why) = origin.reply(ctx, &error.user_message()).await {
+        tracing::warn!("Could not tell {} about the error: {}", user.name, why);
+    }
+
+    if error.is_internal() {
+        let mut context = format!("Used by {} ({})", user.tag(), user.id);
+        if let Some(guild_id) = origin.guild_id() {
+            context.push_str(&format!(" in guild {}", guild_id));
+        }
+        report(ctx, origin.name(), &context, &error).await;
+    }
+}
+
+/// Post an error to the admin channel, if one is configured
+pub async fn report(ctx: &Context, source: &str, context: &str, error: &BotError) {
+    let channel_id: ChannelId = match Config::get(ctx).await.admin.channel {
+        Some(channel_id) => channel_id,
+        None => return,
+    };
+    if let Err(why) = channel_id
+        .send_message(&ctx.http, |m| {
+            m.embed(|e| {
+                e.title(format!("Error in {}", source))
+                    .description(error)
+                    .footer(|f| f.text(context))
+                    .color(0xff0000)
+            })
+        })
+        .await
+    {
+        tracing::warn!("Could not report error to {}: {}", channel_id, why);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn user_errors_are_shown_as_is() {
+        let error = BotError::user("No kok called exam");
+        assert_eq!(error.user_message(), "No kok called exam");
+        assert!(!error.is_internal());
+    }
+
+    #[test]
+    fn internal_details_are_not_shown() {
+        let error = BotError::internal("QuizSessions not found in data");
+        assert!(error.is_internal());
+        assert!(!error.user_message().contains("QuizSessions"));
+
+        let error: BotError = OptionError::Missing("ticker").into();
+        assert!(!error.is_internal());
+        assert!(error.user_message().contains("ticker"));
+    }
+
+    #[test]
+    fn only_refused_requests_are_permanent() {
+        let refused = |status: u16, code: isize| {
+            let response = serenity::http::error::ErrorResponse {
+                status_code: status.try_into().unwrap(),
+                url: "https://discord.com/api/v10/channels/1/messages"
+                    .parse()
+                    .unwrap(),
+                error: serde_json::from_value(serde_json::json!({
+                    "code": code,
+                    "message": "Refused"
+                }))
+                .unwrap(),
+            };
+            BotError::from(serenity::Error::from(HttpError::UnsuccessfulRequest(
+                response,
+            )))
+        };
+        assert!(refused(403, 50007).is_permanent());
+        assert!(refused(404, 10003).is_permanent());
+        assert!(!refused(429, 0).is_permanent());
+        assert!(!refused(502, 0).is_permanent());
+        assert!(!BotError::internal("Timed out").is_permanent());
+    }
+}
@@ -1,53 +1,52 @@
-use std::env;
 use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
 
 use serenity::async_trait;
+use serenity::model::application::command::Command as ApplicationCommand;
 use serenity::model::application::interaction::Interaction;
 use serenity::model::prelude::{GuildId, Ready};
 use serenity::prelude::{Context, EventHandler};
 use tokio::fs::create_dir;
 use tracing::instrument;
 
-use crate::commands::food::modal_handler::handle_modal;
-use crate::commands::kok::save_big;
-use crate::utils::background_threads::ThreadStorage;
+use crate::config::Config;
+use crate::error::{BotError, Origin};
+use crate::registry::CommandRegistry;
+use crate::utils::{shutdown::Shutdown, supervisor::Supervisor};
 
 pub mod background_tasks;
 pub mod commands;
+pub mod config;
+pub mod error;
+pub mod monitoring;
+pub mod options;
+pub mod registry;
+pub mod storage;
 pub mod utils;
 
 pub struct Handler {
     pub loop_running: AtomicBool,
+    pub commands: CommandRegistry,
 }
 
-async fn nop() {}
-
 #[async_trait]
 impl EventHandler for Handler {
     async fn interaction_create(&self, ctx: Context, interaction: Interaction) {
-        if let Interaction::ApplicationCommand(command) = interaction {
-            let input = command.data.name.as_str();
-
-            tracing::debug!("Executing command {input}");
-            dir_macros::run_commands_async!("bot/src/commands" "commands" "run(&ctx,&command)");
-        } else if let Interaction::ModalSubmit(submit) = interaction {
-            let modal = submit.data.custom_id.as_str();
-            match modal {
-                "kok" => {
-                    tracing::debug!("Executing modal {modal}");
-                    save_big(&ctx, &submit).await;
-                }
-                "food" => {
-                    tracing::debug!("Executing modal {modal}");
-                    handle_modal(&ctx, &submit).await;
-                }
-                &_ => {
-                    tracing::debug!("Modal {modal} not handled");
-                }
+        // Held until the handler returns, so shutdown waits for it
+        let _in_flight = match Shutdown::get(&ctx).await.enter() {
+            Some(in_flight) => in_flight,
+            None => return turn_away(&ctx, &interaction).await,
+        };
+        match interaction {
+            Interaction::ApplicationCommand(command) => self.commands.run(&ctx, &command).await,
+            Interaction::Autocomplete(autocomplete) => {
+                self.commands.autocomplete(&ctx, &autocomplete).await
+            }
+            Interaction::MessageComponent(component) => {
+                self.commands.component(&ctx, &component).await
             }
-        } else {
-            tracing::debug!("Interaction not handled");
+            Interaction::ModalSubmit(submit) => self.commands.modal(&ctx, &submit).await,
+            _ => tracing::debug!("Interaction not handled"),
         }
     }
 
@@ -61,42 +60,95 @@ impl EventHandler for Handler {
             Err(_) => tracing::info!("Kok folder already exists"),
         }
 
-        // Check should not be neccessary as ready is only called once
+        // Ready is called again when the gateway reconnects, the supervisor only starts once
         let ctx = Arc::new(ctx);
-        let mut data = ctx.data.write().await;
-        let running = data
-            .get::<ThreadStorage>()
-            .expect("ThreadCounter not found in data")
-            .running;
-        if !running {
-            // Every background task has to handle its own setup, executing, and contiguous execution
-            dir_macros::long_running!("bot/src/background_tasks" "background_tasks" "run(ctx_cpy)");
-            data.insert::<ThreadStorage>(Arc::new(ThreadStorage { running: true }));
-        } else {
-            tracing::info!("Background tasks already running");
+        Supervisor::get(&ctx).await.start(ctx.clone()).await;
+
+        self.register_commands(&ctx).await;
+        tracing::info!("Setup complete");
+        println!("Bot ready");
+    }
+}
+
+impl Handler {
+    /// Register the commands in the guilds in `GUILD_ID`, leaving out the ones disabled there,
+    /// or globally if it is not set. Global commands show up in every guild, so the ones disabled
+    /// in a guild are turned away when used, see [CommandRegistry::run]. Commands not enabled
+    /// everywhere, like `/admin`, are also registered in the other guilds they are enabled in
+    async fn register_commands(&self, ctx: &Context) {
+        let config = Config::get(ctx).await;
+        for (guild_id, settings) in &config.guilds {
+            let listed = settings.commands.iter().flatten().chain(&settings.disabled);
+            for name in listed.filter(|name| self.commands.get(name).is_none()) {
+                tracing::warn!("guilds.{guild_id} lists {name}, but there is no such command");
+            }
+        }
+        let guilds = utils::command_guilds();
+        if guilds.is_empty() {
+            tracing::debug!("Registering commands globally");
+            let commands =
+                ApplicationCommand::set_global_application_commands(&ctx.http, |commands| {
+                    self.commands
+                        .create_commands(commands, |name| config.command_enabled(None, name))
+                })
+                .await;
+            if let Err(e) = commands {
+                tracing::error!("Global command registration failed: {:?}", e);
+            }
         }
-        let guild_id = GuildId(
-            env::var("GUILD_ID")
-                .expect("Expected GUILD_ID in environment")
-                .parse()
-                .expect("GUILD_ID must be an integer"),
-        );
 
-        tracing::debug!("Got Guild Id: {}", &guild_id);
+        for &guild_id in &guilds {
+            self.register_guild_commands(ctx, guild_id, |name| {
+                config.command_enabled(Some(guild_id), name)
+            })
+            .await;
+        }
+        if let Some(guild_id) = config.admin.guild.filter(|id| !guilds.contains(id)) {
+            self.register_guild_commands(ctx, guild_id, |name| {
+                config.command_enabled(Some(guild_id), name) && !config.command_enabled(None, name)
+            })
+            .await;
+        }
+    }
 
-        let commands = GuildId::set_application_commands(&guild_id, &ctx.http, |commands| {
-            dir_macros::register_commands!("bot/src/commands" "commands" "register(command)")
-        }).await;
+    async fn register_guild_commands<Filter>(
+        &self,
+        ctx: &Context,
+        guild_id: GuildId,
+        enabled: Filter,
+    ) where
+        Filter: Fn(&str) -> bool,
+    {
+        tracing::debug!("Registering commands in guild {}", &guild_id);
+        let commands = guild_id
+            .set_application_commands(&ctx.http, |commands| {
+                self.commands.create_commands(commands, enabled)
+            })
+            .await;
 
         match commands {
             Ok(_) => {
-                tracing::debug!("Command registration succeeded")
+                tracing::debug!("Command registration succeeded for guild {}", guild_id)
             }
             Err(e) => {
-                tracing::error!("Command registration failed: {:?}", e)
+                tracing::error!(
+                    "Command registration failed for guild {}: {:?}",
+                    guild_id,
+                    e
+                )
             }
         }
-        tracing::info!("Setup complete");
-        println!("Bot ready");
     }
 }
+
+/// Answer an interaction that arrived while shutting down
+async fn turn_away(ctx: &Context, interaction: &Interaction) {
+    let origin = match interaction {
+        Interaction::ApplicationCommand(command) => Origin::Command(command),
+        Interaction::MessageComponent(component) => Origin::Component(component),
+        Interaction::ModalSubmit(submit) => Origin::Modal(submit),
+        _ => return,
+    };
+    let error = BotError::user("I am restarting, please try again in a minute");
+    error::handle(ctx, origin, error).await;
+}
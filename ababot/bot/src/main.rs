@@ -1,10 +1,16 @@
 use std::{
     env,
     sync::{atomic::AtomicBool, Arc},
+    time::Duration,
 };
 
 use bot::{
-    utils::{self, background_threads::ThreadStorage, gpgpu::gpu::gpu_handler},
+    background_tasks::{self, reminders::Reminders},
+    commands::{self, quiz::QuizSessions},
+    config::Config,
+    monitoring::{self, metrics::Metrics, Monitor},
+    storage::Storage,
+    utils::{self, gpgpu::gpu::gpu_handler, shutdown::Shutdown},
     Handler,
 };
 use serenity::{prelude::GatewayIntents, Client};
@@ -16,11 +22,33 @@ static GLOBAL: MiMalloc = MiMalloc;
 
 #[tokio::main]
 async fn main() {
-    let (subscriber, _guard) = utils::get_logger();
+    // Container health check, see docker-compose.yml
+    if env::args().nth(1).as_deref() == Some("healthcheck") {
+        let healthy = match Config::from_env() {
+            Ok(config) => monitoring::healthcheck(config.monitoring.address).await,
+            Err(e) => {
+                println!("{e}");
+                false
+            }
+        };
+        std::process::exit(if healthy { 0 } else { 1 });
+    }
+
+    let (subscriber, guard) = utils::get_logger();
     tracing::subscriber::set_global_default(subscriber)
         .expect("Failed to set global default subscriber");
     tracing::trace!("Log setup complete");
 
+    let config = Arc::new(Config::from_env().unwrap_or_else(|e| {
+        tracing::error!("{e}");
+        panic!("{e}");
+    }));
+
+    let storage = Storage::open(&config.storage.path).unwrap_or_else(|e| {
+        tracing::error!("Could not open database: {e}");
+        panic!("Could not open database: {e}");
+    });
+
     // Configure the client with your Discord bot token in the environment.
     let token = env::var("DISCORD_TOKEN").expect("Expected a token in the environment");
 
@@ -28,26 +56,72 @@ async fn main() {
     let mut client = Client::builder(token, GatewayIntents::all())
         .event_handler(Handler {
             loop_running: AtomicBool::new(false),
+            commands: commands::registry(),
         })
         .await
         .expect("Error creating client");
 
-    {
-        let thread_counter = Arc::new(ThreadStorage { running: false });
-
-        let (sender, mut receiver) = tokio::sync::mpsc::channel::<utils::gpgpu::channels::GPU>(100);
+    let supervisor = Arc::new(background_tasks::supervisor());
+    let shutdown = Arc::new(Shutdown::new());
+    let metrics = Arc::new(Metrics::new());
+    let grace_period = Duration::from_secs(config.shutdown.grace_period);
+    let monitoring_config = config.monitoring.clone();
+    let gpu = {
+        let (sender, mut receiver) = tokio::sync::mpsc::channel::<utils::gpgpu::channels::GPU>(
+            utils::gpgpu::channels::GPU_QUEUE_SIZE,
+        );
         let sender_arc = Arc::new(sender);
-        tokio::spawn(async move {
+        let gpu = tokio::spawn(async move {
             if (gpu_handler(&mut receiver)).await.is_err() {
                 tracing::error!("GPU handler failed");
             }
         });
 
         let mut data = client.data.write().await;
-        data.insert::<ThreadStorage>(thread_counter);
+        data.insert::<utils::supervisor::Supervisor>(supervisor.clone());
+        data.insert::<Shutdown>(shutdown.clone());
+        data.insert::<Metrics>(metrics.clone());
+
+        if monitoring_config.enabled {
+            let monitor = Monitor {
+                metrics,
+                supervisor: supervisor.clone(),
+                shards: client.shard_manager.lock().await.runners.clone(),
+                gpu: sender_arc.clone(),
+            };
+            tokio::spawn(monitoring::serve(
+                monitoring_config.address,
+                Arc::new(monitor),
+                shutdown.clone(),
+            ));
+        }
 
         data.insert::<utils::gpgpu::channels::GPU>(sender_arc);
-    }
+        data.insert::<QuizSessions>(Arc::default());
+        data.insert::<Config>(config);
+        data.insert::<Storage>(storage.clone());
+        data.insert::<Reminders>(Arc::new(Reminders::new(storage.clone())));
+        data.insert::<utils::clock::SharedClock>(utils::clock::SharedClock::default());
+        gpu
+    };
+
+    // Stop taking interactions on SIGTERM and let the running ones finish before disconnecting
+    let shard_manager = client.shard_manager.clone();
+    tokio::spawn(async move {
+        utils::shutdown::signal().await;
+        tracing::info!(
+            "Shutting down, waiting up to {}s for {} running interactions",
+            grace_period.as_secs(),
+            shutdown.in_flight()
+        );
+        if !shutdown.drain(grace_period).await {
+            tracing::warn!(
+                "{} interactions still running, shutting down anyway",
+                shutdown.in_flight()
+            );
+        }
+        shard_manager.lock().await.shutdown_all().await;
+    });
 
     // Finally, start a single shard, and start listening to events.
     //
@@ -56,4 +130,15 @@ async fn main() {
     if let Err(why) = client.start().await {
         tracing::error!("Error running client: {why}");
     }
+
+    supervisor.shutdown().await;
+    // Only stops between tasks, the handler never yields in the middle of GPU work
+    gpu.abort();
+    let _ = gpu.await;
+    if let Err(why) = storage.flush().await {
+        tracing::error!("Could not flush database: {why}");
+    }
+    tracing::info!("Shutdown complete");
+    // Last, so everything above is written to the log
+    drop(guard);
 }
@@ -0,0 +1,246 @@
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Write},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use serenity::prelude::{Context, TypeMapKey};
+
+use crate::error::BotResult;
+
+/// Upper bounds of the command latency buckets in seconds.
+/// Quizzes keep their command running for minutes, hence the long tail
+const LATENCY_BUCKETS: [f64; 10] = [0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 300.0];
+
+/// How a command invocation ended, the `result` label of the counters
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Outcome {
+    Ok,
+    /// The user made a mistake, see [BotError::is_internal](crate::error::BotError::is_internal)
+    Rejected,
+    Failed,
+}
+
+impl Outcome {
+    pub fn of<T>(result: &BotResult<T>) -> Self {
+        match result {
+            Ok(_) => Outcome::Ok,
+            Err(e) if e.is_internal() => Outcome::Failed,
+            Err(_) => Outcome::Rejected,
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            Outcome::Ok => "ok",
+            Outcome::Rejected => "rejected",
+            Outcome::Failed => "failed",
+        }
+    }
+}
+
+#[derive(Default)]
+struct Histogram {
+    /// Observations per bucket, not cumulative
+    buckets: [u64; LATENCY_BUCKETS.len()],
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn observe(&mut self, seconds: f64) {
+        if let Some(i) = LATENCY_BUCKETS.iter().position(|&le| seconds <= le) {
+            self.buckets[i] += 1;
+        }
+        self.sum += seconds;
+        self.count += 1;
+    }
+}
+
+#[derive(Default)]
+struct CommandMetrics {
+    invocations: BTreeMap<Outcome, u64>,
+    latency: Histogram,
+}
+
+/// Counters exposed on `/metrics` in the Prometheus text format.
+/// Gauges that can be read on demand, like the GPU queue depth, are added by the server
+#[derive(Default)]
+pub struct Metrics {
+    commands: Mutex<BTreeMap<String, CommandMetrics>>,
+    fetch_failures: Mutex<BTreeMap<&'static str, u64>>,
+}
+
+impl TypeMapKey for Metrics {
+    type Value = Arc<Metrics>;
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Metrics::default()
+    }
+
+    pub fn command(&self, name: &str, outcome: Outcome, elapsed: Duration) {
+        let mut commands = self.commands.lock().unwrap_or_else(|e| e.into_inner());
+        let command = commands.entry(name.to_string()).or_default();
+        *command.invocations.entry(outcome).or_default() += 1;
+        command.latency.observe(elapsed.as_secs_f64());
+    }
+
+    /// A background task could not fetch what it posts
+    pub fn fetch_failed(&self, task: &'static str) {
+        *self
+            .fetch_failures
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .entry(task)
+            .or_default() += 1;
+    }
+
+    pub fn render(&self, out: &mut String) -> fmt::Result {
+        let commands = self.commands.lock().unwrap_or_else(|e| e.into_inner());
+
+        header(
+            out,
+            "ababot_command_invocations_total",
+            "counter",
+            "Slash commands run, by result",
+        )?;
+        for (name, command) in commands.iter() {
+            for (outcome, count) in &command.invocations {
+                writeln!(
+                    out,
+                    "ababot_command_invocations_total{{command=\"{}\",result=\"{}\"}} {}",
+                    name,
+                    outcome.label(),
+                    count
+                )?;
+            }
+        }
+
+        header(
+            out,
+            "ababot_command_duration_seconds",
+            "histogram",
+            "Time from receiving a slash command until its handler returned",
+        )?;
+        for (name, command) in commands.iter() {
+            let latency = &command.latency;
+            let mut cumulative = 0;
+            for (le, count) in LATENCY_BUCKETS.iter().zip(latency.buckets) {
+                cumulative += count;
+                writeln!(
+                    out,
+                    "ababot_command_duration_seconds_bucket{{command=\"{}\",le=\"{}\"}} {}",
+                    name, le, cumulative
+                )?;
+            }
+            writeln!(
+                out,
+                "ababot_command_duration_seconds_bucket{{command=\"{}\",le=\"+Inf\"}} {}",
+                name, latency.count
+            )?;
+            writeln!(
+                out,
+                "ababot_command_duration_seconds_sum{{command=\"{}\"}} {}",
+                name, latency.sum
+            )?;
+            writeln!(
+                out,
+                "ababot_command_duration_seconds_count{{command=\"{}\"}} {}",
+                name, latency.count
+            )?;
+        }
+        drop(commands);
+
+        header(
+            out,
+            "ababot_fetch_failures_total",
+            "counter",
+            "Failed HTTP fetches in background tasks",
+        )?;
+        for (task, count) in self
+            .fetch_failures
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+        {
+            writeln!(
+                out,
+                "ababot_fetch_failures_total{{task=\"{}\"}} {}",
+                task, count
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Get the metrics from the context
+    /// # Panics
+    /// Panics if the metrics have not been inserted at startup
+    pub async fn get(ctx: &Context) -> Arc<Metrics> {
+        ctx.data
+            .read()
+            .await
+            .get::<Metrics>()
+            .expect("Metrics not found in data")
+            .clone()
+    }
+}
+
+pub fn header(out: &mut String, name: &str, kind: &str, help: &str) -> fmt::Result {
+    writeln!(out, "# HELP {} {}", name, help)?;
+    writeln!(out, "# TYPE {} {}", name, kind)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::BotError;
+
+    #[test]
+    fn histogram_buckets_are_cumulative() {
+        let metrics = Metrics::new();
+        metrics.command("ping", Outcome::Ok, Duration::from_millis(50));
+        metrics.command("ping", Outcome::Ok, Duration::from_millis(400));
+        metrics.command(
+            "ping",
+            Outcome::of::<()>(&Err(BotError::user("No"))),
+            Duration::from_secs(1000),
+        );
+
+        let mut out = String::new();
+        metrics.render(&mut out).unwrap();
+        assert!(
+            out.contains("ababot_command_invocations_total{command=\"ping\",result=\"ok\"} 2\n")
+        );
+        assert!(out.contains(
+            "ababot_command_invocations_total{command=\"ping\",result=\"rejected\"} 1\n"
+        ));
+        assert!(
+            out.contains("ababot_command_duration_seconds_bucket{command=\"ping\",le=\"0.1\"} 1\n")
+        );
+        assert!(
+            out.contains("ababot_command_duration_seconds_bucket{command=\"ping\",le=\"0.5\"} 2\n")
+        );
+        assert!(
+            out.contains("ababot_command_duration_seconds_bucket{command=\"ping\",le=\"300\"} 2\n")
+        );
+        assert!(out
+            .contains("ababot_command_duration_seconds_bucket{command=\"ping\",le=\"+Inf\"} 3\n"));
+        assert!(out.contains("ababot_command_duration_seconds_count{command=\"ping\"} 3\n"));
+    }
+
+    #[test]
+    fn fetch_failures_are_counted_per_task() {
+        let metrics = Metrics::new();
+        metrics.fetch_failed("yr");
+        metrics.fetch_failed("yr");
+        metrics.fetch_failed("abakus");
+
+        let mut out = String::new();
+        metrics.render(&mut out).unwrap();
+        assert!(out.contains("ababot_fetch_failures_total{task=\"abakus\"} 1\n"));
+        assert!(out.contains("ababot_fetch_failures_total{task=\"yr\"} 2\n"));
+    }
+}
@@ -0,0 +1,194 @@
+pub mod metrics;
+
+use std::{collections::HashMap, convert::Infallible, fmt::Write, net::SocketAddr, sync::Arc};
+
+use hyper::{
+    header::CONTENT_TYPE,
+    service::{make_service_fn, service_fn},
+    Body, Method, Request, Response, Server, StatusCode,
+};
+use serenity::{
+    client::bridge::gateway::{ShardId, ShardRunnerInfo},
+    gateway::ConnectionStage,
+    prelude::Mutex,
+};
+use tokio::sync::mpsc::Sender;
+
+use crate::utils::{
+    gpgpu::channels::{GPU, GPU_QUEUE_SIZE},
+    shutdown::Shutdown,
+    supervisor::{Supervisor, TaskState},
+};
+
+use metrics::Metrics;
+
+/// Everything `/healthz` and `/metrics` look at
+pub struct Monitor {
+    pub metrics: Arc<Metrics>,
+    pub supervisor: Arc<Supervisor>,
+    /// From [ShardManager::runners](serenity::client::bridge::gateway::ShardManager::runners)
+    pub shards: Arc<Mutex<HashMap<ShardId, ShardRunnerInfo>>>,
+    pub gpu: Arc<Sender<GPU>>,
+}
+
+impl Monitor {
+    /// One line per check, and whether all of them passed
+    async fn health(&self) -> (bool, String) {
+        let mut healthy = true;
+        let mut report = String::new();
+
+        let shards = self.shards.lock().await;
+        let disconnected: Vec<_> = shards
+            .iter()
+            .filter(|(_, runner)| runner.stage != ConnectionStage::Connected)
+            .map(|(id, runner)| format!("shard {} {}", id.0, runner.stage))
+            .collect();
+        let gateway = if shards.is_empty() {
+            "no shards running".to_string()
+        } else if disconnected.is_empty() {
+            "ok".to_string()
+        } else {
+            disconnected.join(", ")
+        };
+        drop(shards);
+        healthy &= gateway == "ok";
+        let _ = writeln!(report, "gateway: {}", gateway);
+
+        // A failed run is tried again on the next one, so it is reported without failing the check
+        let statuses = self.supervisor.statuses();
+        let failing: Vec<_> = statuses
+            .iter()
+            .filter_map(|s| match s.state {
+                TaskState::Idle | TaskState::Running => None,
+                state => Some(format!("{} {:?}", s.name, state).to_lowercase()),
+            })
+            .collect();
+        healthy &= failing.is_empty();
+        let _ = writeln!(
+            report,
+            "background tasks: {}",
+            if failing.is_empty() {
+                "ok".to_string()
+            } else {
+                failing.join(", ")
+            }
+        );
+        let failed: Vec<_> = statuses
+            .iter()
+            .filter(|s| s.last_failure.is_some())
+            .map(|s| s.name)
+            .collect();
+        if !failed.is_empty() {
+            let _ = writeln!(report, "last run failed: {}", failed.join(", "));
+        }
+
+        // The receiver is dropped when the handler exits
+        let gpu = !self.gpu.is_closed();
+        healthy &= gpu;
+        let _ = writeln!(report, "gpu: {}", if gpu { "ok" } else { "stopped" });
+
+        (healthy, report)
+    }
+
+    fn metrics(&self) -> Result<String, std::fmt::Error> {
+        let mut out = String::new();
+        self.metrics.render(&mut out)?;
+        metrics::header(
+            &mut out,
+            "ababot_gpu_queue_depth",
+            "gauge",
+            "GPU tasks waiting for the handler",
+        )?;
+        writeln!(
+            out,
+            "ababot_gpu_queue_depth {}",
+            GPU_QUEUE_SIZE - self.gpu.capacity()
+        )?;
+        metrics::header(
+            &mut out,
+            "ababot_job_last_run_success",
+            "gauge",
+            "Whether the last run of a background task succeeded, once it has run",
+        )?;
+        for status in self.supervisor.statuses() {
+            if status.last_run.is_some() {
+                writeln!(
+                    out,
+                    "ababot_job_last_run_success{{task=\"{}\"}} {}",
+                    status.name,
+                    u8::from(status.last_failure.is_none())
+                )?;
+            }
+        }
+        Ok(out)
+    }
+
+    async fn respond(&self, request: Request<Body>) -> Response<Body> {
+        let (status, body) = match (request.method(), request.uri().path()) {
+            (&Method::GET, "/healthz") => match self.health().await {
+                (true, report) => (StatusCode::OK, report),
+                (false, report) => (StatusCode::SERVICE_UNAVAILABLE, report),
+            },
+            (&Method::GET, "/metrics") => match self.metrics() {
+                Ok(metrics) => (StatusCode::OK, metrics),
+                Err(_) => (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Could not render metrics".to_string(),
+                ),
+            },
+            _ => (StatusCode::NOT_FOUND, "Not found".to_string()),
+        };
+        Response::builder()
+            .status(status)
+            .header(CONTENT_TYPE, "text/plain; version=0.0.4")
+            .body(Body::from(body))
+            .unwrap_or_default()
+    }
+}
+
+/// Serve `/healthz` and `/metrics` until shutdown starts
+pub async fn serve(address: SocketAddr, monitor: Arc<Monitor>, shutdown: Arc<Shutdown>) {
+    let make_service = make_service_fn(move |_| {
+        let monitor = monitor.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request| {
+                let monitor = monitor.clone();
+                async move { Ok::<_, Infallible>(monitor.respond(request).await) }
+            }))
+        }
+    });
+
+    let server = match Server::try_bind(&address) {
+        Ok(server) => server,
+        Err(e) => {
+            tracing::error!("Could not serve health and metrics on {}: {}", address, e);
+            return;
+        }
+    };
+    tracing::info!("Serving health and metrics on {}", address);
+    let server = server
+        .serve(make_service)
+        .with_graceful_shutdown(async move { shutdown.stopped().await });
+    if let Err(e) = server.await {
+        tracing::error!("Health and metrics server failed: {}", e);
+    }
+}
+
+/// Ask a running bot whether it is healthy, for the container health check.
+/// The image has no shell or curl, so the binary checks itself
+pub async fn healthcheck(address: SocketAddr) -> bool {
+    let url = format!("http://127.0.0.1:{}/healthz", address.port());
+    match reqwest::get(url).await {
+        Ok(response) => {
+            let healthy = response.status().is_success();
+            if let Ok(report) = response.text().await {
+                print!("{}", report);
+            }
+            healthy
+        }
+        Err(e) => {
+            println!("Bot is not responding: {}", e);
+            false
+        }
+    }
+}
@@ -0,0 +1,301 @@
+use std::fmt;
+
+use serenity::{
+    builder::{CreateApplicationCommand, CreateApplicationCommandOption},
+    model::{
+        channel::{Attachment, PartialChannel},
+        guild::Role,
+        prelude::{
+            command::CommandOptionType,
+            interaction::application_command::{
+                ApplicationCommandInteraction, CommandDataOption, CommandDataOptionValue,
+            },
+        },
+        user::User,
+    },
+};
+
+/// Derive [CommandOptions] for a struct of options or an enum of subcommands.
+///
+/// Struct fields become options, named after the field, with the doc comment as description.
+/// `Option<T>` fields are optional, everything else is required.
+/// Enum variants become subcommands, either without options or wrapping a type deriving
+/// [CommandOptions]. Wrapping an enum makes the variant a subcommand group.
+///
+/// Fields and variants can be tweaked with `#[option(...)]`:
+/// * `name = "..."` overrides the name
+/// * `min = ...` and `max = ...` limit integer and number values
+/// * `autocomplete` enables autocompletion
+/// * `choice("label", value)` adds a predefined choice, can be repeated
+pub use dir_macros::CommandOptions;
+
+/// A typed value Discord can send as a command option
+pub trait OptionValue: Sized {
+    const KIND: CommandOptionType;
+
+    fn from_option(option: &CommandDataOption) -> Option<Self>;
+}
+
+/// Options of a command, registered and parsed from the same definition.
+/// Implemented with `#[derive(CommandOptions)]`
+pub trait CommandOptions: Sized {
+    /// Kind of the option wrapping these options when used in a subcommand enum
+    const NESTED_KIND: CommandOptionType;
+
+    fn options() -> Vec<CreateApplicationCommandOption>;
+
+    fn from_options(options: &[CommandDataOption]) -> Result<Self, OptionError>;
+
+    fn register(command: &mut CreateApplicationCommand) -> &mut CreateApplicationCommand {
+        Self::options()
+            .into_iter()
+            .fold(command, |command, option| command.add_option(option))
+    }
+
+    fn parse(command: &ApplicationCommandInteraction) -> Result<Self, OptionError> {
+        Self::from_options(&command.data.options)
+    }
+}
+
+/// The options sent by Discord do not match the registered ones.
+/// Usually caused by commands registered by an older version of the bot.
+/// Converts into [BotError](crate::error::BotError), so `?` answers the user
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OptionError {
+    Missing(&'static str),
+    WrongType {
+        name: &'static str,
+        expected: CommandOptionType,
+    },
+    MissingSubcommand,
+    UnknownSubcommand(String),
+}
+
+impl fmt::Display for OptionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OptionError::Missing(name) => write!(f, "Missing option {}", name),
+            OptionError::WrongType { name, expected } => {
+                write!(f, "Option {} should be of type {:?}", name, expected)
+            }
+            OptionError::MissingSubcommand => write!(f, "Missing subcommand"),
+            OptionError::UnknownSubcommand(name) => write!(f, "Unknown subcommand {}", name),
+        }
+    }
+}
+
+impl std::error::Error for OptionError {}
+
+/// Parse an option that may be left out
+pub fn optional<T: OptionValue>(
+    options: &[CommandDataOption],
+    name: &'static str,
+) -> Result<Option<T>, OptionError> {
+    match options.iter().find(|option| option.name == name) {
+        Some(option) => T::from_option(option)
+            .map(Some)
+            .ok_or(OptionError::WrongType {
+                name,
+                expected: T::KIND,
+            }),
+        None => Ok(None),
+    }
+}
+
+/// Parse an option that has to be present
+pub fn required<T: OptionValue>(
+    options: &[CommandDataOption],
+    name: &'static str,
+) -> Result<T, OptionError> {
+    optional(options, name)?.ok_or(OptionError::Missing(name))
+}
+
+impl OptionValue for String {
+    const KIND: CommandOptionType = CommandOptionType::String;
+
+    fn from_option(option: &CommandDataOption) -> Option<Self> {
+        option.value.as_ref()?.as_str().map(str::to_string)
+    }
+}
+
+impl OptionValue for i64 {
+    const KIND: CommandOptionType = CommandOptionType::Integer;
+
+    fn from_option(option: &CommandDataOption) -> Option<Self> {
+        option.value.as_ref()?.as_i64()
+    }
+}
+
+impl OptionValue for f64 {
+    const KIND: CommandOptionType = CommandOptionType::Number;
+
+    fn from_option(option: &CommandDataOption) -> Option<Self> {
+        option.value.as_ref()?.as_f64()
+    }
+}
+
+impl OptionValue for bool {
+    const KIND: CommandOptionType = CommandOptionType::Boolean;
+
+    fn from_option(option: &CommandDataOption) -> Option<Self> {
+        option.value.as_ref()?.as_bool()
+    }
+}
+
+impl OptionValue for Attachment {
+    const KIND: CommandOptionType = CommandOptionType::Attachment;
+
+    fn from_option(option: &CommandDataOption) -> Option<Self> {
+        match option.resolved.as_ref()? {
+            CommandDataOptionValue::Attachment(attachment) => Some(attachment.clone()),
+            _ => None,
+        }
+    }
+}
+
+impl OptionValue for User {
+    const KIND: CommandOptionType = CommandOptionType::User;
+
+    fn from_option(option: &CommandDataOption) -> Option<Self> {
+        match option.resolved.as_ref()? {
+            CommandDataOptionValue::User(user, _) => Some(user.clone()),
+            _ => None,
+        }
+    }
+}
+
+impl OptionValue for PartialChannel {
+    const KIND: CommandOptionType = CommandOptionType::Channel;
+
+    fn from_option(option: &CommandDataOption) -> Option<Self> {
+        match option.resolved.as_ref()? {
+            CommandDataOptionValue::Channel(channel) => Some(channel.clone()),
+            _ => None,
+        }
+    }
+}
+
+impl OptionValue for Role {
+    const KIND: CommandOptionType = CommandOptionType::Role;
+
+    fn from_option(option: &CommandDataOption) -> Option<Self> {
+        match option.resolved.as_ref()? {
+            CommandDataOptionValue::Role(role) => Some(role.clone()),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use serenity::model::prelude::{
+        command::CommandOptionType, interaction::application_command::CommandDataOption,
+    };
+
+    use super::{CommandOptions, OptionError};
+
+    #[derive(Debug, PartialEq, CommandOptions)]
+    struct Roll {
+        /// Lowest possible value
+        #[option(min = 0, max = 99)]
+        min: i64,
+        /// Whether everyone can see the result
+        public: Option<bool>,
+    }
+
+    #[derive(Debug, PartialEq, CommandOptions)]
+    enum Save {
+        /// Save a small file
+        Small(Roll),
+        /// Save a big file
+        BigFile,
+    }
+
+    #[derive(Debug, PartialEq, CommandOptions)]
+    enum Kok {
+        /// Add your contribution
+        Save(Save),
+        /// How to use kok
+        #[option(name = "hjelp")]
+        Help,
+    }
+
+    fn options(value: serde_json::Value) -> Vec<CommandDataOption> {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn registers_struct_fields_as_options() {
+        let options = Roll::options();
+        assert_eq!(options.len(), 2);
+        assert_eq!(options[0].0["name"], "min");
+        assert_eq!(options[0].0["description"], "Lowest possible value");
+        assert_eq!(options[0].0["type"], CommandOptionType::Integer as u8);
+        assert_eq!(options[0].0["required"], true);
+        assert_eq!(options[0].0["min_value"], 0);
+        assert_eq!(options[1].0["required"], false);
+    }
+
+    #[test]
+    fn registers_enums_as_subcommands() {
+        let options = Kok::options();
+        assert_eq!(options[0].0["name"], "save");
+        assert_eq!(
+            options[0].0["type"],
+            CommandOptionType::SubCommandGroup as u8
+        );
+        assert_eq!(options[0].0["options"][0]["name"], "small");
+        assert_eq!(options[0].0["options"][1]["name"], "big_file");
+        assert_eq!(
+            options[0].0["options"][0]["type"],
+            CommandOptionType::SubCommand as u8
+        );
+        assert_eq!(options[1].0["name"], "hjelp");
+    }
+
+    #[test]
+    fn parses_nested_subcommands() {
+        let parsed = Kok::from_options(&options(json!([{
+            "name": "save",
+            "type": 2,
+            "options": [{
+                "name": "small",
+                "type": 1,
+                "options": [{"name": "min", "type": 4, "value": 3}]
+            }]
+        }])));
+        assert_eq!(
+            parsed,
+            Ok(Kok::Save(Save::Small(Roll {
+                min: 3,
+                public: None
+            })))
+        );
+    }
+
+    #[test]
+    fn reports_missing_and_mistyped_options() {
+        let missing = Roll::from_options(&options(
+            json!([{"name": "public", "type": 5, "value": true}]),
+        ));
+        assert_eq!(missing, Err(OptionError::Missing("min")));
+
+        let mistyped = Roll::from_options(&options(
+            json!([{"name": "min", "type": 3, "value": "three"}]),
+        ));
+        assert_eq!(
+            mistyped,
+            Err(OptionError::WrongType {
+                name: "min",
+                expected: CommandOptionType::Integer
+            })
+        );
+
+        let unknown = Kok::from_options(&options(json!([{"name": "delete", "type": 1}])));
+        assert_eq!(
+            unknown,
+            Err(OptionError::UnknownSubcommand("delete".to_string()))
+        );
+    }
+}
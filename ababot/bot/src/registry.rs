@@ -0,0 +1,437 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    time::Instant,
+};
+
+use serenity::{
+    async_trait,
+    builder::{CreateApplicationCommand, CreateApplicationCommands},
+    model::prelude::interaction::{
+        application_command::{ApplicationCommandInteraction, CommandDataOption},
+        autocomplete::AutocompleteInteraction,
+        message_component::MessageComponentInteraction,
+        modal::ModalSubmitInteraction,
+        InteractionResponseType,
+    },
+    prelude::Context,
+};
+use tracing::instrument;
+
+use crate::config::Config;
+use crate::error::{self, BotError, BotResult, Origin};
+use crate::monitoring::metrics::{Metrics, Outcome};
+
+const CUSTOM_ID_SEPARATOR: char = ':';
+/// Discord limit on the number of autocomplete suggestions
+const MAX_CHOICES: usize = 25;
+
+/// Custom id of a modal or message component on the form `prefix:action:arg:arg...`
+///
+/// The prefix decides which command the interaction is routed to,
+/// so components keep working without a collector waiting for them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CustomId<'a> {
+    pub prefix: &'a str,
+    pub action: &'a str,
+    pub args: Vec<&'a str>,
+}
+
+impl<'a> CustomId<'a> {
+    pub fn parse(id: &'a str) -> Self {
+        let mut parts = id.split(CUSTOM_ID_SEPARATOR);
+        CustomId {
+            prefix: parts.next().unwrap_or_default(),
+            action: parts.next().unwrap_or_default(),
+            args: parts.collect(),
+        }
+    }
+
+    pub fn arg(&self, index: usize) -> Option<&'a str> {
+        self.args.get(index).copied()
+    }
+}
+
+/// Build a custom id that routes back to the command owning `prefix`
+pub fn custom_id(prefix: &str, action: &str, args: &[&str]) -> String {
+    let mut id = format!("{prefix}{CUSTOM_ID_SEPARATOR}{action}");
+    for arg in args {
+        id.push(CUSTOM_ID_SEPARATOR);
+        id.push_str(arg);
+    }
+    id
+}
+
+/// A slash command the bot can register and respond to
+///
+/// Only [Command::name], [Command::register] and [Command::run] have to be implemented,
+/// the remaining hooks default to doing nothing.
+/// Errors returned from the handlers are shown to the user, see [error::handle].
+#[async_trait]
+pub trait Command: Send + Sync {
+    /// The slash command name, used both when registering and when dispatching
+    fn name(&self) -> &'static str;
+
+    /// Describe the command and its options.
+    /// The name is set by the registry, so it does not have to be set here
+    fn register<'a>(
+        &self,
+        command: &'a mut CreateApplicationCommand,
+    ) -> &'a mut CreateApplicationCommand;
+
+    async fn run(&self, ctx: &Context, command: &ApplicationCommandInteraction) -> BotResult;
+
+    /// Suggest values for the option the user is typing in, see [focused_option] and [suggest]
+    async fn autocomplete(&self, _ctx: &Context, _interaction: &AutocompleteInteraction) {}
+
+    /// Custom id prefixes of the modals and components this command handles
+    fn custom_id_prefixes(&self) -> Vec<&'static str> {
+        vec![self.name()]
+    }
+
+    async fn component(
+        &self,
+        _ctx: &Context,
+        _interaction: &MessageComponentInteraction,
+        _id: CustomId<'_>,
+    ) -> BotResult {
+        Ok(())
+    }
+
+    async fn modal(
+        &self,
+        _ctx: &Context,
+        _interaction: &ModalSubmitInteraction,
+        _id: CustomId<'_>,
+    ) -> BotResult {
+        Ok(())
+    }
+}
+
+/// Collection of every command the bot knows about, keyed by slash command name
+#[derive(Default)]
+pub struct CommandRegistry {
+    commands: BTreeMap<&'static str, Box<dyn Command>>,
+    /// Custom id prefix to the name of the command handling it
+    routes: HashMap<&'static str, &'static str>,
+}
+
+impl CommandRegistry {
+    pub fn new() -> Self {
+        CommandRegistry {
+            commands: BTreeMap::new(),
+            routes: HashMap::new(),
+        }
+    }
+
+    /// Add a command to the registry and route its custom id prefixes to it
+    /// # Panics
+    /// Panics if a command with the same name or a custom id prefix is already registered
+    pub fn add<C>(&mut self, command: C)
+    where
+        C: Command + 'static,
+    {
+        let name = command.name();
+        for prefix in command.custom_id_prefixes() {
+            if let Some(other) = self.routes.insert(prefix, name) {
+                tracing::error!("Custom id prefix {prefix} used by both {other} and {name}");
+                panic!("Custom id prefix {prefix} used by both {other} and {name}");
+            }
+        }
+        if self.commands.insert(name, Box::new(command)).is_some() {
+            tracing::error!("Command {name} registered twice");
+            panic!("Command {name} registered twice");
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn Command> {
+        self.commands.get(name).map(|c| c.as_ref())
+    }
+
+    /// Names of all registered commands in alphabetical order
+    pub fn names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.commands.keys().copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.commands.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    /// Add every command accepted by `enabled` to the builder
+    pub fn create_commands<'a, Filter>(
+        &self,
+        commands: &'a mut CreateApplicationCommands,
+        enabled: Filter,
+    ) -> &'a mut CreateApplicationCommands
+    where
+        Filter: Fn(&str) -> bool,
+    {
+        for (name, command) in self.commands.iter().filter(|(name, _)| enabled(name)) {
+            tracing::debug!("Registering command {name}");
+            commands.create_application_command(|c| command.register(c).name(name));
+        }
+        commands
+    }
+
+    #[instrument(skip(self, ctx, command), fields(name = %command.data.name))]
+    pub async fn run(&self, ctx: &Context, command: &ApplicationCommandInteraction) {
+        match self.get(&command.data.name) {
+            // Global commands show up in every guild, including the ones they are disabled in
+            Some(_)
+                if !Config::get(ctx)
+                    .await
+                    .command_enabled(command.guild_id, &command.data.name) =>
+            {
+                let error = BotError::user("That command is not enabled in this server");
+                error::handle(ctx, Origin::Command(command), error).await;
+            }
+            Some(c) => {
+                tracing::debug!("Executing command {}", command.data.name);
+                let started = Instant::now();
+                let result = c.run(ctx, command).await;
+                Metrics::get(ctx).await.command(
+                    &command.data.name,
+                    Outcome::of(&result),
+                    started.elapsed(),
+                );
+                if let Err(e) = result {
+                    error::handle(ctx, Origin::Command(command), e).await;
+                }
+            }
+            None => tracing::warn!("Command {} not found", command.data.name),
+        }
+    }
+
+    #[instrument(skip(self, ctx, interaction), fields(name = %interaction.data.name))]
+    pub async fn autocomplete(&self, ctx: &Context, interaction: &AutocompleteInteraction) {
+        match self.get(&interaction.data.name) {
+            Some(c) => c.autocomplete(ctx, interaction).await,
+            None => tracing::warn!("Command {} not found", interaction.data.name),
+        }
+    }
+
+    /// The command owning the prefix of a custom id
+    fn route(&self, id: &CustomId) -> Option<&dyn Command> {
+        self.routes.get(id.prefix).and_then(|name| self.get(name))
+    }
+
+    #[instrument(skip(self, ctx, interaction), fields(id = %interaction.data.custom_id))]
+    pub async fn component(&self, ctx: &Context, interaction: &MessageComponentInteraction) {
+        let id = CustomId::parse(&interaction.data.custom_id);
+        match self.route(&id) {
+            Some(c) => {
+                tracing::debug!("Executing component {}", interaction.data.custom_id);
+                if let Err(e) = c.component(ctx, interaction, id).await {
+                    error::handle(ctx, Origin::Component(interaction), e).await;
+                }
+            }
+            None => {
+                // Most likely a component on a message sent before custom ids were routed
+                tracing::debug!("Component {} not handled", interaction.data.custom_id);
+                if let Err(why) = interaction
+                    .create_interaction_response(&ctx.http, |response| {
+                        response
+                            .kind(InteractionResponseType::ChannelMessageWithSource)
+                            .interaction_response_data(|message| {
+                                message
+                                    .content("This button is no longer in use")
+                                    .ephemeral(true)
+                            })
+                    })
+                    .await
+                {
+                    tracing::warn!("Failed to respond to component: {}", why);
+                }
+            }
+        }
+    }
+
+    #[instrument(skip(self, ctx, submit), fields(id = %submit.data.custom_id))]
+    pub async fn modal(&self, ctx: &Context, submit: &ModalSubmitInteraction) {
+        let id = CustomId::parse(&submit.data.custom_id);
+        match self.route(&id) {
+            Some(c) => {
+                tracing::debug!("Executing modal {}", submit.data.custom_id);
+                if let Err(e) = c.modal(ctx, submit, id).await {
+                    error::handle(ctx, Origin::Modal(submit), e).await;
+                }
+            }
+            None => tracing::debug!("Modal {} not handled", submit.data.custom_id),
+        }
+    }
+}
+
+/// The option the user is currently typing in, looking through subcommands as well
+pub fn focused_option(options: &[CommandDataOption]) -> Option<&CommandDataOption> {
+    options.iter().find_map(|option| {
+        if option.focused {
+            Some(option)
+        } else {
+            focused_option(&option.options)
+        }
+    })
+}
+
+/// Respond to an autocomplete interaction with `(name, value)` suggestions.
+/// Anything beyond the first 25 suggestions is ignored, as Discord would reject it
+pub async fn suggest<I>(ctx: &Context, interaction: &AutocompleteInteraction, choices: I)
+where
+    I: IntoIterator<Item = (String, String)>,
+{
+    if let Err(why) = interaction
+        .create_autocomplete_response(&ctx.http, |response| {
+            choices
+                .into_iter()
+                .take(MAX_CHOICES)
+                .fold(response, |response, (name, value)| {
+                    response.add_string_choice(name, value)
+                })
+        })
+        .await
+    {
+        tracing::warn!("Failed to send autocomplete suggestions: {}", why);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use serenity::{
+        async_trait,
+        builder::{CreateApplicationCommand, CreateApplicationCommands},
+        model::prelude::interaction::application_command::{
+            ApplicationCommandInteraction, CommandDataOption,
+        },
+        prelude::Context,
+    };
+
+    use super::{custom_id, focused_option, BotResult, Command, CommandRegistry, CustomId};
+
+    struct Dummy(&'static str);
+
+    #[async_trait]
+    impl Command for Dummy {
+        fn name(&self) -> &'static str {
+            self.0
+        }
+
+        fn register<'a>(
+            &self,
+            command: &'a mut CreateApplicationCommand,
+        ) -> &'a mut CreateApplicationCommand {
+            command.name("not-the-file-name").description("Dummy")
+        }
+
+        async fn run(&self, _ctx: &Context, _command: &ApplicationCommandInteraction) -> BotResult {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn lists_commands_in_order() {
+        let mut registry = CommandRegistry::new();
+        registry.add(Dummy("b"));
+        registry.add(Dummy("a"));
+        assert_eq!(registry.names().collect::<Vec<_>>(), vec!["a", "b"]);
+        assert!(registry.get("a").is_some());
+        assert!(registry.get("c").is_none());
+    }
+
+    #[test]
+    #[should_panic]
+    fn rejects_duplicate_names() {
+        let mut registry = CommandRegistry::new();
+        registry.add(Dummy("a"));
+        registry.add(Dummy("a"));
+    }
+
+    #[test]
+    fn registers_only_enabled_commands_under_their_name() {
+        let mut registry = CommandRegistry::new();
+        registry.add(Dummy("a"));
+        registry.add(Dummy("b"));
+        let mut commands = CreateApplicationCommands::default();
+        registry.create_commands(&mut commands, |name| name == "b");
+        assert_eq!(commands.0.len(), 1);
+        assert_eq!(commands.0[0]["name"], "b");
+    }
+
+    #[test]
+    #[should_panic]
+    fn rejects_shared_custom_id_prefixes() {
+        struct Other;
+
+        #[async_trait]
+        impl Command for Other {
+            fn name(&self) -> &'static str {
+                "other"
+            }
+
+            fn custom_id_prefixes(&self) -> Vec<&'static str> {
+                vec!["a"]
+            }
+
+            fn register<'a>(
+                &self,
+                command: &'a mut CreateApplicationCommand,
+            ) -> &'a mut CreateApplicationCommand {
+                command.description("Other")
+            }
+
+            async fn run(
+                &self,
+                _ctx: &Context,
+                _command: &ApplicationCommandInteraction,
+            ) -> BotResult {
+                Ok(())
+            }
+        }
+
+        let mut registry = CommandRegistry::new();
+        registry.add(Dummy("a"));
+        registry.add(Other);
+    }
+
+    #[test]
+    fn custom_id_round_trip() {
+        let id = custom_id("food", "publish", &["42", "x"]);
+        assert_eq!(id, "food:publish:42:x");
+
+        let parsed = CustomId::parse(&id);
+        assert_eq!(parsed.prefix, "food");
+        assert_eq!(parsed.action, "publish");
+        assert_eq!(parsed.arg(0), Some("42"));
+        assert_eq!(parsed.arg(1), Some("x"));
+        assert_eq!(parsed.arg(2), None);
+    }
+
+    #[test]
+    fn custom_id_without_action() {
+        let parsed = CustomId::parse("kok");
+        assert_eq!(parsed.prefix, "kok");
+        assert_eq!(parsed.action, "");
+        assert!(parsed.args.is_empty());
+    }
+
+    #[test]
+    fn finds_focused_option_in_subcommand() {
+        let options: Vec<CommandDataOption> = serde_json::from_value(json!([
+            {
+                "name": "get",
+                "type": 1,
+                "options": [
+                    { "name": "other", "type": 3, "value": "a" },
+                    { "name": "name", "type": 3, "value": "ex", "focused": true }
+                ]
+            }
+        ]))
+        .unwrap();
+
+        let focused = focused_option(&options).unwrap();
+        assert_eq!(focused.name, "name");
+        assert!(focused_option(&options[0].options[..1]).is_none());
+    }
+}
@@ -0,0 +1,60 @@
+use chrono::{DateTime, TimeZone, Utc};
+use rusqlite::{params, OptionalExtension};
+
+use super::{Storage, StorageError};
+
+impl Storage {
+    /// When a background job last finished a run
+    pub async fn last_job_run(
+        &self,
+        name: &'static str,
+    ) -> Result<Option<DateTime<Utc>>, StorageError> {
+        let last_run: Option<i64> = self
+            .call(move |conn| {
+                conn.query_row(
+                    "SELECT last_run FROM job_runs WHERE name = ?1",
+                    params![name],
+                    |row| row.get(0),
+                )
+                .optional()
+            })
+            .await?;
+        Ok(last_run.and_then(|t| Utc.timestamp_opt(t, 0).single()))
+    }
+
+    pub async fn record_job_run(
+        &self,
+        name: &'static str,
+        at: DateTime<Utc>,
+    ) -> Result<(), StorageError> {
+        self.call(move |conn| {
+            conn.execute(
+                "INSERT INTO job_runs (name, last_run) VALUES (?1, ?2)
+                 ON CONFLICT (name) DO UPDATE SET last_run = excluded.last_run",
+                params![name, at.timestamp()],
+            )
+            .map(|_| ())
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use crate::storage::Storage;
+
+    #[tokio::test]
+    async fn last_run_is_replaced() {
+        let storage = Storage::in_memory().unwrap();
+        assert_eq!(storage.last_job_run("lunch").await.unwrap(), None);
+
+        let first = Utc.with_ymd_and_hms(2023, 1, 2, 6, 0, 0).unwrap();
+        let second = Utc.with_ymd_and_hms(2023, 1, 3, 6, 0, 0).unwrap();
+        storage.record_job_run("lunch", first).await.unwrap();
+        storage.record_job_run("lunch", second).await.unwrap();
+        assert_eq!(storage.last_job_run("lunch").await.unwrap(), Some(second));
+        assert_eq!(storage.last_job_run("yr").await.unwrap(), None);
+    }
+}
@@ -0,0 +1,105 @@
+use rusqlite::{params, OptionalExtension};
+use serenity::model::prelude::{GuildId, MessageId, UserId};
+
+use super::{Storage, StorageError};
+
+/// A recipe in the kok catalogue of a guild
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KokFile {
+    pub name: String,
+    pub uploaded_by: UserId,
+    /// The catalogue post announcing the latest upload
+    pub catalogue_message: Option<MessageId>,
+    /// Unix timestamp of the latest upload
+    pub updated_at: i64,
+}
+
+impl Storage {
+    pub async fn kok_file(
+        &self,
+        guild_id: GuildId,
+        name: String,
+    ) -> Result<Option<KokFile>, StorageError> {
+        self.call(move |conn| {
+            conn.query_row(
+                "SELECT name, uploaded_by, catalogue_message, updated_at
+                 FROM kok_files
+                 WHERE guild_id = ?1 AND name = ?2",
+                params![guild_id.0 as i64, name],
+                |row| {
+                    Ok(KokFile {
+                        name: row.get(0)?,
+                        uploaded_by: UserId(row.get::<_, i64>(1)? as u64),
+                        catalogue_message: row
+                            .get::<_, Option<i64>>(2)?
+                            .map(|id| MessageId(id as u64)),
+                        updated_at: row.get(3)?,
+                    })
+                },
+            )
+            .optional()
+        })
+        .await
+    }
+
+    /// Insert or replace the metadata of a recipe
+    pub async fn save_kok_file(
+        &self,
+        guild_id: GuildId,
+        file: KokFile,
+    ) -> Result<(), StorageError> {
+        self.call(move |conn| {
+            conn.execute(
+                "INSERT INTO kok_files (guild_id, name, uploaded_by, catalogue_message, updated_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5)
+                 ON CONFLICT (guild_id, name) DO UPDATE SET
+                    uploaded_by = excluded.uploaded_by,
+                    catalogue_message = excluded.catalogue_message,
+                    updated_at = excluded.updated_at",
+                params![
+                    guild_id.0 as i64,
+                    file.name,
+                    file.uploaded_by.0 as i64,
+                    file.catalogue_message.map(|m| m.0 as i64),
+                    file.updated_at
+                ],
+            )
+            .map(|_| ())
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serenity::model::prelude::{GuildId, MessageId, UserId};
+
+    use super::KokFile;
+    use crate::storage::Storage;
+
+    #[tokio::test]
+    async fn saving_twice_replaces_the_entry() {
+        let storage = Storage::in_memory().unwrap();
+        let mut file = KokFile {
+            name: "pasta".to_string(),
+            uploaded_by: UserId(1),
+            catalogue_message: None,
+            updated_at: 0,
+        };
+        storage
+            .save_kok_file(GuildId(1), file.clone())
+            .await
+            .unwrap();
+        file.uploaded_by = UserId(2);
+        file.catalogue_message = Some(MessageId(3));
+        storage
+            .save_kok_file(GuildId(1), file.clone())
+            .await
+            .unwrap();
+
+        let stored = storage.kok_file(GuildId(1), "pasta".to_string()).await;
+        assert_eq!(stored.unwrap(), Some(file));
+        let other_guild = storage.kok_file(GuildId(2), "pasta".to_string()).await;
+        assert_eq!(other_guild.unwrap(), None);
+    }
+}
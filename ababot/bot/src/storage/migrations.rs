@@ -0,0 +1,54 @@
+/// Schema changes, applied in order.
+/// The index of the last applied migration is kept in `PRAGMA user_version`,
+/// so existing entries must never be edited, only appended to
+pub const MIGRATIONS: &[&str] = &[
+    // 1: Quiz scores and kok metadata
+    "CREATE TABLE quiz_scores (
+        id INTEGER PRIMARY KEY,
+        guild_id INTEGER NOT NULL,
+        user_id INTEGER NOT NULL,
+        correct INTEGER NOT NULL,
+        questions INTEGER NOT NULL,
+        played_at INTEGER NOT NULL
+    );
+    CREATE INDEX quiz_scores_guild ON quiz_scores (guild_id, user_id);
+
+    CREATE TABLE kok_files (
+        guild_id INTEGER NOT NULL,
+        name TEXT NOT NULL,
+        uploaded_by INTEGER NOT NULL,
+        catalogue_message INTEGER,
+        updated_at INTEGER NOT NULL,
+        PRIMARY KEY (guild_id, name)
+    );",
+    // 2: Last run of every background job, to catch up on runs missed while offline
+    "CREATE TABLE job_runs (
+        name TEXT PRIMARY KEY,
+        last_run INTEGER NOT NULL
+    );",
+    // 3: Reminders waiting to be sent, so they survive a restart
+    "CREATE TABLE reminders (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        user_id INTEGER NOT NULL,
+        channel_id INTEGER NOT NULL,
+        message TEXT NOT NULL,
+        due_at INTEGER NOT NULL,
+        public INTEGER NOT NULL
+    );
+    CREATE INDEX reminders_due ON reminders (due_at);
+    CREATE INDEX reminders_user ON reminders (user_id);",
+    // 4: Recurring reminders
+    "ALTER TABLE reminders ADD COLUMN recurrence TEXT;
+    ALTER TABLE reminders ADD COLUMN until_at INTEGER;
+    ALTER TABLE reminders ADD COLUMN remaining INTEGER;",
+    // 5: Roles and users mentioned by reminders
+    "ALTER TABLE reminders ADD COLUMN mention_role INTEGER;
+    ALTER TABLE reminders ADD COLUMN mention_users TEXT;",
+    // 6: Timezone and language of every user who has set them, and of recurring reminders
+    "CREATE TABLE user_settings (
+        user_id INTEGER PRIMARY KEY,
+        timezone TEXT NOT NULL,
+        language TEXT NOT NULL
+    );
+    ALTER TABLE reminders ADD COLUMN timezone TEXT;",
+];
@@ -0,0 +1,151 @@
+pub mod jobs;
+pub mod kok;
+mod migrations;
+pub mod quiz;
+pub mod reminders;
+pub mod settings;
+
+use std::{
+    fmt,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use rusqlite::Connection;
+use serenity::prelude::{Context, TypeMapKey};
+use tokio::task::JoinError;
+
+use migrations::MIGRATIONS;
+
+/// Handle to the embedded database.
+/// Cheap to clone, every clone shares the same connection
+#[derive(Clone)]
+pub struct Storage {
+    conn: Arc<Mutex<Connection>>,
+}
+
+#[derive(Debug)]
+pub enum StorageError {
+    Sqlite(rusqlite::Error),
+    /// The blocking task running the query panicked or was cancelled
+    Join(JoinError),
+}
+
+impl fmt::Display for StorageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StorageError::Sqlite(e) => write!(f, "Database error: {}", e),
+            StorageError::Join(e) => write!(f, "Database task failed: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for StorageError {}
+
+impl From<rusqlite::Error> for StorageError {
+    fn from(e: rusqlite::Error) -> Self {
+        StorageError::Sqlite(e)
+    }
+}
+
+impl From<JoinError> for StorageError {
+    fn from(e: JoinError) -> Self {
+        StorageError::Join(e)
+    }
+}
+
+impl TypeMapKey for Storage {
+    type Value = Storage;
+}
+
+impl Storage {
+    /// Open the database at the given path, creating it if needed, and bring the schema up to date
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Storage, StorageError> {
+        Storage::with_connection(Connection::open(path)?)
+    }
+
+    /// A fresh database that only lives as long as the handle
+    pub fn in_memory() -> Result<Storage, StorageError> {
+        Storage::with_connection(Connection::open_in_memory()?)
+    }
+
+    fn with_connection(mut conn: Connection) -> Result<Storage, StorageError> {
+        conn.pragma_update(None, "foreign_keys", true)?;
+        migrate(&mut conn)?;
+        Ok(Storage {
+            conn: Arc::new(Mutex::new(conn)),
+        })
+    }
+
+    /// Run a closure against the connection on the blocking thread pool.
+    /// Queries are serialized, so keep the closures short
+    pub async fn call<F, T>(&self, f: F) -> Result<T, StorageError>
+    where
+        F: FnOnce(&mut Connection) -> rusqlite::Result<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        let conn = self.conn.clone();
+        let result = tokio::task::spawn_blocking(move || {
+            // A panic in another query does not leave the connection in a bad state
+            let mut conn = conn.lock().unwrap_or_else(|e| e.into_inner());
+            f(&mut conn)
+        })
+        .await?;
+        Ok(result?)
+    }
+
+    /// Wait for running queries and write cached pages to disk, called on shutdown
+    pub async fn flush(&self) -> Result<(), StorageError> {
+        self.call(|conn| conn.cache_flush()).await
+    }
+
+    /// Get the storage from the context
+    /// # Panics
+    /// Panics if the storage has not been inserted at startup
+    pub async fn get(ctx: &Context) -> Storage {
+        ctx.data
+            .read()
+            .await
+            .get::<Storage>()
+            .expect("Storage not found in data")
+            .clone()
+    }
+}
+
+/// Apply every migration newer than the schema version of the database
+fn migrate(conn: &mut Connection) -> rusqlite::Result<()> {
+    let version: usize = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+    for (i, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        tracing::info!("Applying database migration {}", i + 1);
+        let tx = conn.transaction()?;
+        tx.execute_batch(migration)?;
+        tx.pragma_update(None, "user_version", i + 1)?;
+        tx.commit()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn migrations_are_applied_once() {
+        let storage = Storage::in_memory().unwrap();
+        let mut conn = storage.conn.lock().unwrap();
+        migrate(&mut conn).unwrap();
+        let version: usize = conn
+            .pragma_query_value(None, "user_version", |row| row.get(0))
+            .unwrap();
+        assert_eq!(version, MIGRATIONS.len());
+    }
+
+    #[tokio::test]
+    async fn errors_are_returned() {
+        let storage = Storage::in_memory().unwrap();
+        let result = storage
+            .call(|conn| conn.execute("SELECT * FROM missing", []))
+            .await;
+        assert!(matches!(result, Err(StorageError::Sqlite(_))));
+    }
+}
@@ -0,0 +1,110 @@
+use chrono::{DateTime, Utc};
+use rusqlite::params;
+use serenity::model::prelude::{GuildId, UserId};
+
+use super::{Storage, StorageError};
+
+/// All time quiz results of a single user in a guild
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuizStanding {
+    pub user_id: UserId,
+    pub correct: u64,
+    pub questions: u64,
+    pub quizzes: u64,
+}
+
+impl Storage {
+    /// Store the number of correct answers for every participant of a quiz finished at `played_at`
+    pub async fn record_quiz(
+        &self,
+        guild_id: GuildId,
+        scores: Vec<(UserId, usize)>,
+        questions: usize,
+        played_at: DateTime<Utc>,
+    ) -> Result<(), StorageError> {
+        let played_at = played_at.timestamp();
+        self.call(move |conn| {
+            let tx = conn.transaction()?;
+            {
+                let mut insert = tx.prepare(
+                    "INSERT INTO quiz_scores (guild_id, user_id, correct, questions, played_at)
+                     VALUES (?1, ?2, ?3, ?4, ?5)",
+                )?;
+                for (user_id, correct) in scores {
+                    insert.execute(params![
+                        guild_id.0 as i64,
+                        user_id.0 as i64,
+                        correct as i64,
+                        questions as i64,
+                        played_at
+                    ])?;
+                }
+            }
+            tx.commit()
+        })
+        .await
+    }
+
+    /// The users with the most correct answers in a guild, best first
+    pub async fn quiz_leaderboard(
+        &self,
+        guild_id: GuildId,
+        limit: usize,
+    ) -> Result<Vec<QuizStanding>, StorageError> {
+        self.call(move |conn| {
+            let mut query = conn.prepare(
+                "SELECT user_id, SUM(correct), SUM(questions), COUNT(*)
+                 FROM quiz_scores
+                 WHERE guild_id = ?1
+                 GROUP BY user_id
+                 ORDER BY SUM(correct) DESC, SUM(questions) ASC
+                 LIMIT ?2",
+            )?;
+            let rows = query.query_map(params![guild_id.0 as i64, limit as i64], |row| {
+                Ok(QuizStanding {
+                    user_id: UserId(row.get::<_, i64>(0)? as u64),
+                    correct: row.get::<_, i64>(1)? as u64,
+                    questions: row.get::<_, i64>(2)? as u64,
+                    quizzes: row.get::<_, i64>(3)? as u64,
+                })
+            })?;
+            rows.collect()
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use serenity::model::prelude::{GuildId, UserId};
+
+    use crate::storage::Storage;
+
+    #[tokio::test]
+    async fn leaderboard_sums_quizzes_per_guild() {
+        let storage = Storage::in_memory().unwrap();
+        let guild = GuildId(1);
+        let played_at = Utc.with_ymd_and_hms(2024, 3, 27, 12, 0, 0).unwrap();
+        storage
+            .record_quiz(guild, vec![(UserId(10), 2), (UserId(20), 4)], 5, played_at)
+            .await
+            .unwrap();
+        storage
+            .record_quiz(guild, vec![(UserId(10), 5)], 5, played_at)
+            .await
+            .unwrap();
+        storage
+            .record_quiz(GuildId(2), vec![(UserId(20), 5)], 5, played_at)
+            .await
+            .unwrap();
+
+        let leaderboard = storage.quiz_leaderboard(guild, 10).await.unwrap();
+        assert_eq!(leaderboard.len(), 2);
+        assert_eq!(leaderboard[0].user_id, UserId(10));
+        assert_eq!(leaderboard[0].correct, 7);
+        assert_eq!(leaderboard[0].questions, 10);
+        assert_eq!(leaderboard[0].quizzes, 2);
+        assert_eq!(leaderboard[1].correct, 4);
+    }
+}
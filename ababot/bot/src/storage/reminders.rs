@@ -0,0 +1,549 @@
+use std::fmt;
+
+use chrono::{DateTime, TimeZone, Utc};
+use chrono_tz::{Europe::Oslo, Tz};
+use rusqlite::{params, types::Type, Connection, OptionalExtension, Row};
+use serenity::{
+    model::prelude::{ChannelId, Mentionable, RoleId, UserId},
+    utils::{parse_role, parse_username},
+};
+
+use super::{Storage, StorageError};
+use crate::utils::time::recurrence::Recurrence;
+
+/// A reminder waiting to be sent
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reminder {
+    /// Assigned when the reminder is stored, see [Storage::add_reminder]
+    pub id: i64,
+    pub user_id: UserId,
+    /// Where the reminder was set, public reminders are sent there
+    pub channel_id: ChannelId,
+    pub message: String,
+    /// Truncated to whole seconds when stored
+    pub due: DateTime<Utc>,
+    /// Sent to the channel instead of as a direct message
+    pub public: bool,
+    /// Set for reminders that come back after they are sent
+    pub repeat: Option<Repeat>,
+    /// Who a public reminder pings besides the embed
+    pub mentions: Mentions,
+}
+
+/// The role and users a reminder mentions
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Mentions {
+    pub role: Option<RoleId>,
+    pub users: Vec<UserId>,
+}
+
+impl Mentions {
+    pub fn is_empty(&self) -> bool {
+        self.role.is_none() && self.users.is_empty()
+    }
+
+    /// Read back the mentions as [written](fmt::Display) in a sent reminder, skipping anything
+    /// that is not a mention
+    pub fn parse(content: &str) -> Mentions {
+        let mut mentions = Mentions::default();
+        for word in content.split_whitespace() {
+            if let Some(role) = parse_role(word) {
+                mentions.role = Some(RoleId(role));
+            } else if let Some(user) = parse_username(word) {
+                mentions.users.push(UserId(user));
+            }
+        }
+        mentions
+    }
+}
+
+/// Written as the mentions Discord pings
+impl fmt::Display for Mentions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let role = self.role.map(|role| role.mention().to_string());
+        let users = self.users.iter().map(|user| user.mention().to_string());
+        let mentions: Vec<String> = role.into_iter().chain(users).collect();
+        write!(f, "{}", mentions.join(" "))
+    }
+}
+
+/// When a recurring reminder comes back
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Repeat {
+    pub recurrence: Recurrence,
+    /// No occurrences after this
+    pub until: Option<DateTime<Utc>>,
+    /// Occurrences left, counting the one that is due
+    pub remaining: Option<u32>,
+    /// The wall clock the recurrence follows
+    pub timezone: Tz,
+}
+
+impl Reminder {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(Reminder {
+            id: row.get(0)?,
+            user_id: UserId(row.get::<_, i64>(1)? as u64),
+            channel_id: ChannelId(row.get::<_, i64>(2)? as u64),
+            message: row.get(3)?,
+            due: timestamp(row.get(4)?),
+            public: row.get(5)?,
+            repeat: match row.get::<_, Option<String>>(6)? {
+                Some(recurrence) => Some(Repeat {
+                    recurrence: recurrence.parse().map_err(|e: String| {
+                        rusqlite::Error::FromSqlConversionFailure(6, Type::Text, e.into())
+                    })?,
+                    until: row.get::<_, Option<i64>>(7)?.map(timestamp),
+                    remaining: row.get(8)?,
+                    timezone: row
+                        .get::<_, Option<String>>(11)?
+                        .and_then(|timezone| timezone.parse().ok())
+                        .unwrap_or(Oslo),
+                }),
+                None => None,
+            },
+            mentions: Mentions {
+                role: row
+                    .get::<_, Option<i64>>(9)?
+                    .map(|role| RoleId(role as u64)),
+                users: row
+                    .get::<_, Option<String>>(10)?
+                    .unwrap_or_default()
+                    .split_whitespace()
+                    .filter_map(|user| user.parse().ok())
+                    .map(UserId)
+                    .collect(),
+            },
+        })
+    }
+
+    /// The reminder at its first occurrence after `now`, `None` if it does not recur or has
+    /// ended. Occurrences missed while the bot was down count towards the number of times
+    pub fn next_after(&self, now: DateTime<Utc>) -> Option<Reminder> {
+        let repeat = self.repeat.as_ref()?;
+        let mut due = self.due.with_timezone(&repeat.timezone);
+        let mut remaining = repeat.remaining;
+        loop {
+            if let Some(left) = remaining {
+                remaining = Some(left.checked_sub(1).filter(|left| *left > 0)?);
+            }
+            due = repeat.recurrence.next_after(due)?;
+            if matches!(repeat.until, Some(until) if due > until) {
+                return None;
+            }
+            if due > now {
+                break;
+            }
+        }
+        Some(Reminder {
+            due: due.with_timezone(&Utc),
+            repeat: Some(Repeat {
+                remaining,
+                ..repeat.clone()
+            }),
+            ..self.clone()
+        })
+    }
+
+    /// When the reminder is due this time and the following times, at most `limit` of them
+    pub fn occurrences(&self, limit: usize) -> Vec<DateTime<Utc>> {
+        std::iter::successors(Some(self.clone()), |reminder| {
+            reminder.next_after(reminder.due)
+        })
+        .take(limit)
+        .map(|reminder| reminder.due)
+        .collect()
+    }
+
+    fn repeat_columns(&self) -> (Option<String>, Option<i64>, Option<u32>, Option<&str>) {
+        match &self.repeat {
+            Some(repeat) => (
+                Some(repeat.recurrence.to_string()),
+                repeat.until.map(|until| until.timestamp()),
+                repeat.remaining,
+                Some(repeat.timezone.name()),
+            ),
+            None => (None, None, None, None),
+        }
+    }
+}
+
+/// Stored timestamps were valid when written, out of range ones are read as the epoch
+/// See [Storage::update_reminder]
+fn update_row(conn: &Connection, reminder: &Reminder) -> rusqlite::Result<usize> {
+    let (recurrence, until, remaining, timezone) = reminder.repeat_columns();
+    conn.execute(
+        "UPDATE reminders
+         SET message = ?2, due_at = ?3, public = ?4,
+            recurrence = ?5, until_at = ?6, remaining = ?7, timezone = ?8
+         WHERE id = ?1",
+        params![
+            reminder.id,
+            reminder.message,
+            reminder.due.timestamp(),
+            reminder.public,
+            recurrence,
+            until,
+            remaining,
+            timezone
+        ],
+    )
+}
+
+fn timestamp(seconds: i64) -> DateTime<Utc> {
+    Utc.timestamp_opt(seconds, 0)
+        .single()
+        .unwrap_or_else(|| Utc.timestamp_opt(0, 0).unwrap())
+}
+
+impl Storage {
+    /// Store a reminder, returning it with its id. The id it is given is ignored
+    pub async fn add_reminder(&self, reminder: Reminder) -> Result<Reminder, StorageError> {
+        self.store_reminder(None, reminder).await
+    }
+
+    /// Store a reminder again under its own id, as it was before it was sent
+    pub async fn restore_reminder(&self, reminder: Reminder) -> Result<Reminder, StorageError> {
+        self.store_reminder(Some(reminder.id), reminder).await
+    }
+
+    /// Insert a reminder under the id, replacing any reminder stored there,
+    /// or under a new id if it is `None`
+    async fn store_reminder(
+        &self,
+        id: Option<i64>,
+        reminder: Reminder,
+    ) -> Result<Reminder, StorageError> {
+        self.call(move |conn| {
+            let (recurrence, until, remaining, timezone) = reminder.repeat_columns();
+            let users: Vec<String> = reminder
+                .mentions
+                .users
+                .iter()
+                .map(|user| user.to_string())
+                .collect();
+            conn.execute(
+                "INSERT OR REPLACE INTO reminders
+                    (user_id, channel_id, message, due_at, public, recurrence, until_at, remaining,
+                     mention_role, mention_users, timezone, id)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+                params![
+                    reminder.user_id.0 as i64,
+                    reminder.channel_id.0 as i64,
+                    reminder.message,
+                    reminder.due.timestamp(),
+                    reminder.public,
+                    recurrence,
+                    until,
+                    remaining,
+                    reminder.mentions.role.map(|role| role.0 as i64),
+                    (!users.is_empty()).then(|| users.join(" ")),
+                    timezone,
+                    id
+                ],
+            )?;
+            Ok(Reminder {
+                id: conn.last_insert_rowid(),
+                due: timestamp(reminder.due.timestamp()),
+                ..reminder
+            })
+        })
+        .await
+    }
+
+    pub async fn reminder(&self, id: i64) -> Result<Option<Reminder>, StorageError> {
+        self.call(move |conn| {
+            conn.query_row(
+                "SELECT id, user_id, channel_id, message, due_at, public, recurrence, until_at, remaining,
+                    mention_role, mention_users, timezone
+                 FROM reminders
+                 WHERE id = ?1",
+                params![id],
+                Reminder::from_row,
+            )
+            .optional()
+        })
+        .await
+    }
+
+    /// The reminders a user is waiting for, the earliest first
+    pub async fn user_reminders(&self, user_id: UserId) -> Result<Vec<Reminder>, StorageError> {
+        self.call(move |conn| {
+            let mut query = conn.prepare(
+                "SELECT id, user_id, channel_id, message, due_at, public, recurrence, until_at, remaining,
+                    mention_role, mention_users, timezone
+                 FROM reminders
+                 WHERE user_id = ?1
+                 ORDER BY due_at, id",
+            )?;
+            let reminders = query
+                .query_map(params![user_id.0 as i64], Reminder::from_row)?
+                .collect();
+            reminders
+        })
+        .await
+    }
+
+    /// Every reminder due at or before `now`, the earliest first
+    pub async fn due_reminders(&self, now: DateTime<Utc>) -> Result<Vec<Reminder>, StorageError> {
+        self.call(move |conn| {
+            let mut query = conn.prepare(
+                "SELECT id, user_id, channel_id, message, due_at, public, recurrence, until_at, remaining,
+                    mention_role, mention_users, timezone
+                 FROM reminders
+                 WHERE due_at <= ?1
+                 ORDER BY due_at, id",
+            )?;
+            let reminders = query
+                .query_map(params![now.timestamp()], Reminder::from_row)?
+                .collect();
+            reminders
+        })
+        .await
+    }
+
+    /// When the earliest stored reminder is due, `None` if there are none
+    pub async fn next_reminder_due(&self) -> Result<Option<DateTime<Utc>>, StorageError> {
+        let due: Option<i64> = self
+            .call(|conn| conn.query_row("SELECT MIN(due_at) FROM reminders", [], |row| row.get(0)))
+            .await?;
+        Ok(due.map(timestamp))
+    }
+
+    /// Change everything but the user, channel and mentions of a stored reminder.
+    /// Returns `false` if there was no such reminder
+    pub async fn update_reminder(&self, reminder: Reminder) -> Result<bool, StorageError> {
+        self.call(move |conn| update_row(conn, &reminder).map(|updated| updated > 0))
+            .await
+    }
+
+    /// Put back `reminder` as it was before it moved on to `advanced`, unless it has been
+    /// edited or deleted since. Returns `false` if it was left alone
+    pub async fn roll_back_reminder(
+        &self,
+        reminder: Reminder,
+        advanced: Reminder,
+    ) -> Result<bool, StorageError> {
+        self.call(move |conn| {
+            let tx = conn.transaction()?;
+            let stored = tx
+                .query_row(
+                    "SELECT id, user_id, channel_id, message, due_at, public, recurrence, until_at, remaining,
+                        mention_role, mention_users, timezone
+                     FROM reminders
+                     WHERE id = ?1",
+                    params![reminder.id],
+                    Reminder::from_row,
+                )
+                .optional()?;
+            let advanced = Reminder {
+                due: timestamp(advanced.due.timestamp()),
+                ..advanced
+            };
+            if stored != Some(advanced) {
+                return Ok(false);
+            }
+            update_row(&tx, &reminder)?;
+            tx.commit()?;
+            Ok(true)
+        })
+        .await
+    }
+
+    /// Returns `false` if there was no such reminder
+    pub async fn delete_reminder(&self, id: i64) -> Result<bool, StorageError> {
+        self.call(move |conn| {
+            conn.execute("DELETE FROM reminders WHERE id = ?1", params![id])
+                .map(|deleted| deleted > 0)
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use serenity::model::prelude::{ChannelId, RoleId, UserId};
+
+    use super::{Mentions, Reminder, Repeat};
+    use crate::storage::Storage;
+
+    #[tokio::test]
+    async fn reminders_are_returned_once_due() {
+        let storage = Storage::in_memory().unwrap();
+        let at = |hour| Utc.with_ymd_and_hms(2023, 1, 2, hour, 0, 0).unwrap();
+        let reminder = |message: &str, due| Reminder {
+            id: 0,
+            user_id: UserId(1),
+            channel_id: ChannelId(2),
+            message: message.to_string(),
+            due,
+            public: false,
+            repeat: None,
+            mentions: Mentions::default(),
+        };
+        assert_eq!(storage.next_reminder_due().await.unwrap(), None);
+
+        let later = storage
+            .add_reminder(reminder("later", at(12)))
+            .await
+            .unwrap();
+        let sooner = storage
+            .add_reminder(reminder("sooner", at(10)))
+            .await
+            .unwrap();
+        assert_ne!(later.id, sooner.id);
+        assert_eq!(storage.next_reminder_due().await.unwrap(), Some(at(10)));
+        assert_eq!(storage.due_reminders(at(9)).await.unwrap(), vec![]);
+        assert_eq!(
+            storage.due_reminders(at(12)).await.unwrap(),
+            vec![sooner.clone(), later.clone()]
+        );
+
+        assert!(storage.delete_reminder(sooner.id).await.unwrap());
+        assert!(!storage.delete_reminder(sooner.id).await.unwrap());
+        assert_eq!(
+            storage.due_reminders(at(12)).await.unwrap(),
+            vec![later.clone()]
+        );
+
+        // Put back as it was after it could not be sent
+        storage.restore_reminder(sooner.clone()).await.unwrap();
+        let moved = Reminder {
+            due: at(14),
+            ..later.clone()
+        };
+        assert!(storage.update_reminder(moved).await.unwrap());
+        storage.restore_reminder(later.clone()).await.unwrap();
+        assert_eq!(
+            storage.due_reminders(at(12)).await.unwrap(),
+            vec![sooner, later]
+        );
+    }
+
+    #[tokio::test]
+    async fn reminders_are_edited_per_user() {
+        let storage = Storage::in_memory().unwrap();
+        let at = |hour| Utc.with_ymd_and_hms(2023, 1, 2, hour, 0, 0).unwrap();
+        let reminder = |user_id, due| Reminder {
+            id: 0,
+            user_id: UserId(user_id),
+            channel_id: ChannelId(2),
+            message: "Buy milk".to_string(),
+            due,
+            public: false,
+            repeat: None,
+            mentions: Mentions::default(),
+        };
+        let mine = storage.add_reminder(reminder(1, at(12))).await.unwrap();
+        let theirs = storage.add_reminder(reminder(2, at(10))).await.unwrap();
+        assert_eq!(
+            storage.user_reminders(UserId(1)).await.unwrap(),
+            vec![mine.clone()]
+        );
+
+        let edited = Reminder {
+            message: "Buy bread".to_string(),
+            due: at(8),
+            public: true,
+            ..mine.clone()
+        };
+        assert!(storage.update_reminder(edited.clone()).await.unwrap());
+        assert_eq!(storage.reminder(mine.id).await.unwrap(), Some(edited));
+        assert_eq!(storage.reminder(theirs.id).await.unwrap(), Some(theirs));
+        assert_eq!(storage.reminder(-1).await.unwrap(), None);
+        assert!(!storage
+            .update_reminder(Reminder { id: -1, ..mine })
+            .await
+            .unwrap());
+    }
+
+    #[tokio::test]
+    async fn recurring_reminders_come_back_until_they_end() {
+        let storage = Storage::in_memory().unwrap();
+        let oslo = |m, d, h| {
+            chrono_tz::Europe::Oslo
+                .with_ymd_and_hms(2024, m, d, h, 15, 0)
+                .unwrap()
+                .with_timezone(&Utc)
+        };
+        let reminder = storage
+            .add_reminder(Reminder {
+                id: 0,
+                user_id: UserId(1),
+                channel_id: ChannelId(2),
+                message: "Stand-up".to_string(),
+                due: oslo(3, 27, 8),
+                public: true,
+                repeat: Some(Repeat {
+                    recurrence: "every weekday at 08:15".parse().unwrap(),
+                    until: None,
+                    remaining: Some(5),
+                    timezone: chrono_tz::Europe::Oslo,
+                }),
+                mentions: Mentions {
+                    role: Some(RoleId(3)),
+                    users: vec![UserId(4), UserId(5)],
+                },
+            })
+            .await
+            .unwrap();
+        assert_eq!(
+            storage.reminder(reminder.id).await.unwrap(),
+            Some(reminder.clone())
+        );
+        assert_eq!(reminder.mentions.to_string(), "<@&3> <@4> <@5>");
+        assert_eq!(
+            reminder.occurrences(3),
+            vec![oslo(3, 27, 8), oslo(3, 28, 8), oslo(3, 29, 8)]
+        );
+
+        // Down from Wednesday to Sunday, the missed occurrences count
+        let monday = reminder.next_after(oslo(3, 31, 12)).unwrap();
+        assert_eq!(monday.due, oslo(4, 1, 8));
+        assert_eq!(monday.repeat.as_ref().unwrap().remaining, Some(2));
+        assert_eq!(monday.occurrences(5), vec![oslo(4, 1, 8), oslo(4, 2, 8)]);
+
+        let until_friday = Reminder {
+            repeat: Some(Repeat {
+                remaining: None,
+                until: Some(oslo(3, 29, 12)),
+                ..reminder.repeat.clone().unwrap()
+            }),
+            ..reminder.clone()
+        };
+        assert_eq!(until_friday.occurrences(5).len(), 3);
+        assert!(storage.update_reminder(until_friday.clone()).await.unwrap());
+        assert_eq!(
+            storage.reminder(reminder.id).await.unwrap(),
+            Some(until_friday)
+        );
+
+        // At 08:15 in New York, where the clocks went forward two weeks before Oslo
+        let new_york = |m, d, h| {
+            chrono_tz::America::New_York
+                .with_ymd_and_hms(2024, m, d, h, 15, 0)
+                .unwrap()
+                .with_timezone(&Utc)
+        };
+        let abroad = storage
+            .add_reminder(Reminder {
+                due: new_york(3, 27, 8),
+                repeat: Some(Repeat {
+                    timezone: chrono_tz::America::New_York,
+                    ..reminder.repeat.clone().unwrap()
+                }),
+                ..reminder.clone()
+            })
+            .await
+            .unwrap();
+        assert_eq!(
+            storage.reminder(abroad.id).await.unwrap(),
+            Some(abroad.clone())
+        );
+        assert_eq!(
+            abroad.occurrences(2),
+            vec![new_york(3, 27, 8), new_york(3, 28, 8)]
+        );
+    }
+}
@@ -0,0 +1,148 @@
+use std::{fmt, str::FromStr};
+
+use chrono_tz::{Europe::Oslo, Tz};
+use rusqlite::{params, OptionalExtension};
+use serenity::{model::prelude::UserId, prelude::Context};
+
+use super::{Storage, StorageError};
+
+/// How a user wants times shown and read
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UserSettings {
+    pub timezone: Tz,
+    pub language: Language,
+}
+
+/// Users who have not set anything are in Oslo
+impl Default for UserSettings {
+    fn default() -> Self {
+        UserSettings {
+            timezone: Oslo,
+            language: Language::English,
+        }
+    }
+}
+
+impl UserSettings {
+    /// The settings of a user, from the storage in the context
+    pub async fn of(ctx: &Context, user_id: UserId) -> Result<UserSettings, StorageError> {
+        Storage::get(ctx).await.user_settings(user_id).await
+    }
+}
+
+/// The language times are read and echoed back in, the rest of the bot is in English
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Language {
+    English,
+    Norwegian,
+}
+
+impl Language {
+    /// The code the language is stored and chosen by
+    pub fn code(&self) -> &'static str {
+        match self {
+            Language::English => "en",
+            Language::Norwegian => "no",
+        }
+    }
+}
+
+impl FromStr for Language {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "en" => Ok(Language::English),
+            "no" | "nb" => Ok(Language::Norwegian),
+            other => Err(format!("Unknown language {}", other)),
+        }
+    }
+}
+
+impl fmt::Display for Language {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Language::English => write!(f, "English"),
+            Language::Norwegian => write!(f, "Norsk"),
+        }
+    }
+}
+
+impl Storage {
+    /// The settings of a user, the defaults if they have not set any.
+    /// Settings that are no longer understood are read as their defaults
+    pub async fn user_settings(&self, user_id: UserId) -> Result<UserSettings, StorageError> {
+        let stored: Option<(String, String)> = self
+            .call(move |conn| {
+                conn.query_row(
+                    "SELECT timezone, language FROM user_settings WHERE user_id = ?1",
+                    params![user_id.0 as i64],
+                    |row| Ok((row.get(0)?, row.get(1)?)),
+                )
+                .optional()
+            })
+            .await?;
+        let defaults = UserSettings::default();
+        Ok(match stored {
+            Some((timezone, language)) => UserSettings {
+                timezone: timezone.parse().unwrap_or(defaults.timezone),
+                language: language.parse().unwrap_or(defaults.language),
+            },
+            None => defaults,
+        })
+    }
+
+    pub async fn set_user_settings(
+        &self,
+        user_id: UserId,
+        settings: UserSettings,
+    ) -> Result<(), StorageError> {
+        self.call(move |conn| {
+            conn.execute(
+                "INSERT INTO user_settings (user_id, timezone, language) VALUES (?1, ?2, ?3)
+                 ON CONFLICT (user_id) DO UPDATE
+                 SET timezone = excluded.timezone, language = excluded.language",
+                params![
+                    user_id.0 as i64,
+                    settings.timezone.name(),
+                    settings.language.code()
+                ],
+            )
+            .map(|_| ())
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono_tz::America::New_York;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn settings_are_replaced() {
+        let storage = Storage::in_memory().unwrap();
+        let user = UserId(1);
+        assert_eq!(
+            storage.user_settings(user).await.unwrap(),
+            UserSettings::default()
+        );
+
+        let abroad = UserSettings {
+            timezone: New_York,
+            language: Language::English,
+        };
+        storage.set_user_settings(user, abroad).await.unwrap();
+        let home = UserSettings {
+            timezone: Oslo,
+            language: Language::Norwegian,
+        };
+        storage.set_user_settings(user, home).await.unwrap();
+        assert_eq!(storage.user_settings(user).await.unwrap(), home);
+        assert_eq!(
+            storage.user_settings(UserId(2)).await.unwrap(),
+            UserSettings::default()
+        );
+    }
+}
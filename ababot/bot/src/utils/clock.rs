@@ -0,0 +1,133 @@
+use std::{
+    ops::Deref,
+    sync::{Arc, Mutex},
+};
+
+use chrono::{DateTime, Utc};
+use chrono_tz::{Europe::Oslo, Tz};
+use serenity::prelude::{Context, TypeMapKey};
+use tokio::time::Instant;
+
+/// Where the bot reads the current time from, so tests can decide what time it is
+pub trait Clock: Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+
+    /// The current time on the wall clocks the bot posts for
+    fn now_in_oslo(&self) -> DateTime<Tz> {
+        self.now().with_timezone(&Oslo)
+    }
+
+    /// The current time on the wall clock of a user
+    fn now_in(&self, timezone: Tz) -> DateTime<Tz> {
+        self.now().with_timezone(&timezone)
+    }
+}
+
+/// The time of the operating system
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// Starts at a given time and moves with the Tokio clock, so `tokio::time::pause` stops it and
+/// `tokio::time::advance` moves it forward. Clones share the time set with [TestClock::set]
+#[derive(Debug, Clone)]
+pub struct TestClock {
+    /// The time at `started`
+    start: Arc<Mutex<(DateTime<Utc>, Instant)>>,
+}
+
+impl TestClock {
+    pub fn new<T: chrono::TimeZone>(start: DateTime<T>) -> Self {
+        TestClock {
+            start: Arc::new(Mutex::new((start.with_timezone(&Utc), Instant::now()))),
+        }
+    }
+
+    /// Jump to another time without moving the Tokio clock, like the system clock being corrected
+    pub fn set<T: chrono::TimeZone>(&self, now: DateTime<T>) {
+        *self.start.lock().unwrap_or_else(|e| e.into_inner()) =
+            (now.with_timezone(&Utc), Instant::now());
+    }
+}
+
+impl Clock for TestClock {
+    fn now(&self) -> DateTime<Utc> {
+        let (start, started) = *self.start.lock().unwrap_or_else(|e| e.into_inner());
+        start
+            + chrono::Duration::from_std(started.elapsed())
+                .unwrap_or_else(|_| chrono::Duration::zero())
+    }
+}
+
+/// The clock in the [TypeMap](serenity::prelude::TypeMap), [SystemClock] outside of tests
+#[derive(Clone)]
+pub struct SharedClock(Arc<dyn Clock>);
+
+impl SharedClock {
+    pub fn new<C: Clock + 'static>(clock: C) -> Self {
+        SharedClock(Arc::new(clock))
+    }
+
+    /// Get the clock from the context
+    /// # Panics
+    /// Panics if the clock has not been inserted at startup
+    pub async fn get(ctx: &Context) -> SharedClock {
+        ctx.data
+            .read()
+            .await
+            .get::<SharedClock>()
+            .expect("Clock not found in data")
+            .clone()
+    }
+}
+
+impl Default for SharedClock {
+    fn default() -> Self {
+        SharedClock::new(SystemClock)
+    }
+}
+
+impl Deref for SharedClock {
+    type Target = dyn Clock;
+
+    fn deref(&self) -> &Self::Target {
+        &*self.0
+    }
+}
+
+impl TypeMapKey for SharedClock {
+    type Value = SharedClock;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_clock_follows_tokio_time() {
+        let start = Oslo.with_ymd_and_hms(2023, 3, 26, 1, 30, 0).unwrap();
+        let clock = TestClock::new(start);
+        let shared = SharedClock::new(clock.clone());
+        assert_eq!(shared.now_in_oslo(), start);
+
+        tokio::time::advance(Duration::from_secs(60 * 60)).await;
+        // 02:30 does not exist that night
+        assert_eq!(
+            shared.now_in_oslo(),
+            Oslo.with_ymd_and_hms(2023, 3, 26, 3, 30, 0).unwrap()
+        );
+
+        let later = Utc.with_ymd_and_hms(2023, 10, 29, 0, 0, 0).unwrap();
+        clock.set(later);
+        assert_eq!(shared.now(), later);
+    }
+}
@@ -0,0 +1,421 @@
+use std::{fmt, str::FromStr};
+
+use chrono::{DateTime, Datelike, NaiveDate, TimeZone};
+use chrono_tz::{Europe::Oslo, Tz};
+use serde::Deserialize;
+
+use super::time::Time;
+
+/// How far ahead [Cron::next_after] looks before deciding a schedule never fires.
+/// Long enough to always include a leap day
+const SEARCH_DAYS: u32 = 5 * 366;
+
+const MONTHS: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+const WEEKDAYS: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+/// A cron-like schedule, evaluated in Europe/Oslo wall clock time unless moved to another
+/// timezone with [Cron::in_timezone].
+///
+/// Written as the five classic fields `minute hour day-of-month month day-of-week`.
+/// Each field is a `*`, a number, a range `a-b`, a step `*/n` or `a-b/n`, or a comma separated
+/// list of those. Months and weekdays can be named (`jan`, `mon`), Sunday is both 0 and 7.
+/// `weekday#n` matches the n-th such weekday of the month, `0 9 * * mon#1` is 09:00 on the first
+/// Monday of every month. As in cron, a day only has to match one of day-of-month and
+/// day-of-week when both are restricted.
+///
+/// `HH:MM` is shorthand for every day at that time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Cron {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    /// Counted from Sunday
+    weekdays: u64,
+    /// Bit `n - 1` of entry `d` is set by `d#n`
+    nth_weekdays: [u8; 7],
+    /// Day-of-month started with `*`
+    any_day: bool,
+    /// Day-of-week started with `*`
+    any_weekday: bool,
+    /// The wall clock the schedule follows
+    timezone: Tz,
+}
+
+impl Cron {
+    /// Every day at the given time, to the minute
+    pub fn daily(time: Time) -> Self {
+        Cron {
+            minutes: 1 << time.minute(),
+            hours: 1 << time.hour(),
+            days: range_mask(1, 31),
+            months: range_mask(1, 12),
+            weekdays: range_mask(0, 6),
+            nth_weekdays: [0; 7],
+            any_day: true,
+            any_weekday: true,
+            timezone: Oslo,
+        }
+    }
+
+    /// The same schedule on the wall clock of `timezone`
+    pub fn in_timezone(self, timezone: Tz) -> Self {
+        Cron { timezone, ..self }
+    }
+
+    /// The first time after `after` the schedule fires.
+    /// Times skipped by the spring DST change never fire, repeated ones fire the first time.
+    /// `None` if it does not fire in the next five years
+    pub fn next_after<T: TimeZone>(&self, after: &DateTime<T>) -> Option<DateTime<Tz>> {
+        let after = after.with_timezone(&self.timezone);
+        let mut date = after.date_naive();
+        for _ in 0..SEARCH_DAYS {
+            if self.matches_date(date) {
+                for hour in (0..24).filter(|h| has(self.hours, *h)) {
+                    for minute in (0..60).filter(|m| has(self.minutes, *m)) {
+                        let time = match self
+                            .timezone
+                            .from_local_datetime(&date.and_hms_opt(hour, minute, 0)?)
+                            .earliest()
+                        {
+                            Some(time) => time,
+                            None => continue,
+                        };
+                        if time > after {
+                            return Some(time);
+                        }
+                    }
+                }
+            }
+            date = date.succ_opt()?;
+        }
+        None
+    }
+
+    fn matches_date(&self, date: NaiveDate) -> bool {
+        if !has(self.months, date.month()) {
+            return false;
+        }
+        let weekday = date.weekday().num_days_from_sunday();
+        let week_of_month = (date.day() - 1) / 7;
+        let day = has(self.days, date.day());
+        let weekday = has(self.weekdays, weekday)
+            || self.nth_weekdays[weekday as usize] & (1 << week_of_month) != 0;
+        if self.any_day || self.any_weekday {
+            day && weekday
+        } else {
+            day || weekday
+        }
+    }
+}
+
+fn has(mask: u64, value: u32) -> bool {
+    mask & (1 << value) != 0
+}
+
+fn range_mask(min: u32, max: u32) -> u64 {
+    (min..=max).fold(0, |mask, i| mask | 1 << i)
+}
+
+/// A number or, if `names` are given, a name counted from `min`
+fn parse_value(value: &str, min: u32, max: u32, names: &[&str]) -> Result<u32, String> {
+    let lower = value.to_lowercase();
+    let parsed = match names.iter().position(|name| *name == lower) {
+        Some(i) => i as u32 + min,
+        None => value
+            .parse()
+            .map_err(|_| format!("{} is not a number", value))?,
+    };
+    if parsed < min || parsed > max {
+        return Err(format!("{} is outside {}-{}", value, min, max));
+    }
+    Ok(parsed)
+}
+
+fn parse_field(field: &str, min: u32, max: u32, names: &[&str]) -> Result<u64, String> {
+    let mut mask = 0;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => match step.parse::<u32>() {
+                Ok(step) if step > 0 => (range, step),
+                _ => return Err(format!("{} is not a valid step", step)),
+            },
+            None => (part, 1),
+        };
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((start, end)) = range.split_once('-') {
+            (
+                parse_value(start, min, max, names)?,
+                parse_value(end, min, max, names)?,
+            )
+        } else {
+            let value = parse_value(range, min, max, names)?;
+            // `5/15` means from 5 and every 15 after, as in cron
+            (value, if part.contains('/') { max } else { value })
+        };
+        if start > end {
+            return Err(format!("{} is an empty range", range));
+        }
+        mask |= (start..=end)
+            .step_by(step as usize)
+            .fold(0, |m, i| m | 1 << i);
+    }
+    Ok(mask)
+}
+
+/// Day-of-week, which also allows `weekday#n`
+fn parse_weekdays(field: &str) -> Result<(u64, [u8; 7]), String> {
+    let mut plain = Vec::new();
+    let mut nth = [0; 7];
+    for part in field.split(',') {
+        match part.split_once('#') {
+            Some((weekday, n)) => {
+                let weekday = parse_value(weekday, 0, 7, &WEEKDAYS)? % 7;
+                match n.parse::<u8>() {
+                    Ok(n @ 1..=5) => nth[weekday as usize] |= 1 << (n - 1),
+                    _ => return Err(format!("{} is not between 1 and 5", n)),
+                }
+            }
+            None => plain.push(part),
+        }
+    }
+    let mut weekdays = if plain.is_empty() {
+        0
+    } else {
+        parse_field(&plain.join(","), 0, 7, &WEEKDAYS)?
+    };
+    // Sunday can be written as 7
+    if has(weekdays, 7) {
+        weekdays = (weekdays & !(1 << 7)) | 1;
+    }
+    Ok((weekdays, nth))
+}
+
+impl FromStr for Cron {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.contains(':') {
+            let time: Time = s.parse()?;
+            if time.second() != 0 {
+                return Err("Schedules can not be more precise than a minute".to_string());
+            }
+            return Ok(Cron::daily(time));
+        }
+
+        let fields: Vec<&str> = s.split_whitespace().collect();
+        let (minute, hour, day, month, weekday) = match fields[..] {
+            [minute, hour, day, month, weekday] => (minute, hour, day, month, weekday),
+            _ => {
+                return Err(format!(
+                    "Expected HH:MM or 5 fields (minute hour day month weekday), got {}",
+                    fields.len()
+                ))
+            }
+        };
+        let (weekdays, nth_weekdays) =
+            parse_weekdays(weekday).map_err(|e| format!("day of week: {}", e))?;
+        let cron = Cron {
+            minutes: parse_field(minute, 0, 59, &[]).map_err(|e| format!("minute: {}", e))?,
+            hours: parse_field(hour, 0, 23, &[]).map_err(|e| format!("hour: {}", e))?,
+            days: parse_field(day, 1, 31, &[]).map_err(|e| format!("day of month: {}", e))?,
+            months: parse_field(month, 1, 12, &MONTHS).map_err(|e| format!("month: {}", e))?,
+            weekdays,
+            nth_weekdays,
+            any_day: day.starts_with('*'),
+            any_weekday: weekday.starts_with('*'),
+            timezone: Oslo,
+        };
+
+        let reference = Oslo
+            .with_ymd_and_hms(2000, 1, 1, 0, 0, 0)
+            .single()
+            .ok_or("Invalid reference date")?;
+        if cron.next_after(&reference).is_none() {
+            return Err(format!("{} never happens", s));
+        }
+        Ok(cron)
+    }
+}
+
+impl TryFrom<String> for Cron {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+/// A field as a comma separated list of values and ranges, `*` if every value is set
+fn write_field(f: &mut fmt::Formatter<'_>, mask: u64, min: u32, max: u32) -> fmt::Result {
+    if mask == range_mask(min, max) {
+        return write!(f, "*");
+    }
+    let mut parts = Vec::new();
+    let mut value = min;
+    while value <= max {
+        if !has(mask, value) {
+            value += 1;
+            continue;
+        }
+        let start = value;
+        while value < max && has(mask, value + 1) {
+            value += 1;
+        }
+        parts.push(if start == value {
+            start.to_string()
+        } else {
+            format!("{}-{}", start, value)
+        });
+        value += 1;
+    }
+    write!(f, "{}", parts.join(","))
+}
+
+/// Written back as the five cron fields, `HH:MM` comes out as `MM HH * * *`
+impl fmt::Display for Cron {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_field(f, self.minutes, 0, 59)?;
+        write!(f, " ")?;
+        write_field(f, self.hours, 0, 23)?;
+        write!(f, " ")?;
+        write_field(f, self.days, 1, 31)?;
+        write!(f, " ")?;
+        write_field(f, self.months, 1, 12)?;
+        write!(f, " ")?;
+        if self.weekdays != 0 {
+            write_field(f, self.weekdays, 0, 6)?;
+        }
+        let mut first = self.weekdays == 0;
+        for (weekday, weeks) in self.nth_weekdays.iter().enumerate() {
+            for n in (1..=5).filter(|n| weeks & (1 << (n - 1)) != 0) {
+                write!(f, "{}{}#{}", if first { "" } else { "," }, weekday, n)?;
+                first = false;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn oslo(y: i32, m: u32, d: u32, h: u32, min: u32) -> DateTime<Tz> {
+        Oslo.with_ymd_and_hms(y, m, d, h, min, 0).unwrap()
+    }
+
+    fn fires(cron: &str, after: DateTime<Tz>, count: usize) -> Vec<DateTime<Tz>> {
+        let cron: Cron = cron.parse().unwrap();
+        let mut times = Vec::new();
+        let mut last = after;
+        for _ in 0..count {
+            last = cron.next_after(&last).unwrap();
+            times.push(last);
+        }
+        times
+    }
+
+    #[test]
+    fn weekdays_at_seven() {
+        // Friday 13 January 2023, after the post went out
+        let times = fires("0 7 * * 1-5", oslo(2023, 1, 13, 7, 0), 3);
+        assert_eq!(
+            times,
+            vec![
+                oslo(2023, 1, 16, 7, 0),
+                oslo(2023, 1, 17, 7, 0),
+                oslo(2023, 1, 18, 7, 0)
+            ]
+        );
+    }
+
+    #[test]
+    fn first_monday_of_the_month() {
+        let times = fires("30 9 * * mon#1", oslo(2023, 1, 1, 0, 0), 3);
+        assert_eq!(
+            times,
+            vec![
+                oslo(2023, 1, 2, 9, 30),
+                oslo(2023, 2, 6, 9, 30),
+                oslo(2023, 3, 6, 9, 30)
+            ]
+        );
+    }
+
+    #[test]
+    fn restricted_day_fields_match_either() {
+        // The 1st, and every Sunday
+        let times = fires("0 12 1 * 0", oslo(2023, 1, 28, 0, 0), 3);
+        assert_eq!(
+            times,
+            vec![
+                oslo(2023, 1, 29, 12, 0),
+                oslo(2023, 2, 1, 12, 0),
+                oslo(2023, 2, 5, 12, 0)
+            ]
+        );
+    }
+
+    #[test]
+    fn steps_and_lists() {
+        let times = fires("*/20 8,16 * * *", oslo(2023, 1, 1, 8, 30), 4);
+        assert_eq!(
+            times,
+            vec![
+                oslo(2023, 1, 1, 8, 40),
+                oslo(2023, 1, 1, 16, 0),
+                oslo(2023, 1, 1, 16, 20),
+                oslo(2023, 1, 1, 16, 40)
+            ]
+        );
+    }
+
+    #[test]
+    fn time_is_shorthand_for_daily() {
+        let cron: Cron = "08:00".parse().unwrap();
+        assert_eq!(cron, Cron::daily(Time::new_unchecked(8, 0, 0)));
+        assert_eq!(cron, "0 8 * * *".parse().unwrap());
+        assert_eq!(cron.to_string(), "0 8 * * *");
+        assert!("08:00:30".parse::<Cron>().is_err());
+    }
+
+    #[test]
+    fn display_round_trips() {
+        for cron in [
+            "0 7 * * 1-5",
+            "30 9 * * 1#1",
+            "0,15,30,45 8-10 1,15 1-6,12 *",
+            "0 12 * * 0,6",
+        ] {
+            assert_eq!(cron.parse::<Cron>().unwrap().to_string(), cron);
+        }
+        assert_eq!(
+            "0 9 * jan-mar sun,7".parse::<Cron>().unwrap().to_string(),
+            "0 9 * 1-3 0"
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_expressions() {
+        for cron in [
+            "",
+            "0 7 * *",
+            "60 7 * * *",
+            "0 24 * * *",
+            "0 7 0 * *",
+            "0 7 * 13 *",
+            "0 7 * * 8",
+            "0 7 * * mon#6",
+            "*/0 7 * * *",
+            "0 9-7 * * *",
+            "0 7 30 2 *",
+        ] {
+            assert!(cron.parse::<Cron>().is_err(), "{} should be rejected", cron);
+        }
+    }
+}
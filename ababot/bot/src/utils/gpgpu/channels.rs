@@ -5,6 +5,9 @@ use tokio::sync::{mpsc::Sender, oneshot};
 
 use super::worker::{GpuWork, GpuWorkType};
 
+/// Tasks that can wait for the GPU handler before senders have to wait too
+pub const GPU_QUEUE_SIZE: usize = 100;
+
 #[derive(Debug)]
 pub struct GpuTask<T>
 where
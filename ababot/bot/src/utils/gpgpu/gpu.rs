@@ -13,7 +13,8 @@ pub async fn gpu_handler(receiver: &mut mpsc::Receiver<GPU>) -> Result<(), Box<d
     loop {
         let data = match receiver.recv().await {
             Some(data) => data,
-            None => continue,
+            // Every sender is gone, nothing can ask for work anymore
+            None => return Ok(()),
         };
 
         match data {
@@ -1,10 +1,15 @@
-pub mod background_threads;
+pub mod clock;
+pub mod cron;
 pub mod gpgpu;
+pub mod retry;
+pub mod shutdown;
+pub mod supervisor;
 pub mod time;
 
 use serenity::{
     http::Http,
     model::prelude::{ChannelId, GuildId},
+    prelude::Context,
 };
 use std::env;
 use tracing::{instrument, metadata::LevelFilter, Level};
@@ -46,33 +51,73 @@ pub fn get_logger() -> (
     (subscriber, guard)
 }
 
+/// Guilds to register commands in, read from the comma separated `GUILD_ID` environment variable.
+/// An empty list means the commands should be registered globally
+pub fn command_guilds() -> Vec<GuildId> {
+    env::var("GUILD_ID")
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|id| !id.is_empty())
+        .filter_map(|id| match id.parse() {
+            Ok(id) => Some(GuildId(id)),
+            Err(_) => {
+                tracing::error!("Ignoring guild id {id}, it must be an integer");
+                None
+            }
+        })
+        .collect()
+}
+
+/// Find the channel with the given name in a guild, creating it if it does not exist
 #[instrument(skip(http))]
-pub async fn get_channel_id<T>(name: T, http: &Http) -> Result<ChannelId, &'static str>
+pub async fn get_channel_id<T>(
+    guild: GuildId,
+    name: T,
+    http: &Http,
+) -> Result<ChannelId, &'static str>
 where
     T: AsRef<str> + std::fmt::Debug,
 {
-    let guild = GuildId(
-        env::var("GUILD_ID")
-            .expect("Guild ID must be set as enviroment variable")
-            .parse::<u64>()
-            .expect("Guild ID must be a valid integer"),
-    );
+    if let Some(channel) = find_channel(guild, name.as_ref(), http).await? {
+        return Ok(channel);
+    }
+    let r = guild.create_channel(http, |c| c.name(name.as_ref())).await;
+    match r {
+        Ok(c) => Ok(c.id),
+        Err(_) => Err("Error creating guild"),
+    }
+}
+
+/// Find the channel with the given name in a guild
+#[instrument(skip(http))]
+pub async fn find_channel(
+    guild: GuildId,
+    name: &str,
+    http: &Http,
+) -> Result<Option<ChannelId>, &'static str> {
     let channels = guild.channels(http).await.map_err(|_e| {
         tracing::warn!("Failed to fetch channels for guild {}", guild);
         "Error fetching channels"
     })?;
 
-    let first = channels
+    Ok(channels
         .into_iter()
-        .find(|(_, g)| g.name() == name.as_ref());
-    match first {
-        Some((c, _)) => Ok(c),
-        None => {
-            let r = guild.create_channel(http, |c| c.name(name.as_ref())).await;
-            match r {
-                Ok(c) => Ok(c.id),
-                Err(_) => Err("Error creating guild"),
-            }
+        .find(|(_, g)| g.name() == name)
+        .map(|(c, _)| c))
+}
+
+/// The channel with the given name in every guild the bot is in.
+/// Guilds opt in to a background task by creating the channel it posts to
+#[instrument(skip(ctx))]
+pub async fn opted_in_channels(name: &str, ctx: &Context) -> Vec<ChannelId> {
+    let mut channels = Vec::new();
+    for guild in ctx.cache.guilds() {
+        match find_channel(guild, name, &ctx.http).await {
+            Ok(Some(channel)) => channels.push(channel),
+            Ok(None) => tracing::debug!("Guild {} has not opted in to {}", guild, name),
+            Err(e) => tracing::warn!("Skipping guild {}: {}", guild, e),
         }
     }
+    channels
 }
@@ -0,0 +1,127 @@
+use std::{fmt::Display, future::Future, time::Duration};
+
+use rand::Rng;
+use tokio::time::Instant;
+
+/// How a failed action is tried again: the delay doubles after every failure, up to `max_delay`,
+/// and it is given up once the next attempt would start after `deadline`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Backoff {
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+    /// Counted from the first attempt
+    pub deadline: Duration,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Backoff {
+            initial_delay: Duration::from_secs(10),
+            max_delay: Duration::from_secs(10 * 60),
+            deadline: Duration::from_secs(60 * 60),
+        }
+    }
+}
+
+impl Backoff {
+    /// Give up by `deadline` at the latest
+    pub fn until(self, deadline: Duration) -> Self {
+        Backoff {
+            deadline: self.deadline.min(deadline),
+            ..self
+        }
+    }
+
+    /// Delay after the given number of failures in a row, before jitter
+    fn delay(&self, failures: u32) -> Duration {
+        self.initial_delay
+            .saturating_mul(2u32.saturating_pow(failures.saturating_sub(1)))
+            .min(self.max_delay)
+    }
+}
+
+/// Between half and all of the delay, so jobs that failed together do not retry together
+fn jitter(delay: Duration) -> Duration {
+    delay.mul_f64(rand::thread_rng().gen_range(0.5..=1.0))
+}
+
+/// Run the action until it succeeds or the [Backoff] gives up, returning the last error then.
+/// Every failure but the last is logged
+pub async fn retry<Action, Async, T, E>(
+    name: &str,
+    backoff: Backoff,
+    mut action: Action,
+) -> Result<T, E>
+where
+    Action: FnMut() -> Async,
+    Async: Future<Output = Result<T, E>>,
+    E: Display,
+{
+    let started = Instant::now();
+    let mut failures = 0;
+    loop {
+        let error = match action().await {
+            Ok(value) => return Ok(value),
+            Err(e) => e,
+        };
+        failures += 1;
+        let delay = jitter(backoff.delay(failures));
+        if started.elapsed() + delay > backoff.deadline {
+            return Err(error);
+        }
+        tracing::warn!(
+            "{name} failed ({failures} in a row): {error}. Retrying in {}s",
+            delay.as_secs()
+        );
+        tokio::time::sleep(delay).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use super::*;
+
+    #[test]
+    fn delay_doubles_up_to_the_limit() {
+        let backoff = Backoff::default();
+        assert_eq!(backoff.delay(1), Duration::from_secs(10));
+        assert_eq!(backoff.delay(3), Duration::from_secs(40));
+        assert_eq!(backoff.delay(100), backoff.max_delay);
+        for _ in 0..100 {
+            let delay = jitter(Duration::from_secs(40));
+            assert!(delay >= Duration::from_secs(20) && delay <= Duration::from_secs(40));
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn retries_until_success() {
+        let attempts = AtomicU32::new(0);
+        let result = retry("flaky", Backoff::default(), || async {
+            match attempts.fetch_add(1, Ordering::SeqCst) {
+                0 | 1 => Err("Timed out"),
+                _ => Ok(42),
+            }
+        })
+        .await;
+        assert_eq!(result, Ok(42));
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn gives_up_at_the_deadline() {
+        let attempts = AtomicU32::new(0);
+        let started = Instant::now();
+        let backoff = Backoff::default().until(Duration::from_secs(5 * 60));
+        let result: Result<(), _> = retry("broken", backoff, || async {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            Err("Service unavailable")
+        })
+        .await;
+        assert_eq!(result, Err("Service unavailable"));
+        assert!(started.elapsed() <= backoff.deadline);
+        // 10s, 20s, 40s, 80s and 160s at most add up to 310s
+        assert!(attempts.load(Ordering::SeqCst) >= 5);
+    }
+}
@@ -0,0 +1,168 @@
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use serenity::prelude::{Context, TypeMapKey};
+use tokio::sync::{watch, Notify};
+
+/// Coordinates a graceful shutdown.
+/// Work that should not be cut off holds an [InFlight] guard, once shutdown starts
+/// no new guards are handed out and [Shutdown::drain] waits for the existing ones
+pub struct Shutdown {
+    stopping: watch::Sender<bool>,
+    in_flight: AtomicUsize,
+    idle: Notify,
+}
+
+impl TypeMapKey for Shutdown {
+    type Value = Arc<Shutdown>;
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Shutdown::new()
+    }
+}
+
+/// Keeps the bot from shutting down until it is dropped, see [Shutdown::enter]
+pub struct InFlight(Arc<Shutdown>);
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        if self.0.in_flight.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.0.idle.notify_waiters();
+        }
+    }
+}
+
+impl Shutdown {
+    pub fn new() -> Self {
+        Shutdown {
+            stopping: watch::channel(false).0,
+            in_flight: AtomicUsize::new(0),
+            idle: Notify::new(),
+        }
+    }
+
+    /// Register work that should finish before the bot exits.
+    /// Returns `None` once shutdown has started, the work should then not be started at all
+    pub fn enter(self: &Arc<Self>) -> Option<InFlight> {
+        self.in_flight.fetch_add(1, Ordering::SeqCst);
+        // Created before the check, so the count is given back if shutdown already started
+        let guard = InFlight(self.clone());
+        if self.is_stopping() {
+            return None;
+        }
+        Some(guard)
+    }
+
+    pub fn is_stopping(&self) -> bool {
+        *self.stopping.borrow()
+    }
+
+    /// Resolves once shutdown has started, for work that waits too long to hold an [InFlight]
+    pub async fn stopped(&self) {
+        stopped(&mut self.stopping.subscribe()).await
+    }
+
+    /// Stop handing out [InFlight] guards and wait for the existing ones to be dropped.
+    /// Returns `false` if some were still held when the deadline passed
+    pub async fn drain(&self, deadline: Duration) -> bool {
+        self.stopping.send_replace(true);
+        let idle = async {
+            loop {
+                // Registered before the check so a guard dropped in between is not missed
+                let notified = self.idle.notified();
+                if self.in_flight.load(Ordering::SeqCst) == 0 {
+                    return;
+                }
+                notified.await;
+            }
+        };
+        tokio::time::timeout(deadline, idle).await.is_ok()
+    }
+
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::SeqCst)
+    }
+
+    /// Get the shutdown coordinator from the context
+    /// # Panics
+    /// Panics if it has not been inserted at startup
+    pub async fn get(ctx: &Context) -> Arc<Shutdown> {
+        ctx.data
+            .read()
+            .await
+            .get::<Shutdown>()
+            .expect("Shutdown not found in data")
+            .clone()
+    }
+}
+
+/// Resolves once the flag is set or its sender is gone
+pub async fn stopped(flag: &mut watch::Receiver<bool>) {
+    while !*flag.borrow() {
+        if flag.changed().await.is_err() {
+            return;
+        }
+    }
+}
+
+/// Resolves on SIGTERM, which docker sends on stop, or on Ctrl-C
+pub async fn signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                tokio::select! {
+                    _ = terminate.recv() => tracing::info!("Received SIGTERM"),
+                    _ = tokio::signal::ctrl_c() => tracing::info!("Received Ctrl-C"),
+                }
+                return;
+            }
+            Err(e) => tracing::error!("Could not listen for SIGTERM: {}", e),
+        }
+    }
+    if let Err(e) = tokio::signal::ctrl_c().await {
+        tracing::error!("Could not listen for Ctrl-C: {}", e);
+        std::future::pending::<()>().await;
+    }
+    tracing::info!("Received Ctrl-C");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn drain_waits_for_in_flight_work() {
+        let shutdown = Arc::new(Shutdown::new());
+        let guard = shutdown.enter().unwrap();
+
+        let drained = tokio::spawn({
+            let shutdown = shutdown.clone();
+            async move { shutdown.drain(Duration::from_secs(10)).await }
+        });
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        assert!(shutdown.is_stopping());
+        assert!(shutdown.enter().is_none());
+        assert_eq!(shutdown.in_flight(), 1);
+
+        drop(guard);
+        assert!(drained.await.unwrap());
+        assert_eq!(shutdown.in_flight(), 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn drain_gives_up_at_the_deadline() {
+        let shutdown = Arc::new(Shutdown::new());
+        let _guard = shutdown.enter().unwrap();
+        assert!(!shutdown.drain(Duration::from_secs(10)).await);
+        shutdown.stopped().await;
+    }
+}
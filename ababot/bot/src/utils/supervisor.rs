@@ -0,0 +1,753 @@
+use std::{
+    any::Any,
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use chrono_tz::Europe::Oslo;
+use serenity::prelude::{Context, TypeMapKey};
+use tokio::{
+    sync::{watch, Notify},
+    task::JoinHandle,
+};
+
+use super::{
+    clock::SharedClock,
+    retry::{retry, Backoff},
+    shutdown::stopped,
+    time::{describe, repeat, until_next_run, Interval},
+};
+use crate::{
+    config::Config,
+    error::{self, BotError},
+    storage::Storage,
+};
+
+/// Delay before the first restart of a crashed task, doubled for every crash in a row
+const INITIAL_BACKOFF: Duration = Duration::from_secs(5);
+const MAX_BACKOFF: Duration = Duration::from_secs(30 * 60);
+/// A task that stayed up this long before crashing starts over at [INITIAL_BACKOFF]
+const HEALTHY_AFTER: Duration = Duration::from_secs(60 * 60);
+
+type TaskFuture = Pin<Box<dyn Future<Output = ()> + Send>>;
+type StartTask = Box<dyn Fn(Arc<Context>, TaskReporter) -> TaskFuture + Send + Sync>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaskState {
+    /// Added, but the bot has not connected yet
+    Pending,
+    /// Waiting for its next run
+    Idle,
+    Running,
+    /// Crashed, waiting to be started again
+    Restarting,
+    Stopped,
+}
+
+/// Snapshot of a background task, see [Supervisor::statuses]
+#[derive(Debug, Clone)]
+pub struct TaskStatus {
+    pub name: &'static str,
+    pub state: TaskState,
+    /// Scheduled runs are skipped while paused
+    pub paused: bool,
+    /// Set once the task has scheduled its action
+    pub schedule: Option<String>,
+    pub restarts: u32,
+    pub last_run: Option<DateTime<Utc>>,
+    /// Why the last run failed once retries ran out, `None` if it succeeded
+    pub last_failure: Option<String>,
+    pub next_run: Option<DateTime<Utc>>,
+    /// Panic message of the last crash
+    pub last_error: Option<String>,
+}
+
+impl TaskStatus {
+    fn new(name: &'static str) -> Self {
+        TaskStatus {
+            name,
+            state: TaskState::Pending,
+            paused: false,
+            schedule: None,
+            restarts: 0,
+            last_run: None,
+            last_failure: None,
+            next_run: None,
+            last_error: None,
+        }
+    }
+}
+
+/// Where the runs of a task are recorded, so runs missed while the bot was down are made up for
+#[derive(Clone)]
+struct History {
+    storage: Storage,
+    /// How long after a missed run it is still made up for
+    catch_up: chrono::Duration,
+}
+
+/// Handed to a background task so it can keep its status up to date
+#[derive(Clone)]
+pub struct TaskReporter {
+    status: Arc<Mutex<TaskStatus>>,
+    /// Set by [Supervisor::run_now]
+    run_now: Arc<Notify>,
+    /// Set once the supervisor has started, see [Supervisor::start]
+    history: Option<History>,
+    /// Failed runs are retried, then reported to the admin channel through the context
+    backoff: Backoff,
+    ctx: Option<Arc<Context>>,
+    clock: SharedClock,
+}
+
+impl TaskReporter {
+    fn new(name: &'static str) -> Self {
+        TaskReporter {
+            status: Arc::new(Mutex::new(TaskStatus::new(name))),
+            run_now: Arc::new(Notify::new()),
+            history: None,
+            backoff: Backoff::default(),
+            ctx: None,
+            clock: SharedClock::default(),
+        }
+    }
+
+    fn update<F: FnOnce(&mut TaskStatus) -> T, T>(&self, f: F) -> T {
+        // A task panicking while holding the lock can not leave the status half written
+        f(&mut self.status.lock().unwrap_or_else(|e| e.into_inner()))
+    }
+
+    /// [schedule](super::time::schedule) the action, recording every run and when the next one
+    /// is due. A run missed while the bot was down is made up for first, see
+    /// [TaskReporter::missed_run]. Scheduled runs are skipped while the task is paused,
+    /// [Supervisor::run_now] runs it anyway. Failed runs are retried until the next one is due
+    pub async fn schedule<Action, Async>(&self, interval: Interval, action: Action)
+    where
+        Action: Fn() -> Async,
+        Async: Future<Output = Result<(), BotError>>,
+    {
+        self.update(|s| s.schedule = Some(interval.to_string()));
+        if let Some(missed) = self.missed_run(interval).await {
+            let (name, paused) = self.update(|s| (s.name, s.paused));
+            if paused {
+                tracing::info!("Not making up for the missed run of paused task {name}");
+            } else {
+                tracing::info!("Background task {name} missed its run at {missed}, running it now");
+                self.run(&action, interval).await;
+            }
+        }
+
+        // Scheduled and requested runs take turns, so a run is never started while one is going
+        let running = tokio::sync::Mutex::new(());
+        self.next_run(interval.next_run_after(self.clock.now_in_oslo()));
+        let scheduled = repeat(&*self.clock, interval, || async {
+            let _running = running.lock().await;
+            let (name, paused) = self.update(|s| (s.name, s.paused));
+            if paused {
+                tracing::info!("Skipping paused task {name}");
+            } else {
+                self.run(&action, interval).await;
+            }
+            self.next_run(interval.next_run_after(self.clock.now_in_oslo()));
+        });
+        let requested = async {
+            loop {
+                self.run_now.notified().await;
+                let _running = running.lock().await;
+                self.run(&action, interval).await;
+            }
+        };
+
+        tokio::select! {
+            _ = scheduled => {}
+            _ = requested => {}
+        }
+    }
+
+    /// Run the action, retrying until it succeeds or the next run is due.
+    /// Only successful runs are recorded, so a failed one is made up for after a restart
+    async fn run<Action, Async>(&self, action: &Action, interval: Interval)
+    where
+        Action: Fn() -> Async,
+        Async: Future<Output = Result<(), BotError>>,
+    {
+        let name = self.update(|s| {
+            s.state = TaskState::Running;
+            s.name
+        });
+        let backoff = self.backoff.until(until_next_run(&*self.clock, interval));
+        let result = retry(&format!("Background task {name}"), backoff, action).await;
+        let finished = self.finished(&result);
+
+        if let Err(e) = result {
+            tracing::error!("Background task {name} failed, giving up until the next run: {e}");
+            if let Some(ctx) = &self.ctx {
+                let context = format!("Gave up after retrying for {}", describe(backoff.deadline));
+                error::report(ctx, name, &context, &e).await;
+            }
+        } else if let Some(history) = &self.history {
+            if let Err(e) = history.storage.record_job_run(name, finished).await {
+                tracing::error!("Could not record the run of {name}: {e}");
+            }
+        }
+    }
+
+    /// The latest scheduled run since the last recorded run, if it is within the catch up window.
+    /// Tasks that have never been recorded, or are not tied to the wall clock, have nothing to
+    /// make up for
+    async fn missed_run(&self, interval: Interval) -> Option<DateTime<chrono_tz::Tz>> {
+        let history = self.history.as_ref()?;
+        if !interval.has_slots() {
+            return None;
+        }
+        let name = self.update(|s| s.name);
+        let last_run = match history.storage.last_job_run(name).await {
+            Ok(last_run) => last_run?,
+            Err(e) => {
+                tracing::error!("Could not read the last run of {name}: {e}");
+                return None;
+            }
+        };
+        self.update(|s| s.last_run = s.last_run.or(Some(last_run)));
+
+        let now = self.clock.now_in_oslo();
+        let mut from = last_run.with_timezone(&Oslo).max(now - history.catch_up);
+        let mut missed = None;
+        while let Some(slot) = interval.next_run_after(from).filter(|slot| *slot <= now) {
+            missed = Some(slot);
+            from = slot;
+        }
+        missed
+    }
+
+    /// Whether scheduled runs are skipped, for tasks keeping their own schedule
+    pub fn paused(&self) -> bool {
+        self.update(|s| s.paused)
+    }
+
+    /// Resolves once [Supervisor::run_now] asks for a run, for tasks keeping their own schedule
+    pub async fn run_requested(&self) {
+        self.run_now.notified().await
+    }
+
+    /// Record that a task keeping its own schedule has started a run
+    pub fn running(&self) {
+        self.update(|s| s.state = TaskState::Running);
+    }
+
+    /// Record that a task has finished a run and how it went, returning when it finished
+    pub fn finished(&self, result: &Result<(), BotError>) -> DateTime<Utc> {
+        let finished = self.clock.now();
+        self.update(|s| {
+            s.state = TaskState::Idle;
+            s.last_run = Some(finished);
+            s.last_failure = result.as_ref().err().map(ToString::to_string);
+        });
+        finished
+    }
+
+    pub fn next_run<Tz: chrono::TimeZone>(&self, at: Option<DateTime<Tz>>) {
+        self.update(|s| s.next_run = at.map(|at| at.with_timezone(&Utc)));
+    }
+}
+
+struct Task {
+    name: &'static str,
+    start: StartTask,
+    reporter: TaskReporter,
+}
+
+/// Runs the background tasks, restarting them with backoff when they panic.
+/// Started once the bot has connected, see [Supervisor::start]
+pub struct Supervisor {
+    tasks: Vec<Arc<Task>>,
+    started: AtomicBool,
+    shutdown: watch::Sender<bool>,
+    monitors: tokio::sync::Mutex<Vec<JoinHandle<()>>>,
+}
+
+impl TypeMapKey for Supervisor {
+    type Value = Arc<Supervisor>;
+}
+
+impl Default for Supervisor {
+    fn default() -> Self {
+        Supervisor::new()
+    }
+}
+
+impl Supervisor {
+    pub fn new() -> Self {
+        Supervisor {
+            tasks: Vec::new(),
+            started: AtomicBool::new(false),
+            shutdown: watch::channel(false).0,
+            monitors: tokio::sync::Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Add a task, `start` is called again every time the task has to be restarted
+    /// # Panics
+    /// Panics if a task with the same name is already added
+    pub fn add<Start, Fut>(&mut self, name: &'static str, start: Start)
+    where
+        Start: Fn(Arc<Context>, TaskReporter) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        if self.tasks.iter().any(|t| t.name == name) {
+            tracing::error!("Background task {name} added twice");
+            panic!("Background task {name} added twice");
+        }
+        self.tasks.push(Arc::new(Task {
+            name,
+            start: Box::new(move |ctx, reporter| Box::pin(start(ctx, reporter))),
+            reporter: TaskReporter::new(name),
+        }));
+    }
+
+    /// Start every task. Calling it again, e.g. when the gateway reconnects, does nothing
+    pub async fn start(&self, ctx: Arc<Context>) {
+        if self.started.swap(true, Ordering::SeqCst) {
+            tracing::info!("Background tasks already running");
+            return;
+        }
+        let config = Config::get(&ctx).await;
+        let history = History {
+            storage: Storage::get(&ctx).await,
+            catch_up: chrono::Duration::seconds(config.scheduler.catch_up_window as i64),
+        };
+        let backoff = Backoff {
+            deadline: Duration::from_secs(config.scheduler.retry_deadline),
+            ..Backoff::default()
+        };
+        let clock = SharedClock::get(&ctx).await;
+        let mut monitors = self.monitors.lock().await;
+        for task in &self.tasks {
+            tracing::info!("Starting background task {}", task.name);
+            let ctx = ctx.clone();
+            let start_task = task.clone();
+            let reporter = TaskReporter {
+                history: Some(history.clone()),
+                backoff,
+                ctx: Some(ctx.clone()),
+                clock: clock.clone(),
+                ..task.reporter.clone()
+            };
+            monitors.push(tokio::spawn(monitor(
+                task.name,
+                reporter,
+                move |reporter| (start_task.start)(ctx.clone(), reporter),
+                self.shutdown.subscribe(),
+            )));
+        }
+    }
+
+    /// Stop every task and wait for them to finish
+    pub async fn shutdown(&self) {
+        self.shutdown.send_replace(true);
+        for monitor in self.monitors.lock().await.drain(..) {
+            if let Err(e) = monitor.await {
+                tracing::error!("Background task monitor failed: {}", e);
+            }
+        }
+    }
+
+    pub fn statuses(&self) -> Vec<TaskStatus> {
+        self.tasks
+            .iter()
+            .map(|t| t.reporter.update(|s| s.clone()))
+            .collect()
+    }
+
+    fn task(&self, name: &str) -> Option<&Task> {
+        self.tasks.iter().find(|t| t.name == name).map(|t| &**t)
+    }
+
+    /// Run the action of a task now, outside its schedule and even if it is paused.
+    /// A task that is running does it once the run is done, and one that is restarting once it
+    /// is back up. Asking again before it has started runs it only once.
+    /// Returns `None` if there is no such task
+    pub fn run_now(&self, name: &str) -> Option<TaskStatus> {
+        let task = self.task(name)?;
+        task.reporter.run_now.notify_one();
+        Some(task.reporter.update(|s| s.clone()))
+    }
+
+    /// Skip the scheduled runs of a task until it is resumed.
+    /// Returns `None` if there is no such task
+    pub fn set_paused(&self, name: &str, paused: bool) -> Option<TaskStatus> {
+        let task = self.task(name)?;
+        tracing::info!(
+            "{} background task {name}",
+            if paused { "Pausing" } else { "Resuming" }
+        );
+        Some(task.reporter.update(|s| {
+            s.paused = paused;
+            s.clone()
+        }))
+    }
+
+    /// Get the supervisor from the context
+    /// # Panics
+    /// Panics if the supervisor has not been inserted at startup
+    pub async fn get(ctx: &Context) -> Arc<Supervisor> {
+        ctx.data
+            .read()
+            .await
+            .get::<Supervisor>()
+            .expect("Supervisor not found in data")
+            .clone()
+    }
+}
+
+/// Keep one task running until shutdown
+async fn monitor<Start>(
+    name: &'static str,
+    reporter: TaskReporter,
+    start: Start,
+    mut shutdown: watch::Receiver<bool>,
+) where
+    Start: Fn(TaskReporter) -> TaskFuture,
+{
+    let mut failures = 0;
+    loop {
+        reporter.update(|s| s.state = TaskState::Idle);
+        let started = tokio::time::Instant::now();
+        let mut handle = tokio::spawn(start(reporter.clone()));
+
+        let result = tokio::select! {
+            result = &mut handle => result,
+            _ = stopped(&mut shutdown) => {
+                handle.abort();
+                let _ = handle.await;
+                break;
+            }
+        };
+
+        let error = match result {
+            Ok(()) => {
+                tracing::info!("Background task {name} finished");
+                break;
+            }
+            Err(e) if e.is_panic() => panic_message(e.into_panic()),
+            Err(e) => e.to_string(),
+        };
+
+        if started.elapsed() >= HEALTHY_AFTER {
+            failures = 0;
+        }
+        failures += 1;
+        let delay = backoff(failures);
+        tracing::error!(
+            "Background task {name} crashed: {error}. Restarting in {}s",
+            delay.as_secs()
+        );
+        reporter.update(|s| {
+            s.state = TaskState::Restarting;
+            s.restarts += 1;
+            s.next_run = None;
+            s.last_error = Some(error);
+        });
+
+        tokio::select! {
+            _ = tokio::time::sleep(delay) => {}
+            _ = stopped(&mut shutdown) => break,
+        }
+    }
+    reporter.update(|s| {
+        s.state = TaskState::Stopped;
+        s.next_run = None;
+    });
+}
+
+fn backoff(failures: u32) -> Duration {
+    INITIAL_BACKOFF
+        .saturating_mul(2u32.saturating_pow(failures.saturating_sub(1)))
+        .min(MAX_BACKOFF)
+}
+
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    match payload.downcast::<String>() {
+        Ok(message) => *message,
+        Err(payload) => match payload.downcast::<&'static str>() {
+            Ok(message) => message.to_string(),
+            Err(_) => "Unknown panic".to_string(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicU32;
+
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::utils::clock::TestClock;
+
+    #[test]
+    fn backoff_doubles_up_to_the_limit() {
+        assert_eq!(backoff(1), INITIAL_BACKOFF);
+        assert_eq!(backoff(2), INITIAL_BACKOFF * 2);
+        assert_eq!(backoff(3), INITIAL_BACKOFF * 4);
+        assert_eq!(backoff(100), MAX_BACKOFF);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn crashed_tasks_are_restarted() {
+        let reporter = TaskReporter::new("crashing");
+        let starts = Arc::new(AtomicU32::new(0));
+        let (shutdown, receiver) = watch::channel(false);
+
+        let counter = starts.clone();
+        let monitor = tokio::spawn(monitor(
+            "crashing",
+            reporter.clone(),
+            move |_| {
+                let starts = counter.fetch_add(1, Ordering::SeqCst) + 1;
+                Box::pin(async move {
+                    if starts < 3 {
+                        panic!("Not exactly 10 hours");
+                    }
+                    std::future::pending::<()>().await
+                })
+            },
+            receiver,
+        ));
+
+        tokio::time::sleep(INITIAL_BACKOFF * 4).await;
+        assert_eq!(starts.load(Ordering::SeqCst), 3);
+        {
+            let status = reporter.status.lock().unwrap();
+            assert_eq!(status.restarts, 2);
+            assert_eq!(status.last_error.as_deref(), Some("Not exactly 10 hours"));
+            assert_eq!(status.state, TaskState::Idle);
+        }
+
+        shutdown.send_replace(true);
+        monitor.await.unwrap();
+        assert_eq!(reporter.status.lock().unwrap().state, TaskState::Stopped);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn shutdown_interrupts_backoff() {
+        let reporter = TaskReporter::new("always crashing");
+        let (shutdown, receiver) = watch::channel(false);
+        let monitor = tokio::spawn(monitor(
+            "always crashing",
+            reporter.clone(),
+            |_| Box::pin(async { panic!("Crash") }),
+            receiver,
+        ));
+
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        assert_eq!(reporter.status.lock().unwrap().state, TaskState::Restarting);
+        shutdown.send_replace(true);
+        monitor.await.unwrap();
+        assert_eq!(reporter.status.lock().unwrap().state, TaskState::Stopped);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn paused_tasks_only_run_on_request() {
+        let reporter = TaskReporter::new("counting");
+        reporter.update(|s| s.paused = true);
+        let runs = Arc::new(AtomicU32::new(0));
+        let task = tokio::spawn({
+            let reporter = reporter.clone();
+            let runs = runs.clone();
+            async move {
+                reporter
+                    .schedule(Interval::EveryDelta(Duration::from_secs(60)), || async {
+                        runs.fetch_add(1, Ordering::SeqCst);
+                        Ok(())
+                    })
+                    .await
+            }
+        });
+
+        tokio::time::sleep(Duration::from_secs(150)).await;
+        assert_eq!(runs.load(Ordering::SeqCst), 0);
+        assert_eq!(
+            reporter.status.lock().unwrap().schedule.as_deref(),
+            Some("every 1m")
+        );
+
+        reporter.run_now.notify_one();
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+        assert!(reporter.status.lock().unwrap().last_run.is_some());
+        task.abort();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn requested_runs_wait_for_the_running_one() {
+        let reporter = TaskReporter::new("slow");
+        let running = Arc::new(AtomicU32::new(0));
+        let runs = Arc::new(AtomicU32::new(0));
+        let task = tokio::spawn({
+            let reporter = reporter.clone();
+            let running = running.clone();
+            let runs = runs.clone();
+            async move {
+                reporter
+                    .schedule(Interval::EveryDelta(Duration::from_secs(60)), || async {
+                        assert_eq!(running.fetch_add(1, Ordering::SeqCst), 0);
+                        tokio::time::sleep(Duration::from_secs(10)).await;
+                        running.fetch_sub(1, Ordering::SeqCst);
+                        runs.fetch_add(1, Ordering::SeqCst);
+                        Ok(())
+                    })
+                    .await
+            }
+        });
+
+        // The first run starts right away
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        reporter.run_now.notify_one();
+        reporter.run_now.notify_one();
+        tokio::time::sleep(Duration::from_secs(5)).await;
+        assert_eq!(runs.load(Ordering::SeqCst), 0);
+        tokio::time::sleep(Duration::from_secs(20)).await;
+        assert_eq!(runs.load(Ordering::SeqCst), 2);
+        assert!(!task.is_finished());
+        task.abort();
+    }
+
+    fn with_history(name: &'static str, catch_up: chrono::Duration) -> TaskReporter {
+        TaskReporter {
+            history: Some(History {
+                storage: Storage::in_memory().unwrap(),
+                catch_up,
+            }),
+            ..TaskReporter::new(name)
+        }
+    }
+
+    fn oslo(d: u32, h: u32, min: u32) -> DateTime<chrono_tz::Tz> {
+        Oslo.with_ymd_and_hms(2023, 3, d, h, min, 0).unwrap()
+    }
+
+    #[tokio::test]
+    async fn missed_runs_within_the_window_are_made_up_for() {
+        // Weekdays at 07:00, and the bot comes back up on Monday 27 March at 09:05
+        let interval = Interval::Cron("0 7 * * 1-5".parse().unwrap());
+        let reporter = TaskReporter {
+            clock: SharedClock::new(TestClock::new(oslo(27, 9, 5))),
+            ..with_history("lunch", chrono::Duration::hours(3))
+        };
+        let storage = reporter.history.as_ref().unwrap().storage.clone();
+        assert_eq!(reporter.missed_run(interval).await, None);
+
+        // The last run was on Friday, only Monday's run is within the window
+        storage
+            .record_job_run("lunch", oslo(24, 7, 0).with_timezone(&Utc))
+            .await
+            .unwrap();
+        assert_eq!(reporter.missed_run(interval).await, Some(oslo(27, 7, 0)));
+        storage
+            .record_job_run("lunch", oslo(27, 7, 1).with_timezone(&Utc))
+            .await
+            .unwrap();
+        assert_eq!(reporter.missed_run(interval).await, None);
+        assert_eq!(
+            reporter
+                .missed_run(Interval::EveryDelta(Duration::from_secs(60)))
+                .await,
+            None
+        );
+
+        let late = TaskReporter {
+            clock: SharedClock::new(TestClock::new(oslo(27, 10, 30))),
+            ..reporter.clone()
+        };
+        storage
+            .record_job_run("lunch", oslo(24, 7, 0).with_timezone(&Utc))
+            .await
+            .unwrap();
+        assert_eq!(late.missed_run(interval).await, None);
+    }
+
+    #[tokio::test]
+    async fn runs_are_recorded_and_caught_up_once() {
+        let interval = Interval::Cron("0 7 * * *".parse().unwrap());
+        let clock = TestClock::new(oslo(27, 8, 0));
+        let reporter = TaskReporter {
+            clock: SharedClock::new(clock.clone()),
+            ..with_history("yr", chrono::Duration::hours(3))
+        };
+        let storage = reporter.history.as_ref().unwrap().storage.clone();
+        let before = oslo(26, 7, 0).with_timezone(&Utc);
+        storage.record_job_run("yr", before).await.unwrap();
+
+        let (ran, mut runs) = tokio::sync::mpsc::unbounded_channel();
+        let task = tokio::spawn({
+            let reporter = reporter.clone();
+            async move {
+                reporter
+                    .schedule(interval, || async {
+                        ran.send(()).unwrap();
+                        Ok(())
+                    })
+                    .await
+            }
+        });
+        tokio::time::timeout(Duration::from_secs(5), runs.recv())
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        task.abort();
+
+        let recorded = storage.last_job_run("yr").await.unwrap().unwrap();
+        assert!(recorded >= oslo(27, 8, 0));
+        assert_eq!(reporter.missed_run(interval).await, None);
+        assert!(runs.try_recv().is_err());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn failed_runs_are_retried() {
+        let reporter = TaskReporter::new("flaky");
+        let attempts = AtomicU32::new(0);
+        let interval = Interval::EveryDelta(Duration::from_secs(24 * 60 * 60));
+        reporter
+            .run(
+                &|| async {
+                    match attempts.fetch_add(1, Ordering::SeqCst) {
+                        0 | 1 => Err(BotError::internal("Timed out")),
+                        _ => Ok(()),
+                    }
+                },
+                interval,
+            )
+            .await;
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+        assert_eq!(reporter.status.lock().unwrap().state, TaskState::Idle);
+    }
+
+    #[tokio::test]
+    async fn failed_runs_are_not_recorded() {
+        let reporter = TaskReporter {
+            backoff: Backoff {
+                deadline: Duration::ZERO,
+                ..Backoff::default()
+            },
+            ..with_history("broken", chrono::Duration::hours(3))
+        };
+        let storage = reporter.history.as_ref().unwrap().storage.clone();
+        let interval = Interval::EveryDelta(Duration::from_secs(60));
+        reporter
+            .run(&|| async { Err(BotError::internal("Not found")) }, interval)
+            .await;
+        assert_eq!(storage.last_job_run("broken").await.unwrap(), None);
+        assert!(reporter.status.lock().unwrap().last_failure.is_some());
+
+        reporter.run(&|| async { Ok(()) }, interval).await;
+        assert!(storage.last_job_run("broken").await.unwrap().is_some());
+        assert_eq!(reporter.status.lock().unwrap().last_failure, None);
+    }
+}
@@ -1,19 +1,145 @@
-use std::time::Duration;
+use std::{
+    fmt,
+    future::Future,
+    str::FromStr,
+    sync::{Arc, Mutex, MutexGuard},
+    time::Duration,
+};
 
-use chrono::{Datelike, Days, Weekday};
+use chrono::{Datelike, Days, TimeZone, Weekday};
 use chrono_tz::{Europe::Oslo, Tz};
-use tracing::instrument;
+use serde::Deserialize;
+use tokio::{sync::watch, task::JoinHandle, time::Instant};
+use tracing::Instrument;
+
+pub mod holidays;
+pub mod natural;
+pub mod recurrence;
+
+use super::{
+    clock::{Clock, SharedClock, SystemClock},
+    cron::Cron,
+    retry::{retry, Backoff},
+};
+use holidays::DaysOff;
 
 #[derive(Debug, Clone, Copy)]
 pub enum Interval {
     EveryDelta(std::time::Duration),
     EveryDeltaStartAt(std::time::Duration, chrono::DateTime<Tz>),
+    /// Follows the wall clock of the [Cron], Oslo unless it was moved
+    Cron(Cron),
+    /// Like [Interval::Cron], with the runs due on weekends and public holidays handled by [DaysOff]
+    Calendar(Cron, DaysOff),
+}
+
+impl Interval {
+    /// When [schedule] runs the action next, as seen from now by the system clock.
+    /// `None` if it never will
+    pub fn next_run(&self) -> Option<chrono::DateTime<Tz>> {
+        self.next_run_after(SystemClock.now_in_oslo())
+    }
+
+    /// The first run strictly after `after`, `None` if there is none.
+    /// [Interval::EveryDelta] has no fixed slots, it runs one delta later
+    pub fn next_run_after(&self, after: chrono::DateTime<Tz>) -> Option<chrono::DateTime<Tz>> {
+        match *self {
+            Interval::EveryDelta(delta) => Some(
+                after
+                    + chrono::Duration::from_std(delta)
+                        .unwrap_or_else(|_| chrono::Duration::zero()),
+            ),
+            Interval::EveryDeltaStartAt(delta, start) => next_fire(delta, start, after),
+            Interval::Cron(cron) => cron.next_after(&after),
+            Interval::Calendar(cron, days_off) => days_off.next_run(&cron, &after),
+        }
+    }
+
+    /// Whether runs are tied to the wall clock, so a run can be missed while the bot is down
+    pub fn has_slots(&self) -> bool {
+        !matches!(self, Interval::EveryDelta(_))
+    }
+}
+
+/// The first time after `after` an action repeated every `delta` from `start` fires.
+/// Whole days are stepped on the wall clock of `start`, Oslo for everything the bot schedules,
+/// so a daily post stays at the same local time across DST changes, and times skipped in the
+/// spring are skipped. Shorter intervals step elapsed time
+fn next_fire(
+    delta: Duration,
+    start: chrono::DateTime<Tz>,
+    after: chrono::DateTime<Tz>,
+) -> Option<chrono::DateTime<Tz>> {
+    let timezone = start.timezone();
+    let after = after.with_timezone(&timezone);
+    if start > after {
+        return Some(start);
+    }
+
+    let days = delta.as_secs() / DAY_AS_SECONDS;
+    if days == 0 || Duration::from_secs(days * DAY_AS_SECONDS) != delta {
+        let delta = (delta.as_millis() as i64).max(1);
+        let elapsed = (after - start).num_milliseconds();
+        return Some(start + chrono::Duration::milliseconds((elapsed / delta + 1) * delta));
+    }
+
+    let days = days as i64;
+    let mut periods = (after.date_naive() - start.date_naive()).num_days() / days;
+    loop {
+        let local = start
+            .naive_local()
+            .checked_add_signed(chrono::Duration::days(periods * days))?;
+        if let Some(time) = timezone.from_local_datetime(&local).earliest() {
+            if time > after {
+                return Some(time);
+            }
+        }
+        periods += 1;
+    }
+}
+
+/// Written as e.g. `every 1d from 24/12 08:00`
+impl fmt::Display for Interval {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Interval::EveryDelta(delta) => write!(f, "every {}", describe(*delta)),
+            Interval::EveryDeltaStartAt(delta, start) => write!(
+                f,
+                "every {} from {}",
+                describe(*delta),
+                start.format("%d/%m %H:%M")
+            ),
+            Interval::Cron(cron) => write!(f, "cron {}", cron),
+            Interval::Calendar(cron, days_off) => write!(f, "cron {}, {}", cron, days_off),
+        }
+    }
+}
+
+/// A duration as days, hours, minutes and seconds, leaving out the zero parts
+pub fn describe(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    let parts: Vec<String> = [
+        (secs / DAY_AS_SECONDS, "d"),
+        (secs % DAY_AS_SECONDS / 3600, "h"),
+        (secs % 3600 / 60, "m"),
+        (secs % 60, "s"),
+    ]
+    .iter()
+    .filter(|(amount, _)| *amount > 0)
+    .map(|(amount, unit)| format!("{amount}{unit}"))
+    .collect();
+    if parts.is_empty() {
+        "0s".to_string()
+    } else {
+        parts.join(" ")
+    }
 }
 
 pub const WEEK_AS_SECONDS: u64 = 604800;
 pub const DAY_AS_SECONDS: u64 = 86400;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
 pub struct Time {
     hour: u8,
     minute: u8,
@@ -48,8 +174,20 @@ impl Time {
         })
     }
 
+    pub fn hour(&self) -> u8 {
+        self.hour
+    }
+
+    pub fn minute(&self) -> u8 {
+        self.minute
+    }
+
+    pub fn second(&self) -> u8 {
+        self.second
+    }
+
     pub fn today(&self) -> Option<chrono::NaiveDateTime> {
-        chrono::Utc::now().date_naive().and_hms_opt(
+        SystemClock.now().date_naive().and_hms_opt(
             self.hour as u32,
             self.minute as u32,
             self.second as u32,
@@ -57,8 +195,16 @@ impl Time {
     }
 
     pub fn nearest(&self) -> Option<chrono::DateTime<Tz>> {
-        let today = self.today()?.and_local_timezone(Oslo).earliest()?;
-        let now = chrono::Utc::now().with_timezone(&Oslo);
+        self.nearest_after(SystemClock.now_in_oslo())
+    }
+
+    /// This time of day in Oslo, the same day as `now` unless it has passed
+    pub fn nearest_after(&self, now: chrono::DateTime<Tz>) -> Option<chrono::DateTime<Tz>> {
+        let today = now
+            .date_naive()
+            .and_hms_opt(self.hour as u32, self.minute as u32, self.second as u32)?
+            .and_local_timezone(Oslo)
+            .earliest()?;
         if now > today {
             today
                 .date_naive()
@@ -72,12 +218,16 @@ impl Time {
     }
 
     pub fn nearest_unchecked(&self) -> chrono::DateTime<Tz> {
+        self.nearest_after_unchecked(SystemClock.now_in_oslo())
+    }
+
+    fn nearest_after_unchecked(&self, now: chrono::DateTime<Tz>) -> chrono::DateTime<Tz> {
         Time::new(self.hour, self.minute, self.second)
             .unwrap_or_else(|| {
                 tracing::error!("Illegal time");
                 panic!("Illegal time")
             })
-            .nearest()
+            .nearest_after(now)
             .unwrap_or_else(|| {
                 tracing::error!("Time is unrepresentable");
                 panic!("Time is unrepresentable");
@@ -95,6 +245,30 @@ impl Default for Time {
     }
 }
 
+/// Parses times written as `HH:MM` or `HH:MM:SS`
+impl FromStr for Time {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.trim().split(':').map(|p| p.parse::<u8>());
+        let (hour, minute, second) = match (parts.next(), parts.next(), parts.next(), parts.next())
+        {
+            (Some(Ok(h)), Some(Ok(m)), None, None) => (h, m, 0),
+            (Some(Ok(h)), Some(Ok(m)), Some(Ok(s)), None) => (h, m, s),
+            _ => return Err("Time must be written as HH:MM or HH:MM:SS"),
+        };
+        Time::new(hour, minute, second).ok_or("Time is outside of 00:00:00 - 23:59:59")
+    }
+}
+
+impl TryFrom<String> for Time {
+    type Error = &'static str;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
 pub struct TimeBuilder {
     hour: Option<u8>,
     minute: Option<u8>,
@@ -147,20 +321,13 @@ impl From<TimeBuilder> for Option<Time> {
 /// # Panics
 /// This function panics if the provided time is illegal
 /// i.e. hour > 23 or minute / second > 59
-#[instrument(skip(action))]
-pub async fn daily<Action, Async>(time: Time, action: Action)
+pub fn daily<Action, Async, E>(time: Time, action: Action) -> JobHandle
 where
-    Action: Fn() -> Async,
-    Async: std::future::Future<Output = ()>,
+    Action: Fn() -> Async + Send + Sync + 'static,
+    Async: Future<Output = Result<(), E>> + Send,
+    E: fmt::Display + Send,
 {
-    schedule(
-        Interval::EveryDeltaStartAt(
-            Duration::from_secs(DAY_AS_SECONDS),
-            time.nearest_unchecked(),
-        ),
-        action,
-    )
-    .await;
+    Scheduler::default().daily(time, action)
 }
 
 /// Schedules an action to be run weekly at the provided time
@@ -168,69 +335,476 @@ where
 /// # Panics
 /// This function panics if the provided time is illegal
 /// i.e. hour > 23 or minute / second > 59
-#[instrument(skip(action))]
-pub async fn weekly<Action, Async>(time: Time, day: Weekday, action: Action)
+pub fn weekly<Action, Async, E>(time: Time, day: Weekday, action: Action) -> JobHandle
 where
-    Action: Fn() -> Async,
-    Async: std::future::Future<Output = ()>,
+    Action: Fn() -> Async + Send + Sync + 'static,
+    Async: Future<Output = Result<(), E>> + Send,
+    E: fmt::Display + Send,
 {
-    let mut nearest_time = time.nearest_unchecked();
-    while nearest_time.weekday() != day {
-        nearest_time = nearest_time
-            .checked_add_days(Days::new(1))
-            .unwrap_or_else(|| {
-                tracing::error!("Date cannot be represented");
-                panic!("Date cannot be represented");
-            })
+    Scheduler::default().weekly(time, day, action)
+}
+
+/// Schedule an action to be repeated on a new task, until it is cancelled through the handle
+/// Utilizes [Scheduler::schedule] on the system clock, so the same restrictions apply
+pub fn schedule<Action, Async, E>(interval: Interval, action: Action) -> JobHandle
+where
+    Action: Fn() -> Async + Send + Sync + 'static,
+    Async: Future<Output = Result<(), E>> + Send,
+    E: fmt::Display + Send,
+{
+    Scheduler::default().schedule(interval, action)
+}
+
+/// Starts jobs that read the time from its clock, see [SharedClock]
+#[derive(Clone, Default)]
+pub struct Scheduler {
+    clock: SharedClock,
+}
+
+impl Scheduler {
+    pub fn new(clock: SharedClock) -> Self {
+        Scheduler { clock }
+    }
+
+    /// Like [daily], on the clock of the scheduler
+    pub fn daily<Action, Async, E>(&self, time: Time, action: Action) -> JobHandle
+    where
+        Action: Fn() -> Async + Send + Sync + 'static,
+        Async: Future<Output = Result<(), E>> + Send,
+        E: fmt::Display + Send,
+    {
+        let start = time.nearest_after_unchecked(self.clock.now_in_oslo());
+        self.schedule(
+            Interval::EveryDeltaStartAt(Duration::from_secs(DAY_AS_SECONDS), start),
+            action,
+        )
     }
 
-    schedule(
-        Interval::EveryDeltaStartAt(Duration::from_secs(WEEK_AS_SECONDS), nearest_time),
-        action,
-    )
-    .await;
+    /// Like [weekly], on the clock of the scheduler
+    pub fn weekly<Action, Async, E>(&self, time: Time, day: Weekday, action: Action) -> JobHandle
+    where
+        Action: Fn() -> Async + Send + Sync + 'static,
+        Async: Future<Output = Result<(), E>> + Send,
+        E: fmt::Display + Send,
+    {
+        let mut nearest_time = time.nearest_after_unchecked(self.clock.now_in_oslo());
+        while nearest_time.weekday() != day {
+            nearest_time = nearest_time
+                .checked_add_days(Days::new(1))
+                .unwrap_or_else(|| {
+                    tracing::error!("Date cannot be represented");
+                    panic!("Date cannot be represented");
+                })
+        }
+
+        self.schedule(
+            Interval::EveryDeltaStartAt(Duration::from_secs(WEEK_AS_SECONDS), nearest_time),
+            action,
+        )
+    }
+
+    /// Schedule an action to be repeated on a new task, until it is cancelled through the handle
+    /// A failed run is retried with [Backoff::default], giving up before the next run is due
+    /// [Interval::EveryDeltaStartAt] and [Interval::Cron] follow the Oslo wall clock, see [Interval::next_run].
+    /// A start time in the past is fine, the first run is the next one after now
+    /// # Panics
+    /// Panics if called outside of a Tokio runtime
+    pub fn schedule<Action, Async, E>(&self, interval: Interval, action: Action) -> JobHandle
+    where
+        Action: Fn() -> Async + Send + Sync + 'static,
+        Async: Future<Output = Result<(), E>> + Send,
+        E: fmt::Display + Send,
+    {
+        let (sender, receiver) = watch::channel(interval);
+        // Delta intervals run right away, see [follow]
+        let now = self.clock.now_in_oslo();
+        let next_run = Arc::new(Mutex::new(if interval.has_slots() {
+            interval.next_run_after(now)
+        } else {
+            Some(now)
+        }));
+        let job = {
+            let clock = self.clock.clone();
+            let next_run = next_run.clone();
+            async move {
+                let action = &action;
+                let clock = &*clock;
+                follow(clock, receiver, &next_run, |interval| async move {
+                    let backoff = Backoff::default().until(until_next_run(clock, interval));
+                    if let Err(e) = retry("Scheduled action", backoff, action).await {
+                        tracing::error!(
+                            "Scheduled action failed, giving up until the next run: {}",
+                            e
+                        );
+                    }
+                })
+                .await
+            }
+        };
+        JobHandle {
+            interval: sender,
+            next_run,
+            task: tokio::spawn(job.instrument(tracing::info_span!("schedule", %interval))),
+        }
+    }
+
+    /// Run an action once on a new task after `delay`, unless it is cancelled through the handle
+    /// first. A failed run is retried with [Backoff::default]. Rescheduling the job does nothing
+    /// # Panics
+    /// Panics if called outside of a Tokio runtime
+    pub fn once<Action, Async, E>(&self, delay: Duration, action: Action) -> JobHandle
+    where
+        Action: Fn() -> Async + Send + Sync + 'static,
+        Async: Future<Output = Result<(), E>> + Send,
+        E: fmt::Display + Send,
+    {
+        let interval = Interval::EveryDelta(delay);
+        let at = chrono::Duration::from_std(delay)
+            .ok()
+            .and_then(|delay| self.clock.now_in_oslo().checked_add_signed(delay));
+        let next_run = Arc::new(Mutex::new(at));
+        let job = {
+            let next_run = next_run.clone();
+            async move {
+                tokio::time::sleep(delay).await;
+                *lock(&next_run) = None;
+                if let Err(e) = retry("Scheduled action", Backoff::default(), action).await {
+                    tracing::error!("Scheduled action failed, giving up: {}", e);
+                }
+            }
+        };
+        JobHandle {
+            interval: watch::channel(interval).0,
+            next_run,
+            task: tokio::spawn(job.instrument(tracing::info_span!("once", %interval))),
+        }
+    }
 }
 
-/// Schedule an action to be repeated
-/// This function will never return, as it is stuck in an infinite loop
-/// Only way it exits is through a panic
-/// # Panics
-/// This function will panic when providing [Interval::EveryDeltaStartAt] iwth a start time that is behind current time
-#[instrument(skip(action))]
-pub async fn schedule<Action, Async>(time: Interval, action: Action)
+/// Control over a job started by [schedule] or [Scheduler::once].
+/// Dropping the handle leaves the job running, like a [JoinHandle]
+#[derive(Debug)]
+pub struct JobHandle {
+    interval: watch::Sender<Interval>,
+    next_run: Arc<Mutex<Option<chrono::DateTime<Tz>>>>,
+    task: JoinHandle<()>,
+}
+
+impl JobHandle {
+    /// Stop the job. A run in progress is stopped at its next `.await`
+    pub fn cancel(&self) {
+        self.task.abort();
+        *lock(&self.next_run) = None;
+    }
+
+    /// Follow another interval from now on. A run in progress is not interrupted
+    pub fn reschedule(&self, interval: Interval) {
+        self.interval.send_replace(interval);
+    }
+
+    pub fn interval(&self) -> Interval {
+        *self.interval.borrow()
+    }
+
+    /// When the job runs next, `None` if it never will or is cancelled
+    pub fn next_run(&self) -> Option<chrono::DateTime<Tz>> {
+        if self.task.is_finished() {
+            return None;
+        }
+        *lock(&self.next_run)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.task.is_finished()
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// How long until the next run is due, the time there is to retry a failed run.
+/// [Duration::MAX] if there is no next run
+pub fn until_next_run(clock: &dyn Clock, interval: Interval) -> Duration {
+    let now = clock.now_in_oslo();
+    match interval.next_run_after(now) {
+        Some(next) => (next - now).to_std().unwrap_or_default(),
+        None => Duration::MAX,
+    }
+}
+
+/// Call `tick` at every run of the interval, like [schedule] without the retries.
+/// It runs as part of the calling task and never returns
+pub async fn repeat<Tick, Async>(clock: &dyn Clock, interval: Interval, tick: Tick)
 where
-    Action: Fn() -> Async,
-    Async: std::future::Future<Output = ()>,
+    Tick: Fn() -> Async,
+    Async: Future<Output = ()>,
+{
+    let (_, receiver) = watch::channel(interval);
+    follow(clock, receiver, &Mutex::new(None), |_| tick()).await
+}
+
+/// Call `tick` at every run of the latest interval sent, keeping `next_run` up to date.
+/// [Interval::EveryDelta] runs right away, then every delta after the previous run
+async fn follow<Tick, Async>(
+    clock: &dyn Clock,
+    mut interval: watch::Receiver<Interval>,
+    next_run: &Mutex<Option<chrono::DateTime<Tz>>>,
+    tick: Tick,
+) where
+    Tick: Fn(Interval) -> Async,
+    Async: Future<Output = ()>,
 {
-    let now = chrono::Utc::now().with_timezone(&Oslo);
-    match time {
-        Interval::EveryDelta(delta) => {
-            let mut interval_timer = tokio::time::interval(delta);
-            loop {
-                interval_timer.tick().await;
-                action().await;
+    // Recomputed from the wall clock every time, a fixed timer drifts an hour off at every
+    // DST change. Never before the last run, in case the sleep ends early by the wall clock
+    let mut last = clock.now_in_oslo();
+    let mut last_tick: Option<Instant> = None;
+    loop {
+        let current = *interval.borrow_and_update();
+        let now = clock.now_in_oslo();
+        let (at, next) = match current {
+            Interval::EveryDelta(delta) => {
+                let at = last_tick.map_or_else(Instant::now, |last_tick| last_tick + delta);
+                let wait = at.saturating_duration_since(Instant::now());
+                let wait =
+                    chrono::Duration::from_std(wait).unwrap_or_else(|_| chrono::Duration::zero());
+                (at, now + wait)
             }
-        }
-        Interval::EveryDeltaStartAt(delta, time) => {
-            let offset = time - now;
-            match offset.to_std() {
-                Ok(o) => {
-                    tokio::time::sleep(o).await;
-                    let mut interval_timer = tokio::time::interval(delta);
-                    loop {
-                        interval_timer.tick().await;
-                        action().await;
+            _ => match current.next_run_after(now.max(last)) {
+                Some(next) => (
+                    Instant::now() + (next - now).to_std().unwrap_or_default(),
+                    next,
+                ),
+                None => {
+                    tracing::warn!("Schedule {} never fires again", current);
+                    *lock(next_run) = None;
+                    if interval.changed().await.is_err() {
+                        return std::future::pending().await;
                     }
+                    continue;
                 }
-                Err(_) => {
-                    tracing::error!(
-                        "Target time ({}) is behind current time ({})",
-                        time,
-                        chrono::offset::Local::now()
-                    );
-                    panic!("Target time is behind current time");
-                }
+            },
+        };
+        *lock(next_run) = Some(next);
+
+        tokio::select! {
+            _ = tokio::time::sleep_until(at) => {}
+            Ok(()) = interval.changed() => {
+                // Delta intervals count from the change when there has been no run yet
+                last_tick.get_or_insert_with(Instant::now);
+                continue;
             }
         }
+        last = next;
+        last_tick = Some(at);
+        tick(current).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::clock::TestClock;
+
+    fn oslo(y: i32, m: u32, d: u32, h: u32, min: u32) -> chrono::DateTime<Tz> {
+        Oslo.with_ymd_and_hms(y, m, d, h, min, 0).unwrap()
+    }
+
+    fn utc(y: i32, m: u32, d: u32, h: u32, min: u32) -> chrono::DateTime<chrono::Utc> {
+        chrono::Utc.with_ymd_and_hms(y, m, d, h, min, 0).unwrap()
+    }
+
+    fn day() -> Duration {
+        Duration::from_secs(DAY_AS_SECONDS)
+    }
+
+    #[test]
+    fn daily_keeps_local_time_over_march_change() {
+        // Clocks go forward 02:00 -> 03:00 on 26 March 2023
+        let start = oslo(2023, 3, 24, 7, 0);
+        let saturday = next_fire(day(), start, oslo(2023, 3, 24, 7, 0)).unwrap();
+        assert_eq!(saturday, utc(2023, 3, 25, 6, 0));
+        let sunday = next_fire(day(), start, saturday).unwrap();
+        assert_eq!(sunday, utc(2023, 3, 26, 5, 0));
+        let monday = next_fire(day(), start, sunday).unwrap();
+        assert_eq!(monday, utc(2023, 3, 27, 5, 0));
+        assert_eq!(monday.format("%H:%M").to_string(), "07:00");
+    }
+
+    #[test]
+    fn daily_keeps_local_time_over_october_change() {
+        // Clocks go back 03:00 -> 02:00 on 29 October 2023
+        let start = oslo(2023, 10, 1, 8, 0);
+        let sunday = next_fire(day(), start, oslo(2023, 10, 28, 9, 0)).unwrap();
+        assert_eq!(sunday, utc(2023, 10, 29, 7, 0));
+        let monday = next_fire(day(), start, sunday).unwrap();
+        assert_eq!(monday, utc(2023, 10, 30, 7, 0));
+        assert_eq!(monday.format("%H:%M").to_string(), "08:00");
+    }
+
+    #[test]
+    fn weekly_keeps_local_time_over_both_changes() {
+        let start = oslo(2023, 3, 20, 7, 0);
+        let week = Duration::from_secs(WEEK_AS_SECONDS);
+        assert_eq!(
+            next_fire(week, start, start).unwrap(),
+            oslo(2023, 3, 27, 7, 0)
+        );
+        assert_eq!(
+            next_fire(week, start, oslo(2023, 10, 24, 12, 0)).unwrap(),
+            oslo(2023, 10, 30, 7, 0)
+        );
+    }
+
+    #[test]
+    fn skipped_and_repeated_hours() {
+        // 02:30 does not exist on 26 March and exists twice on 29 October
+        let start = oslo(2023, 3, 24, 2, 30);
+        assert_eq!(
+            next_fire(day(), start, oslo(2023, 3, 25, 12, 0)).unwrap(),
+            oslo(2023, 3, 27, 2, 30)
+        );
+        let start = oslo(2023, 10, 27, 2, 30);
+        let fall = next_fire(day(), start, oslo(2023, 10, 28, 12, 0)).unwrap();
+        assert_eq!(fall, utc(2023, 10, 29, 0, 30));
+        assert_eq!(
+            next_fire(day(), start, fall).unwrap(),
+            oslo(2023, 10, 30, 2, 30)
+        );
+    }
+
+    #[test]
+    fn shorter_intervals_count_elapsed_time() {
+        let start = oslo(2023, 3, 26, 0, 0);
+        let hours = Duration::from_secs(6 * 3600);
+        let next = next_fire(hours, start, start).unwrap();
+        assert_eq!(next, utc(2023, 3, 26, 5, 0));
+        assert_eq!(next.format("%H:%M").to_string(), "07:00");
+    }
+
+    #[test]
+    fn cron_follows_the_wall_clock() {
+        let cron: Cron = "0 7 * * *".parse().unwrap();
+        let interval = Interval::Cron(cron);
+        assert!(interval.next_run().is_some());
+        assert_eq!(
+            cron.next_after(&oslo(2023, 3, 25, 8, 0)).unwrap(),
+            utc(2023, 3, 26, 5, 0)
+        );
+        assert_eq!(
+            cron.next_after(&oslo(2023, 10, 28, 8, 0)).unwrap(),
+            utc(2023, 10, 29, 6, 0)
+        );
+
+        let cron: Cron = "30 2 * * *".parse().unwrap();
+        assert_eq!(
+            cron.next_after(&oslo(2023, 3, 25, 12, 0)).unwrap(),
+            oslo(2023, 3, 27, 2, 30)
+        );
+        assert_eq!(
+            cron.next_after(&utc(2023, 10, 29, 0, 30)).unwrap(),
+            oslo(2023, 10, 30, 2, 30)
+        );
+    }
+
+    fn counting_job(interval: Interval) -> (JobHandle, Arc<std::sync::atomic::AtomicU32>) {
+        let runs = Arc::new(std::sync::atomic::AtomicU32::new(0));
+        let counter = runs.clone();
+        let handle = schedule(interval, move || {
+            counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            async { Ok::<(), String>(()) }
+        });
+        (handle, runs)
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn jobs_can_be_rescheduled_and_cancelled() {
+        let runs_after =
+            |runs: &std::sync::atomic::AtomicU32| runs.load(std::sync::atomic::Ordering::SeqCst);
+        let (handle, runs) = counting_job(Interval::EveryDelta(Duration::from_secs(60)));
+        tokio::time::sleep(Duration::from_secs(150)).await;
+        assert_eq!(runs_after(&runs), 3);
+        let next = handle.next_run().unwrap();
+        assert!(next > chrono::Utc::now().with_timezone(&Oslo));
+
+        handle.reschedule(Interval::EveryDelta(Duration::from_secs(600)));
+        tokio::time::sleep(Duration::from_secs(300)).await;
+        assert_eq!(runs_after(&runs), 3);
+        tokio::time::sleep(Duration::from_secs(600)).await;
+        assert_eq!(runs_after(&runs), 4);
+        assert!(matches!(handle.interval(), Interval::EveryDelta(d) if d.as_secs() == 600));
+
+        handle.cancel();
+        tokio::time::sleep(Duration::from_secs(3600)).await;
+        assert_eq!(runs_after(&runs), 4);
+        assert!(handle.is_finished());
+        assert_eq!(handle.next_run(), None);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn next_run_is_known_as_soon_as_a_job_is_scheduled() {
+        let now = oslo(2023, 3, 25, 12, 0);
+        let scheduler = Scheduler::new(SharedClock::new(TestClock::new(now)));
+        let noop = || async { Ok::<(), String>(()) };
+        let delta = scheduler.schedule(Interval::EveryDelta(Duration::from_secs(600)), noop);
+        assert_eq!(delta.next_run(), Some(now));
+        let daily = scheduler.daily(Time::new_unchecked(7, 0, 0), noop);
+        assert_eq!(daily.next_run(), Some(oslo(2023, 3, 26, 7, 0)));
+        delta.cancel();
+        daily.cancel();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn jobs_can_run_once() {
+        let runs = Arc::new(std::sync::atomic::AtomicU32::new(0));
+        let once = |delay| {
+            let runs = runs.clone();
+            Scheduler::default().once(Duration::from_secs(delay), move || {
+                runs.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                async { Ok::<(), String>(()) }
+            })
+        };
+        let handle = once(60);
+        let cancelled = once(60);
+        assert!(handle.next_run().is_some());
+        cancelled.cancel();
+
+        tokio::time::sleep(Duration::from_secs(3600)).await;
+        assert_eq!(runs.load(std::sync::atomic::Ordering::SeqCst), 1);
+        assert!(handle.is_finished());
+        assert_eq!(handle.next_run(), None);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn jobs_in_the_past_start_at_the_next_slot() {
+        let start = chrono::Utc::now().with_timezone(&Oslo) - chrono::Duration::hours(1);
+        let (handle, runs) = counting_job(Interval::EveryDeltaStartAt(day(), start));
+        tokio::time::sleep(Duration::from_secs(60)).await;
+        assert_eq!(runs.load(std::sync::atomic::Ordering::SeqCst), 0);
+        assert!(handle.next_run().unwrap() > start + chrono::Duration::hours(22));
+        assert!(!handle.is_finished());
+        handle.cancel();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn scheduler_follows_the_clock_over_dst() {
+        let clock = TestClock::new(oslo(2023, 3, 25, 12, 0));
+        let scheduler = Scheduler::new(SharedClock::new(clock.clone()));
+        let runs = Arc::new(Mutex::new(Vec::new()));
+        let handle = scheduler.daily(Time::new_unchecked(7, 0, 0), {
+            let runs = runs.clone();
+            move || {
+                runs.lock().unwrap().push(clock.now_in_oslo());
+                async { Ok::<(), String>(()) }
+            }
+        });
+        assert_eq!(handle.next_run(), Some(oslo(2023, 3, 26, 7, 0)));
+
+        tokio::time::sleep(Duration::from_secs(2 * DAY_AS_SECONDS)).await;
+        let runs = runs.lock().unwrap().clone();
+        // 23 hours apart, the clocks went forward in between
+        assert_eq!(runs, [oslo(2023, 3, 26, 7, 0), oslo(2023, 3, 27, 7, 0)]);
+        assert_eq!(handle.next_run(), Some(oslo(2023, 3, 28, 7, 0)));
+        handle.cancel();
     }
 }
@@ -0,0 +1,240 @@
+use std::fmt;
+
+use chrono::{Datelike, Duration, NaiveDate, TimeZone, Weekday};
+use chrono_tz::Tz;
+use serde::Deserialize;
+
+use crate::utils::cron::Cron;
+
+/// Upper bound on the runs [DaysOff::Skip] looks past, so a schedule that only fires on days
+/// off gives up instead of searching forever
+const MAX_SKIPPED_RUNS: usize = 1000;
+
+/// A Norwegian public holiday
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Holiday {
+    pub date: NaiveDate,
+    pub name: &'static str,
+}
+
+/// Easter Sunday in the Gregorian calendar, by the anonymous Gregorian algorithm
+pub fn easter_sunday(year: i32) -> Option<NaiveDate> {
+    let a = year.rem_euclid(19);
+    let b = year.div_euclid(100);
+    let c = year.rem_euclid(100);
+    let d = b / 4;
+    let e = b % 4;
+    let f = (b + 8) / 25;
+    let g = (b - f + 1) / 3;
+    let h = (19 * a + b - d - g + 15) % 30;
+    let i = c / 4;
+    let k = c % 4;
+    let l = (32 + 2 * e + 2 * i - h - k) % 7;
+    let m = (a + 11 * h + 22 * l) / 451;
+    let month = (h + l - 7 * m + 114) / 31;
+    let day = (h + l - 7 * m + 114) % 31 + 1;
+    NaiveDate::from_ymd_opt(year, month as u32, day as u32)
+}
+
+/// Every public holiday of the year in date order, empty if the year can not be represented
+pub fn holidays(year: i32) -> Vec<Holiday> {
+    let easter = match easter_sunday(year) {
+        Some(easter) => easter,
+        None => return Vec::new(),
+    };
+    let fixed = |month, day, name| {
+        NaiveDate::from_ymd_opt(year, month, day).map(|date| Holiday { date, name })
+    };
+    let moving = |days, name| {
+        easter
+            .checked_add_signed(Duration::days(days))
+            .map(|date| Holiday { date, name })
+    };
+
+    let mut holidays: Vec<Holiday> = [
+        fixed(1, 1, "Nyttårsdag"),
+        moving(-3, "Skjærtorsdag"),
+        moving(-2, "Langfredag"),
+        moving(0, "Første påskedag"),
+        moving(1, "Andre påskedag"),
+        fixed(5, 1, "Arbeidernes dag"),
+        fixed(5, 17, "Grunnlovsdag"),
+        moving(39, "Kristi himmelfartsdag"),
+        moving(49, "Første pinsedag"),
+        moving(50, "Andre pinsedag"),
+        fixed(12, 25, "Første juledag"),
+        fixed(12, 26, "Andre juledag"),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    // Ascension Day can fall on 1 or 17 May
+    holidays.sort_by_key(|h| h.date);
+    holidays
+}
+
+/// The name of the holiday on the date, if it is one
+pub fn holiday(date: NaiveDate) -> Option<&'static str> {
+    holidays(date.year())
+        .into_iter()
+        .find(|h| h.date == date)
+        .map(|h| h.name)
+}
+
+pub fn is_weekend(date: NaiveDate) -> bool {
+    matches!(date.weekday(), Weekday::Sat | Weekday::Sun)
+}
+
+/// Neither a weekend nor a public holiday
+pub fn is_working_day(date: NaiveDate) -> bool {
+    !is_weekend(date) && holiday(date).is_none()
+}
+
+/// The first working day after the date
+pub fn next_working_day(date: NaiveDate) -> Option<NaiveDate> {
+    let mut date = date.succ_opt()?;
+    while !is_working_day(date) {
+        date = date.succ_opt()?;
+    }
+    Some(date)
+}
+
+/// What a scheduled task does about runs due on weekends and public holidays
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DaysOff {
+    /// Run as usual
+    #[default]
+    Run,
+    /// Leave the run out
+    Skip,
+    /// Run at the same time on the next working day instead, once even if several runs moved
+    Shift,
+}
+
+impl DaysOff {
+    /// The first run of the cron schedule after `after`, with runs on days off skipped or moved
+    pub fn next_run(
+        &self,
+        cron: &Cron,
+        after: &chrono::DateTime<Tz>,
+    ) -> Option<chrono::DateTime<Tz>> {
+        let first = cron.next_after(after)?;
+        if *self == DaysOff::Run || is_working_day(first.date_naive()) {
+            return Some(first);
+        }
+
+        let mut regular = Some(first);
+        for _ in 0..MAX_SKIPPED_RUNS {
+            match regular {
+                Some(run) if !is_working_day(run.date_naive()) => regular = cron.next_after(&run),
+                _ => break,
+            }
+        }
+        let regular = regular.filter(|run| is_working_day(run.date_naive()));
+        if *self == DaysOff::Skip {
+            return regular;
+        }
+
+        let shifted = next_working_day(first.date_naive()).and_then(|date| {
+            first
+                .timezone()
+                .from_local_datetime(&date.and_time(first.time()))
+                .earliest()
+        });
+        match (shifted, regular) {
+            (Some(shifted), Some(regular)) => Some(shifted.min(regular)),
+            (shifted, regular) => shifted.or(regular),
+        }
+    }
+}
+
+impl fmt::Display for DaysOff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DaysOff::Run => write!(f, "also on days off"),
+            DaysOff::Skip => write!(f, "skipping days off"),
+            DaysOff::Shift => write!(f, "moving days off to the next working day"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono_tz::Europe::Oslo;
+
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    fn oslo(m: u32, d: u32, h: u32) -> chrono::DateTime<Tz> {
+        Oslo.with_ymd_and_hms(2024, m, d, h, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn easter_dates() {
+        assert_eq!(easter_sunday(2023), Some(date(2023, 4, 9)));
+        assert_eq!(easter_sunday(2024), Some(date(2024, 3, 31)));
+        assert_eq!(easter_sunday(2025), Some(date(2025, 4, 20)));
+        // The earliest and latest possible
+        assert_eq!(easter_sunday(2285), Some(date(2285, 3, 22)));
+        assert_eq!(easter_sunday(2038), Some(date(2038, 4, 25)));
+    }
+
+    #[test]
+    fn holidays_of_2024() {
+        let year = holidays(2024);
+        assert_eq!(year.len(), 12);
+        assert_eq!(holiday(date(2024, 3, 28)), Some("Skjærtorsdag"));
+        assert_eq!(holiday(date(2024, 5, 9)), Some("Kristi himmelfartsdag"));
+        assert_eq!(holiday(date(2024, 5, 20)), Some("Andre pinsedag"));
+        assert_eq!(holiday(date(2024, 12, 24)), None);
+        assert!(year.windows(2).all(|w| w[0].date < w[1].date));
+
+        // Ascension Day and Labour Day coincide in 2008
+        assert_eq!(holidays(2008)[5].date, date(2008, 5, 1));
+        assert_eq!(holidays(2008)[6].date, date(2008, 5, 1));
+    }
+
+    #[test]
+    fn working_days() {
+        assert!(is_working_day(date(2024, 3, 27)));
+        assert!(!is_working_day(date(2024, 3, 30)));
+        assert!(!is_working_day(date(2024, 4, 1)));
+        assert_eq!(next_working_day(date(2024, 3, 27)), Some(date(2024, 4, 2)));
+    }
+
+    #[test]
+    fn runs_on_days_off_are_skipped_or_shifted() {
+        let daily: Cron = "0 7 * * *".parse().unwrap();
+        let wednesday = oslo(3, 27, 8);
+        assert_eq!(
+            DaysOff::Run.next_run(&daily, &wednesday),
+            Some(oslo(3, 28, 7))
+        );
+        // Easter runs from Thursday to Monday
+        assert_eq!(
+            DaysOff::Skip.next_run(&daily, &wednesday),
+            Some(oslo(4, 2, 7))
+        );
+        assert_eq!(
+            DaysOff::Shift.next_run(&daily, &wednesday),
+            Some(oslo(4, 2, 7))
+        );
+        assert_eq!(
+            DaysOff::Shift.next_run(&daily, &oslo(4, 2, 7)),
+            Some(oslo(4, 3, 7))
+        );
+
+        // Saturdays only, moved to Monday unless skipped
+        let saturdays: Cron = "0 10 * * 6".parse().unwrap();
+        let friday = oslo(6, 7, 12);
+        assert_eq!(
+            DaysOff::Shift.next_run(&saturdays, &friday),
+            Some(oslo(6, 10, 10))
+        );
+        assert_eq!(DaysOff::Skip.next_run(&saturdays, &friday), None);
+    }
+}
@@ -0,0 +1,744 @@
+use chrono::{
+    DateTime, Datelike, Days, Duration, Months, NaiveDate, NaiveDateTime, NaiveTime, TimeZone,
+    Weekday,
+};
+use chrono_tz::Tz;
+
+use super::{recurrence::DEFAULT_TIME, Time};
+use crate::storage::settings::Language;
+
+/// A time written by a user, and how it was read
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct When {
+    pub at: DateTime<Tz>,
+    /// The input as it was understood, e.g. `tomorrow at 08:00` or `in 3 hours`, in the language
+    /// of the user
+    pub reading: String,
+}
+
+/// Reads a time written in Norwegian or English, in the timezone of `now`. Times can be relative,
+/// like `om 3 timer`, `in 1 hour and 30 minutes`, `2h30m` or `om 2 dager kl 10`, or absolute,
+/// like `i morgen kl 8`, `på fredag 14:00`, `next monday`, `24. desember 18.00` or `2024-12-24`.
+///
+/// A day without a time of day is at 09:00, and a time of day without a day is the next time
+/// the clock shows it. A weekday is the next one, today included if the time is still ahead,
+/// and a date without a year is the next one. `d.m` is read as a date when it can be one,
+/// unless it follows `kl` or `at`. The result can be in the past, e.g. `today at 08:00`
+pub fn parse(input: &str, now: DateTime<Tz>, language: Language) -> Result<When, String> {
+    let words = words(input);
+    let phrase = read(&words)?;
+    if phrase.relative {
+        relative(&phrase, now, language)
+    } else {
+        absolute(&phrase, now, language)
+    }
+}
+
+/// What the words of a time say, before it is known when that is
+#[derive(Debug, Default)]
+struct Phrase {
+    /// Whether any amounts of time from now were given
+    relative: bool,
+    months: u32,
+    /// Days on the wall clock, so `in 1 day` is the same time tomorrow across DST changes
+    days: u64,
+    /// Hours, minutes and seconds as elapsed time
+    seconds: i64,
+    /// The amounts as they were written, to read them back
+    amounts: Vec<(u32, Unit)>,
+    day: Option<Day>,
+    time: Option<Time>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Day {
+    /// Days from today
+    FromToday(u64),
+    /// The next such weekday, never today when `next` was written
+    Weekday { weekday: Weekday, next: bool },
+    Date {
+        day: u32,
+        month: u32,
+        year: Option<i32>,
+    },
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Unit {
+    Second,
+    Minute,
+    Hour,
+    Day,
+    Week,
+    Month,
+}
+
+impl Phrase {
+    fn set_day(&mut self, day: Day) -> Result<(), String> {
+        if self.day.is_some() {
+            return Err("Give only one day".to_string());
+        }
+        self.day = Some(day);
+        Ok(())
+    }
+
+    fn set_time(&mut self, time: Time) -> Result<(), String> {
+        if self.time.is_some() {
+            return Err("Give only one time of day".to_string());
+        }
+        self.time = Some(time);
+        Ok(())
+    }
+
+    fn add(&mut self, amount: u32, unit: Unit) {
+        self.relative = true;
+        match unit {
+            Unit::Second => self.seconds += amount as i64,
+            Unit::Minute => self.seconds += amount as i64 * 60,
+            Unit::Hour => self.seconds += amount as i64 * 3600,
+            Unit::Day => self.days += amount as u64,
+            Unit::Week => self.days += amount as u64 * 7,
+            Unit::Month => self.months = self.months.saturating_add(amount),
+        }
+        self.amounts.push((amount, unit));
+    }
+}
+
+/// The input in lowercase, split into words. Trailing dots are left out, as in `kl.` and `24.`,
+/// and units and `am`/`pm` are split from their numbers, as in `2h30m` and `8pm`
+fn words(input: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    for word in input.to_lowercase().replace(',', " ").split_whitespace() {
+        let word = word.trim_end_matches('.');
+        if !word.starts_with(|c: char| c.is_ascii_digit()) {
+            if !word.is_empty() {
+                words.push(word.to_string());
+            }
+            continue;
+        }
+        let mut part = String::new();
+        for c in word.chars() {
+            if !part.is_empty() && part.ends_with(char::is_alphabetic) != c.is_alphabetic() {
+                words.push(std::mem::take(&mut part));
+            }
+            part.push(c);
+        }
+        words.push(part);
+    }
+    words
+}
+
+fn read(words: &[String]) -> Result<Phrase, String> {
+    let mut phrase = Phrase::default();
+    // After `kl` or `at`, a number is a time of day
+    let mut clock = false;
+    let mut next = false;
+    let mut i = 0;
+    while i < words.len() {
+        let word = words[i].as_str();
+        let following = words.get(i + 1).map(String::as_str);
+        i += 1;
+
+        if next && weekday(word).is_none() {
+            return Err("Expected a weekday after next".to_string());
+        }
+        match word {
+            "kl" | "klokka" | "klokken" | "at" => {
+                clock = true;
+                continue;
+            }
+            "next" | "neste" => {
+                next = true;
+                continue;
+            }
+            "in" | "om" | "on" | "på" | "the" | "den" | "and" | "og" | "st" | "nd" | "rd"
+            | "th" => continue,
+            "today" | "idag" => phrase.set_day(Day::FromToday(0))?,
+            "tomorrow" | "imorgen" => phrase.set_day(Day::FromToday(1))?,
+            "overmorgen" | "iovermorgen" => phrase.set_day(Day::FromToday(2))?,
+            "i" => {
+                let days = match following {
+                    Some("dag") => 0,
+                    Some("morgen") => 1,
+                    Some("overmorgen") => 2,
+                    _ => return Err("Did not understand i".to_string()),
+                };
+                phrase.set_day(Day::FromToday(days))?;
+                i += 1;
+            }
+            "day"
+                if following == Some("after")
+                    && words.get(i + 1).map(String::as_str) == Some("tomorrow") =>
+            {
+                phrase.set_day(Day::FromToday(2))?;
+                i += 2;
+            }
+            "noon" | "midday" => phrase.set_time(Time::new_unchecked(12, 0, 0))?,
+            "midnight" | "midnatt" => phrase.set_time(Time::new_unchecked(0, 0, 0))?,
+            "a" | "an" | "en" | "ei" if following == Some("halvtime") => continue,
+            "halvtime" => phrase.add(30, Unit::Minute),
+            "half"
+                if following == Some("an")
+                    && words.get(i + 1).map(String::as_str) == Some("hour") =>
+            {
+                phrase.add(30, Unit::Minute);
+                i += 2;
+            }
+            word => {
+                if let Some(weekday) = weekday(word) {
+                    phrase.set_day(Day::Weekday { weekday, next })?;
+                } else if let Some(month) = month(word) {
+                    // December 24
+                    let day = following
+                        .and_then(day_of_month)
+                        .ok_or_else(|| format!("Expected a day after {}", word))?;
+                    i += 1;
+                    let year = year(words.get(i));
+                    if year.is_some() {
+                        i += 1;
+                    }
+                    phrase.set_day(Day::Date { day, month, year })?;
+                } else if let (Some(amount), Some(unit)) = (number(word), following.and_then(unit))
+                {
+                    phrase.add(amount, unit);
+                    i += 1;
+                } else if let (Some(day), Some(month)) =
+                    (day_of_month(word), following.and_then(month))
+                {
+                    // 24. desember
+                    i += 1;
+                    let year = year(words.get(i));
+                    if year.is_some() {
+                        i += 1;
+                    }
+                    phrase.set_day(Day::Date { day, month, year })?;
+                } else if let Some(date) = date(word).filter(|_| !clock) {
+                    phrase.set_day(date)?;
+                } else if let Some(time) =
+                    time(word, clock || matches!(following, Some("am" | "pm")))
+                {
+                    let time = match following {
+                        Some(half @ ("am" | "pm")) => {
+                            i += 1;
+                            twelve_hour(time, half == "pm")
+                                .ok_or_else(|| format!("{} {} is not a time", word, half))?
+                        }
+                        _ => time,
+                    };
+                    phrase.set_time(time)?;
+                } else if clock && word.chars().all(|c| c.is_ascii_digit()) {
+                    return Err(format!("{} is not a time of day", word));
+                } else {
+                    return Err(format!("Did not understand {}", word));
+                }
+            }
+        }
+        clock = false;
+        next = false;
+    }
+    if clock || next {
+        return Err("The time ends too early".to_string());
+    }
+    if !phrase.relative && phrase.day.is_none() && phrase.time.is_none() {
+        return Err("Say when, e.g. i morgen kl 8, on friday 14:00 or in 3 hours".to_string());
+    }
+    Ok(phrase)
+}
+
+fn relative(phrase: &Phrase, now: DateTime<Tz>, language: Language) -> Result<When, String> {
+    if phrase.day.is_some() {
+        return Err("Give either a day or how long from now, not both".to_string());
+    }
+    if phrase.time.is_some() && phrase.seconds != 0 {
+        return Err("Only days, weeks and months from now can be at a time of day".to_string());
+    }
+    let too_far = || "That is too far into the future".to_string();
+    let start = if phrase.months == 0 && phrase.days == 0 && phrase.time.is_none() {
+        now
+    } else {
+        let date = now
+            .naive_local()
+            .checked_add_months(Months::new(phrase.months))
+            .and_then(|date| date.checked_add_days(Days::new(phrase.days)))
+            .ok_or_else(too_far)?;
+        let date = match phrase.time {
+            Some(time) => date.date().and_time(naive(time)),
+            None => date,
+        };
+        local(now.timezone(), date)?
+    };
+    let at = start
+        .checked_add_signed(Duration::seconds(phrase.seconds))
+        .ok_or_else(too_far)?;
+
+    let amounts: Vec<String> = phrase
+        .amounts
+        .iter()
+        .map(|(amount, unit)| format!("{} {}", amount, unit_name(*unit, *amount, language)))
+        .collect();
+    let mut reading = match language {
+        Language::English => format!("in {}", amounts.join(" and ")),
+        Language::Norwegian => format!("om {}", amounts.join(" og ")),
+    };
+    if let Some(time) = phrase.time {
+        reading.push_str(&format!(" {}", at_time(time, language)));
+    }
+    Ok(When { at, reading })
+}
+
+fn absolute(phrase: &Phrase, now: DateTime<Tz>, language: Language) -> Result<When, String> {
+    let tz = now.timezone();
+    let today = now.date_naive();
+    let time = phrase.time.unwrap_or(DEFAULT_TIME);
+    let at = |date: NaiveDate| local(tz, date.and_time(naive(time)));
+    let after = |date: NaiveDate, days: u64| {
+        date.checked_add_days(Days::new(days))
+            .ok_or_else(|| "That is too far into the future".to_string())
+    };
+
+    let (date, day) = match phrase.day {
+        None if at(today)? > now => (today, days_from_today(0, language)),
+        None => (after(today, 1)?, days_from_today(1, language)),
+        Some(Day::FromToday(days)) => (after(today, days)?, days_from_today(days, language)),
+        Some(Day::Weekday { weekday, next }) => {
+            let mut date = after(today, next as u64)?;
+            while date.weekday() != weekday {
+                date = after(date, 1)?;
+            }
+            if date == today && at(today)? <= now {
+                date = after(date, 7)?;
+            }
+            let day = match (language, next) {
+                (Language::English, true) => "next",
+                (Language::English, false) => "on",
+                (Language::Norwegian, true) => "neste",
+                (Language::Norwegian, false) => "på",
+            };
+            (date, format!("{} {}", day, weekday_name(weekday, language)))
+        }
+        Some(Day::Date { day, month, year }) => {
+            let date = match year {
+                Some(year) => NaiveDate::from_ymd_opt(year, month, day),
+                // The next one, 29/2 may be a few years away
+                None => (today.year()..today.year() + 8)
+                    .filter_map(|year| NaiveDate::from_ymd_opt(year, month, day))
+                    .find(|date| matches!(at(*date), Ok(at) if at > now)),
+            }
+            .ok_or_else(|| format!("There is no {}/{}", day, month))?;
+            let on = match language {
+                Language::English => "on",
+                Language::Norwegian => "den",
+            };
+            let day = if date.year() == today.year() {
+                date.format("%d/%m")
+            } else {
+                date.format("%d/%m/%Y")
+            };
+            (date, format!("{} {}", on, day))
+        }
+    };
+    Ok(When {
+        at: at(date)?,
+        reading: format!("{} {}", day, at_time(time, language)),
+    })
+}
+
+/// The wall clock time in `tz`. A time skipped when the clocks go forward is read an hour later
+fn local(tz: Tz, time: NaiveDateTime) -> Result<DateTime<Tz>, String> {
+    tz.from_local_datetime(&time)
+        .earliest()
+        .or_else(|| {
+            tz.from_local_datetime(&(time + Duration::hours(1)))
+                .earliest()
+        })
+        .ok_or_else(|| format!("{} does not exist", time))
+}
+
+fn naive(time: Time) -> NaiveTime {
+    NaiveTime::from_hms_opt(time.hour as u32, time.minute as u32, time.second as u32)
+        .unwrap_or(NaiveTime::MIN)
+}
+
+fn at_time(time: Time, language: Language) -> String {
+    let at = match language {
+        Language::English => "at",
+        Language::Norwegian => "kl",
+    };
+    format!("{} {:02}:{:02}", at, time.hour, time.minute)
+}
+
+fn days_from_today(days: u64, language: Language) -> String {
+    let day = match (language, days) {
+        (Language::English, 0) => "today",
+        (Language::English, 1) => "tomorrow",
+        (Language::English, _) => "the day after tomorrow",
+        (Language::Norwegian, 0) => "i dag",
+        (Language::Norwegian, 1) => "i morgen",
+        (Language::Norwegian, _) => "i overmorgen",
+    };
+    day.to_string()
+}
+
+fn unit_name(unit: Unit, amount: u32, language: Language) -> &'static str {
+    let (one, many) = match (language, unit) {
+        (Language::English, Unit::Second) => ("second", "seconds"),
+        (Language::English, Unit::Minute) => ("minute", "minutes"),
+        (Language::English, Unit::Hour) => ("hour", "hours"),
+        (Language::English, Unit::Day) => ("day", "days"),
+        (Language::English, Unit::Week) => ("week", "weeks"),
+        (Language::English, Unit::Month) => ("month", "months"),
+        (Language::Norwegian, Unit::Second) => ("sekund", "sekunder"),
+        (Language::Norwegian, Unit::Minute) => ("minutt", "minutter"),
+        (Language::Norwegian, Unit::Hour) => ("time", "timer"),
+        (Language::Norwegian, Unit::Day) => ("dag", "dager"),
+        (Language::Norwegian, Unit::Week) => ("uke", "uker"),
+        (Language::Norwegian, Unit::Month) => ("måned", "måneder"),
+    };
+    if amount == 1 {
+        one
+    } else {
+        many
+    }
+}
+
+/// A count of something, as in `3 hours` or `en time`
+fn number(word: &str) -> Option<u32> {
+    match word {
+        "a" | "an" | "one" | "en" | "ei" | "et" | "ett" => Some(1),
+        "two" | "to" => Some(2),
+        "three" | "tre" => Some(3),
+        word => word.parse().ok(),
+    }
+}
+
+fn unit(word: &str) -> Option<Unit> {
+    match word {
+        "s" | "sec" | "secs" | "second" | "seconds" | "sek" | "sekund" | "sekunder" => {
+            Some(Unit::Second)
+        }
+        "m" | "min" | "mins" | "minute" | "minutes" | "minutt" | "minutter" => Some(Unit::Minute),
+        "h" | "hr" | "hrs" | "hour" | "hours" | "t" | "time" | "timer" => Some(Unit::Hour),
+        "d" | "day" | "days" | "dag" | "dager" | "døgn" => Some(Unit::Day),
+        "w" | "week" | "weeks" | "uke" | "uker" => Some(Unit::Week),
+        "month" | "months" | "mnd" | "måned" | "måneder" => Some(Unit::Month),
+        _ => None,
+    }
+}
+
+fn weekday(word: &str) -> Option<Weekday> {
+    match word {
+        "monday" | "mon" | "mandag" | "man" => Some(Weekday::Mon),
+        "tuesday" | "tue" | "tues" | "tirsdag" | "tir" => Some(Weekday::Tue),
+        "wednesday" | "wed" | "onsdag" | "ons" => Some(Weekday::Wed),
+        "thursday" | "thu" | "thurs" | "torsdag" | "tor" => Some(Weekday::Thu),
+        "friday" | "fri" | "fredag" | "fre" => Some(Weekday::Fri),
+        "saturday" | "sat" | "lørdag" | "lør" => Some(Weekday::Sat),
+        "sunday" | "sun" | "søndag" | "søn" => Some(Weekday::Sun),
+        _ => None,
+    }
+}
+
+fn weekday_name(weekday: Weekday, language: Language) -> &'static str {
+    let (english, norwegian) = match weekday {
+        Weekday::Mon => ("Monday", "mandag"),
+        Weekday::Tue => ("Tuesday", "tirsdag"),
+        Weekday::Wed => ("Wednesday", "onsdag"),
+        Weekday::Thu => ("Thursday", "torsdag"),
+        Weekday::Fri => ("Friday", "fredag"),
+        Weekday::Sat => ("Saturday", "lørdag"),
+        Weekday::Sun => ("Sunday", "søndag"),
+    };
+    match language {
+        Language::English => english,
+        Language::Norwegian => norwegian,
+    }
+}
+
+/// The month number of a month name or abbreviation in English or Norwegian
+fn month(word: &str) -> Option<u32> {
+    let month = match word {
+        "january" | "januar" | "jan" => 1,
+        "february" | "februar" | "feb" => 2,
+        "march" | "mars" | "mar" => 3,
+        "april" | "apr" => 4,
+        "may" | "mai" => 5,
+        "june" | "juni" | "jun" => 6,
+        "july" | "juli" | "jul" => 7,
+        "august" | "aug" => 8,
+        "september" | "sep" | "sept" => 9,
+        "october" | "oktober" | "oct" | "okt" => 10,
+        "november" | "nov" => 11,
+        "december" | "desember" | "dec" | "des" => 12,
+        _ => return None,
+    };
+    Some(month)
+}
+
+fn day_of_month(word: &str) -> Option<u32> {
+    word.parse().ok().filter(|day| (1..=31).contains(day))
+}
+
+fn year(word: Option<&String>) -> Option<i32> {
+    word.filter(|word| word.len() == 4)
+        .and_then(|word| word.parse().ok())
+}
+
+/// A date written as `24/12`, `24.12`, `24/12/2024`, `24.12.2024` or `2024-12-24`
+fn date(word: &str) -> Option<Day> {
+    let numbers = |separator| {
+        word.split(separator)
+            .map(|part: &str| part.parse::<u32>().ok())
+            .collect::<Option<Vec<u32>>>()
+    };
+    let (day, month, year) = if word.contains('-') {
+        match numbers('-')?[..] {
+            [year, month, day] => (day, month, Some(year as i32)),
+            _ => return None,
+        }
+    } else {
+        let separator = if word.contains('/') { '/' } else { '.' };
+        match numbers(separator)?[..] {
+            [day, month] => (day, month, None),
+            [day, month, year] if year >= 1000 => (day, month, Some(year as i32)),
+            _ => return None,
+        }
+    };
+    if !(1..=31).contains(&day) || !(1..=12).contains(&month) {
+        return None;
+    }
+    Some(Day::Date { day, month, year })
+}
+
+/// A time of day written as `14:00` or `14.00`, or as an hour when `hour` may be alone,
+/// as after `kl` or before `pm`
+fn time(word: &str, hour_alone: bool) -> Option<Time> {
+    let mut parts = word.split([':', '.']);
+    let hour: u8 = parts.next()?.parse().ok()?;
+    let minute: u8 = match parts.next() {
+        Some(minute) if minute.len() == 2 => minute.parse().ok()?,
+        Some(_) => return None,
+        None if hour_alone => 0,
+        None => return None,
+    };
+    if parts.next().is_some() {
+        return None;
+    }
+    Time::new(hour, minute, 0)
+}
+
+/// A time written with `am` or `pm`, from 12 at noon or midnight
+fn twelve_hour(time: Time, pm: bool) -> Option<Time> {
+    if !(1..=12).contains(&time.hour) {
+        return None;
+    }
+    let hour = time.hour % 12 + if pm { 12 } else { 0 };
+    Time::new(hour, time.minute, 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono_tz::Europe::Oslo;
+
+    use super::*;
+
+    fn oslo(y: i32, m: u32, d: u32, h: u32, min: u32) -> DateTime<Tz> {
+        Oslo.with_ymd_and_hms(y, m, d, h, min, 0).unwrap()
+    }
+
+    #[test]
+    fn norwegian_and_english() {
+        // A Wednesday
+        let now = oslo(2024, 5, 22, 12, 0);
+        for (input, at, reading) in [
+            (
+                "i morgen kl 8",
+                oslo(2024, 5, 23, 8, 0),
+                "tomorrow at 08:00",
+            ),
+            (
+                "imorgen kl. 08.15",
+                oslo(2024, 5, 23, 8, 15),
+                "tomorrow at 08:15",
+            ),
+            (
+                "tomorrow at 8pm",
+                oslo(2024, 5, 23, 20, 0),
+                "tomorrow at 20:00",
+            ),
+            ("tomorrow", oslo(2024, 5, 23, 9, 0), "tomorrow at 09:00"),
+            (
+                "i overmorgen",
+                oslo(2024, 5, 24, 9, 0),
+                "the day after tomorrow at 09:00",
+            ),
+            (
+                "day after tomorrow 7:30am",
+                oslo(2024, 5, 24, 7, 30),
+                "the day after tomorrow at 07:30",
+            ),
+            ("i dag kl 18", oslo(2024, 5, 22, 18, 0), "today at 18:00"),
+            (
+                "på fredag 14:00",
+                oslo(2024, 5, 24, 14, 0),
+                "on Friday at 14:00",
+            ),
+            ("Friday", oslo(2024, 5, 24, 9, 0), "on Friday at 09:00"),
+            (
+                "on mon at 10",
+                oslo(2024, 5, 27, 10, 0),
+                "on Monday at 10:00",
+            ),
+            (
+                "onsdag 13:00",
+                oslo(2024, 5, 22, 13, 0),
+                "on Wednesday at 13:00",
+            ),
+            (
+                "onsdag 11:00",
+                oslo(2024, 5, 29, 11, 0),
+                "on Wednesday at 11:00",
+            ),
+            (
+                "neste onsdag 13:00",
+                oslo(2024, 5, 29, 13, 0),
+                "next Wednesday at 13:00",
+            ),
+            (
+                "next friday",
+                oslo(2024, 5, 24, 9, 0),
+                "next Friday at 09:00",
+            ),
+            ("om 3 timer", oslo(2024, 5, 22, 15, 0), "in 3 hours"),
+            ("in an hour", oslo(2024, 5, 22, 13, 0), "in 1 hour"),
+            (
+                "in 1 hour and 30 minutes",
+                oslo(2024, 5, 22, 13, 30),
+                "in 1 hour and 30 minutes",
+            ),
+            (
+                "om 1 time og 15 min",
+                oslo(2024, 5, 22, 13, 15),
+                "in 1 hour and 15 minutes",
+            ),
+            (
+                "2h30m",
+                oslo(2024, 5, 22, 14, 30),
+                "in 2 hours and 30 minutes",
+            ),
+            ("10 min", oslo(2024, 5, 22, 12, 10), "in 10 minutes"),
+            ("om en halvtime", oslo(2024, 5, 22, 12, 30), "in 30 minutes"),
+            (
+                "in half an hour",
+                oslo(2024, 5, 22, 12, 30),
+                "in 30 minutes",
+            ),
+            (
+                "om 2 dager kl 10",
+                oslo(2024, 5, 24, 10, 0),
+                "in 2 days at 10:00",
+            ),
+            ("in a week", oslo(2024, 5, 29, 12, 0), "in 1 week"),
+            ("om 1 måned", oslo(2024, 6, 22, 12, 0), "in 1 month"),
+            ("17:30", oslo(2024, 5, 22, 17, 30), "today at 17:30"),
+            ("kl 8", oslo(2024, 5, 23, 8, 0), "tomorrow at 08:00"),
+            ("8.30", oslo(2024, 5, 23, 8, 30), "tomorrow at 08:30"),
+            ("kl 14.15", oslo(2024, 5, 22, 14, 15), "today at 14:15"),
+            ("at 12:30 pm", oslo(2024, 5, 22, 12, 30), "today at 12:30"),
+            ("midnight", oslo(2024, 5, 23, 0, 0), "tomorrow at 00:00"),
+            ("24.12", oslo(2024, 12, 24, 9, 0), "on 24/12 at 09:00"),
+            (
+                "24. desember kl 18",
+                oslo(2024, 12, 24, 18, 0),
+                "on 24/12 at 18:00",
+            ),
+            (
+                "december 24th 6pm",
+                oslo(2024, 12, 24, 18, 0),
+                "on 24/12 at 18:00",
+            ),
+            ("1/3", oslo(2025, 3, 1, 9, 0), "on 01/03/2025 at 09:00"),
+            (
+                "17. mai 2025",
+                oslo(2025, 5, 17, 9, 0),
+                "on 17/05/2025 at 09:00",
+            ),
+            (
+                "2025-01-15 10:00",
+                oslo(2025, 1, 15, 10, 0),
+                "on 15/01/2025 at 10:00",
+            ),
+            ("29/2", oslo(2028, 2, 29, 9, 0), "on 29/02/2028 at 09:00"),
+        ] {
+            assert_eq!(
+                parse(input, now, Language::English),
+                Ok(When {
+                    at,
+                    reading: reading.to_string()
+                }),
+                "{input}"
+            );
+        }
+    }
+
+    #[test]
+    fn read_back_in_norwegian() {
+        let now = oslo(2024, 5, 22, 12, 0);
+        for (input, reading) in [
+            ("tomorrow at 8", "i morgen kl 08:00"),
+            ("day after tomorrow", "i overmorgen kl 09:00"),
+            ("17:30", "i dag kl 17:30"),
+            ("on friday 14:00", "på fredag kl 14:00"),
+            ("next wednesday", "neste onsdag kl 09:00"),
+            ("december 24", "den 24/12 kl 09:00"),
+            ("in 1 hour and 30 minutes", "om 1 time og 30 minutter"),
+            ("in 2 weeks at 10", "om 2 uker kl 10:00"),
+        ] {
+            assert_eq!(
+                parse(input, now, Language::Norwegian).map(|when| when.reading),
+                Ok(reading.to_string()),
+                "{input}"
+            );
+        }
+    }
+
+    #[test]
+    fn wall_clock_across_dst() {
+        // The clocks go forward at 02:00 the next night
+        let now = oslo(2024, 3, 30, 12, 0);
+        assert_eq!(
+            parse("i morgen kl 2:30", now, Language::English).map(|when| when.at),
+            Ok(oslo(2024, 3, 31, 3, 30))
+        );
+        assert_eq!(
+            parse("om 1 dag", now, Language::English).map(|when| when.at),
+            Ok(oslo(2024, 3, 31, 12, 0))
+        );
+        assert_eq!(
+            parse("in 24 hours", now, Language::English).map(|when| when.at),
+            Ok(oslo(2024, 3, 31, 13, 0))
+        );
+    }
+
+    #[test]
+    fn not_understood() {
+        let now = oslo(2024, 5, 22, 12, 0);
+        for wrong in [
+            "",
+            "whenever",
+            "kl 25",
+            "kl",
+            "25:00",
+            "31/2/2025",
+            "32. desember",
+            "i morgen på fredag",
+            "om 3 timer i morgen",
+            "om 2 timer kl 10",
+            "next week",
+            "8 pm 9 pm",
+            "13pm",
+            "i går",
+        ] {
+            assert!(parse(wrong, now, Language::English).is_err(), "{wrong}");
+        }
+    }
+}
@@ -0,0 +1,311 @@
+use std::{fmt, iter::Peekable, str::FromStr, time::Duration};
+
+use chrono::{DateTime, Weekday};
+use chrono_tz::Tz;
+
+use super::{holidays::DaysOff, Interval, Time, DAY_AS_SECONDS, WEEK_AS_SECONDS};
+use crate::utils::cron::Cron;
+
+/// Time of day of a recurrence or a day that does not say
+pub(super) const DEFAULT_TIME: Time = Time {
+    hour: 9,
+    minute: 0,
+    second: 0,
+};
+
+/// How often something comes back, written like `every weekday at 08:15`,
+/// `every 2 weeks on thursday` or `every monday and friday at 17:00`.
+/// The time of day defaults to 09:00
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Recurrence {
+    /// Days or weeks between occurrences
+    every: u32,
+    unit: Unit,
+    time: Time,
+    /// The days and time of day, every week or day
+    cron: Cron,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Unit {
+    Day,
+    /// Monday to Friday
+    Weekday,
+    /// Monday to Friday, except public holidays
+    WorkingDay,
+    /// On the weekdays in the mask, bit `n` is `n` days from Monday
+    Week(u8),
+}
+
+impl Recurrence {
+    /// The first occurrence after `after`, on the wall clock of its timezone. Recurrences
+    /// skipping days or weeks count them from the first occurrence at or after `after`, so pass
+    /// the last occurrence to keep in step
+    pub fn next_after(&self, after: DateTime<Tz>) -> Option<DateTime<Tz>> {
+        self.interval(after).next_run_after(after)
+    }
+
+    /// The schedule of the occurrences in the timezone of `from`, counted from the first one at
+    /// or after `from`
+    pub fn interval(&self, from: DateTime<Tz>) -> Interval {
+        let cron = self.cron.in_timezone(from.timezone());
+        let days = match (self.every, self.unit) {
+            (_, Unit::WorkingDay) => return Interval::Calendar(cron, DaysOff::Skip),
+            (1, _) | (_, Unit::Weekday) => return Interval::Cron(cron),
+            (every, Unit::Week(_)) => every as u64 * WEEK_AS_SECONDS,
+            (every, Unit::Day) => every as u64 * DAY_AS_SECONDS,
+        };
+        let start = cron
+            .next_after(&(from - chrono::Duration::seconds(1)))
+            .unwrap_or(from);
+        Interval::EveryDeltaStartAt(Duration::from_secs(days), start)
+    }
+}
+
+impl FromStr for Recurrence {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let lowercase = s.to_lowercase().replace(',', " ");
+        let mut words = lowercase.split_whitespace().peekable();
+        if words.peek() == Some(&"every") {
+            words.next();
+        }
+
+        let every = match words.peek().and_then(|w| w.parse::<u32>().ok()) {
+            Some(0) => return Err("Every 0 times never happens".to_string()),
+            Some(every) => {
+                words.next();
+                every
+            }
+            None => 1,
+        };
+        let mut unit = match words.next() {
+            Some("day" | "days") => Unit::Day,
+            Some("weekday" | "weekdays") => Unit::Weekday,
+            Some("working") => match words.next() {
+                Some("day" | "days") => Unit::WorkingDay,
+                _ => return Err("Expected working day".to_string()),
+            },
+            Some("week" | "weeks") => Unit::Week(0),
+            Some(word) => Unit::Week(weekdays(&mut words, weekday(word)?)?),
+            None => return Err("Say how often, e.g. every weekday at 08:15".to_string()),
+        };
+
+        let mut time = DEFAULT_TIME;
+        while let Some(word) = words.next() {
+            match (word, unit) {
+                ("on", Unit::Week(mask)) => unit = Unit::Week(weekdays(&mut words, mask)?),
+                ("on", _) => return Err("Only weekly reminders can be on given days".to_string()),
+                ("at", _) => {
+                    let at = words.next().ok_or("Expected a time after at")?;
+                    time = at.parse()?;
+                    if time.second() != 0 {
+                        return Err("Reminders can not be more precise than a minute".to_string());
+                    }
+                }
+                (word, _) => return Err(format!("Did not understand {}", word)),
+            }
+        }
+
+        let field = match unit {
+            Unit::Day => "*".to_string(),
+            Unit::Weekday | Unit::WorkingDay if every > 1 => {
+                return Err("Every few weekdays is not supported, try every few days".to_string())
+            }
+            Unit::Weekday | Unit::WorkingDay => "1-5".to_string(),
+            Unit::Week(0) => {
+                return Err("Say which day, e.g. every 2 weeks on thursday".to_string())
+            }
+            Unit::Week(mask) if every > 1 && mask.count_ones() > 1 => {
+                return Err("Reminders every few weeks can only be on one day".to_string())
+            }
+            Unit::Week(mask) => days(mask)
+                .map(|day| day.num_days_from_sunday().to_string())
+                .collect::<Vec<_>>()
+                .join(","),
+        };
+        let cron = format!("{} {} * * {}", time.minute(), time.hour(), field).parse()?;
+        Ok(Recurrence {
+            every,
+            unit,
+            time,
+            cron,
+        })
+    }
+}
+
+/// Weekdays written as e.g. `monday and friday`, added to the days already in `mask`
+fn weekdays<'a, I>(words: &mut Peekable<I>, mut mask: u8) -> Result<u8, String>
+where
+    I: Iterator<Item = &'a str>,
+{
+    while let Some(word) = words.peek() {
+        match *word {
+            "and" => {}
+            "at" | "on" => break,
+            word => mask |= weekday(word)?,
+        }
+        words.next();
+    }
+    Ok(mask)
+}
+
+/// A weekday, in the singular or plural, as a single bit mask
+fn weekday(word: &str) -> Result<u8, String> {
+    word.parse::<Weekday>()
+        .or_else(|_| word.trim_end_matches('s').parse::<Weekday>())
+        .map(|day| 1 << day.num_days_from_monday())
+        .map_err(|_| format!("Did not understand {}", word))
+}
+
+fn days(mask: u8) -> impl Iterator<Item = Weekday> {
+    [
+        Weekday::Mon,
+        Weekday::Tue,
+        Weekday::Wed,
+        Weekday::Thu,
+        Weekday::Fri,
+        Weekday::Sat,
+        Weekday::Sun,
+    ]
+    .into_iter()
+    .filter(move |day| mask & (1 << day.num_days_from_monday()) != 0)
+}
+
+/// Written so it parses back, e.g. `every 2 weeks on thursday at 09:00`
+impl fmt::Display for Recurrence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "every ")?;
+        if self.every > 1 {
+            write!(f, "{} ", self.every)?;
+        }
+        let plural = if self.every > 1 { "s" } else { "" };
+        match self.unit {
+            Unit::Day => write!(f, "day{}", plural)?,
+            Unit::Weekday => write!(f, "weekday")?,
+            Unit::WorkingDay => write!(f, "working day")?,
+            Unit::Week(mask) => {
+                let names: Vec<&str> = days(mask).map(name).collect();
+                if self.every > 1 {
+                    write!(f, "weeks on ")?;
+                }
+                write!(f, "{}", names.join(" and "))?;
+            }
+        }
+        write!(f, " at {:02}:{:02}", self.time.hour(), self.time.minute())
+    }
+}
+
+fn name(day: Weekday) -> &'static str {
+    match day {
+        Weekday::Mon => "monday",
+        Weekday::Tue => "tuesday",
+        Weekday::Wed => "wednesday",
+        Weekday::Thu => "thursday",
+        Weekday::Fri => "friday",
+        Weekday::Sat => "saturday",
+        Weekday::Sun => "sunday",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use chrono_tz::Europe::Oslo;
+
+    use super::*;
+
+    fn oslo(m: u32, d: u32, h: u32, min: u32) -> DateTime<Tz> {
+        Oslo.with_ymd_and_hms(2024, m, d, h, min, 0).unwrap()
+    }
+
+    fn occurrences(recurrence: &str, after: DateTime<Tz>, n: usize) -> Vec<DateTime<Tz>> {
+        let recurrence: Recurrence = recurrence.parse().unwrap();
+        let first = recurrence.next_after(after);
+        std::iter::successors(first, |last| recurrence.next_after(*last))
+            .take(n)
+            .collect()
+    }
+
+    #[test]
+    fn written_the_same_way_back() {
+        for (written, canonical) in [
+            ("every weekday at 08:15", "every weekday at 08:15"),
+            (
+                "Every 2 weeks on Thursday",
+                "every 2 weeks on thursday at 09:00",
+            ),
+            (
+                "mondays, fridays at 17:00",
+                "every monday and friday at 17:00",
+            ),
+            (
+                "every week on fri and mon at 17:00",
+                "every monday and friday at 17:00",
+            ),
+            ("every 3 days at 20:30", "every 3 days at 20:30"),
+            ("every working day at 7:45", "every working day at 07:45"),
+        ] {
+            let recurrence: Recurrence = written.parse().unwrap();
+            assert_eq!(recurrence.to_string(), canonical);
+            assert_eq!(canonical.parse::<Recurrence>(), Ok(recurrence));
+        }
+        for wrong in [
+            "",
+            "every 0 days",
+            "every week",
+            "every 2 weekdays",
+            "every 2 weeks on monday and friday",
+            "every day on monday",
+            "every day at noon",
+            "every fortnight",
+        ] {
+            assert!(wrong.parse::<Recurrence>().is_err(), "{wrong}");
+        }
+    }
+
+    #[test]
+    fn weekdays_and_working_days() {
+        // Thursday before Easter
+        let wednesday = oslo(3, 27, 9, 0);
+        assert_eq!(
+            occurrences("every weekday at 08:15", wednesday, 3),
+            vec![oslo(3, 28, 8, 15), oslo(3, 29, 8, 15), oslo(4, 1, 8, 15)]
+        );
+        assert_eq!(
+            occurrences("every working day at 08:15", wednesday, 2),
+            vec![oslo(4, 2, 8, 15), oslo(4, 3, 8, 15)]
+        );
+    }
+
+    #[test]
+    fn every_few_weeks_keeps_in_step() {
+        // A Tuesday, the clocks go forward on the last Sunday of March
+        let tuesday = oslo(3, 19, 12, 0);
+        assert_eq!(
+            occurrences("every 2 weeks on thursday", tuesday, 3),
+            vec![oslo(3, 21, 9, 0), oslo(4, 4, 9, 0), oslo(4, 18, 9, 0)]
+        );
+        assert_eq!(
+            occurrences("every 3 days at 20:00", oslo(3, 19, 21, 0), 2),
+            vec![oslo(3, 20, 20, 0), oslo(3, 23, 20, 0)]
+        );
+        // On the wall clock of the timezone it is asked in
+        let new_york = chrono_tz::America::New_York
+            .with_ymd_and_hms(2024, 3, 19, 12, 0, 0)
+            .unwrap();
+        assert_eq!(
+            occurrences("every 3 days at 20:00", new_york, 1),
+            vec![chrono_tz::America::New_York
+                .with_ymd_and_hms(2024, 3, 19, 20, 0, 0)
+                .unwrap()]
+        );
+        // The same as the first occurrence, so the next one is a period later
+        let recurrence: Recurrence = "every 2 weeks on thursday".parse().unwrap();
+        assert_eq!(
+            recurrence.next_after(oslo(3, 21, 9, 0)),
+            Some(oslo(4, 4, 9, 0))
+        );
+    }
+}
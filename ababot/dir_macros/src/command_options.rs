@@ -0,0 +1,299 @@
+use quote::ToTokens;
+use syn::{
+    Attribute, Data, DataEnum, DeriveInput, Field, Fields, GenericArgument, Lit, Meta, NestedMeta,
+    PathArguments, Type,
+};
+
+const OPTION_TYPE: &str = "serenity::builder::CreateApplicationCommandOption";
+const OPTION_KIND: &str = "serenity::model::prelude::command::CommandOptionType";
+const DATA_OPTION: &str =
+    "serenity::model::prelude::interaction::application_command::CommandDataOption";
+
+/// Everything that can be set through `#[option(...)]`
+#[derive(Default)]
+struct OptionAttributes {
+    name: Option<String>,
+    min: Option<Lit>,
+    max: Option<Lit>,
+    autocomplete: bool,
+    choices: Vec<(String, Lit)>,
+}
+
+pub fn derive(input: DeriveInput) -> String {
+    if !input.generics.params.is_empty() {
+        panic!("CommandOptions can not be derived for generic types");
+    }
+    let name = input.ident.to_string();
+    let (nested_kind, options, parse) = match &input.data {
+        Data::Struct(data) => {
+            let fields = match &data.fields {
+                Fields::Named(fields) => fields.named.iter().collect::<Vec<_>>(),
+                Fields::Unit => Vec::new(),
+                Fields::Unnamed(_) => panic!("CommandOptions fields of {} must be named", name),
+            };
+            let (options, parse) = derive_struct(&fields);
+            ("SubCommand", options, parse)
+        }
+        Data::Enum(data) => {
+            let (options, parse) = derive_enum(data);
+            ("SubCommandGroup", options, parse)
+        }
+        Data::Union(_) => panic!("CommandOptions can not be derived for unions"),
+    };
+
+    format!(
+        "impl crate::options::CommandOptions for {name} {{
+            const NESTED_KIND: {OPTION_KIND} = {OPTION_KIND}::{nested_kind};
+
+            fn options() -> Vec<{OPTION_TYPE}> {{
+                let mut options = Vec::new();
+                {options}
+                options
+            }}
+
+            fn from_options(options: &[{DATA_OPTION}]) -> Result<Self, crate::options::OptionError> {{
+                {parse}
+            }}
+        }}"
+    )
+}
+
+fn derive_struct(fields: &[&Field]) -> (String, String) {
+    let mut options = String::new();
+    let mut parsed_fields = String::new();
+    let mut seen_optional = false;
+
+    for field in fields {
+        let ident = field.ident.as_ref().unwrap().to_string();
+        let attributes = parse_attributes(&field.attrs);
+        let name = attributes
+            .name
+            .clone()
+            .unwrap_or_else(|| ident.trim_start_matches("r#").to_string());
+        let description = description(&field.attrs, &name);
+
+        let (ty, required) = match option_inner(&field.ty) {
+            Some(inner) => (inner, false),
+            None => (&field.ty, true),
+        };
+        if required && seen_optional {
+            panic!(
+                "Required option {} must come before the optional ones, Discord rejects it otherwise",
+                name
+            );
+        }
+        seen_optional |= !required;
+        let ty = ty.to_token_stream().to_string();
+
+        options.push_str(&format!(
+            "{{
+                let mut option = {OPTION_TYPE}::default();
+                option
+                    .name({name:?})
+                    .description({description:?})
+                    .kind(<{ty} as crate::options::OptionValue>::KIND)
+                    .required({required});
+                {tweaks}
+                options.push(option);
+            }}",
+            tweaks = tweaks(&attributes)
+        ));
+
+        let parser = if required { "required" } else { "optional" };
+        parsed_fields.push_str(&format!(
+            "{ident}: crate::options::{parser}(options, {name:?})?,"
+        ));
+    }
+
+    (options, format!("Ok(Self {{ {parsed_fields} }})"))
+}
+
+fn derive_enum(data: &DataEnum) -> (String, String) {
+    let mut options = String::new();
+    let mut arms = String::new();
+
+    for variant in &data.variants {
+        let ident = variant.ident.to_string();
+        let attributes = parse_attributes(&variant.attrs);
+        let name = attributes
+            .name
+            .clone()
+            .unwrap_or_else(|| snake_case(&ident));
+        let description = description(&variant.attrs, &name);
+
+        let (kind, sub_options, parse) = match &variant.fields {
+            Fields::Unit => (
+                format!("{OPTION_KIND}::SubCommand"),
+                String::new(),
+                format!("Ok(Self::{ident})"),
+            ),
+            Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
+                let ty = fields.unnamed[0].ty.to_token_stream().to_string();
+                (
+                    format!("<{ty} as crate::options::CommandOptions>::NESTED_KIND"),
+                    format!(
+                        "for sub_option in <{ty} as crate::options::CommandOptions>::options() {{
+                            option.add_sub_option(sub_option);
+                        }}"
+                    ),
+                    format!(
+                        "Ok(Self::{ident}(<{ty} as crate::options::CommandOptions>::from_options(&option.options)?))"
+                    ),
+                )
+            }
+            _ => panic!(
+                "Subcommand {} must be a unit variant or wrap a single type deriving CommandOptions",
+                ident
+            ),
+        };
+
+        options.push_str(&format!(
+            "{{
+                let mut option = {OPTION_TYPE}::default();
+                option
+                    .name({name:?})
+                    .description({description:?})
+                    .kind({kind});
+                {sub_options}
+                options.push(option);
+            }}"
+        ));
+        arms.push_str(&format!("{name:?} => {parse},"));
+    }
+
+    let parse = format!(
+        "let option = options
+            .get(0)
+            .ok_or(crate::options::OptionError::MissingSubcommand)?;
+        match option.name.as_str() {{
+            {arms}
+            other => Err(crate::options::OptionError::UnknownSubcommand(other.to_string())),
+        }}"
+    );
+    (options, parse)
+}
+
+/// Builder calls for the limits, autocompletion and choices of an option
+fn tweaks(attributes: &OptionAttributes) -> String {
+    let mut tweaks = String::new();
+    for (lit, bound) in [(&attributes.min, "min"), (&attributes.max, "max")] {
+        match lit {
+            Some(Lit::Int(i)) => tweaks.push_str(&format!("option.{bound}_int_value({i});")),
+            Some(Lit::Float(f)) => tweaks.push_str(&format!("option.{bound}_number_value({f});")),
+            Some(_) => panic!("{} must be an integer or a float", bound),
+            None => {}
+        }
+    }
+    if attributes.autocomplete {
+        tweaks.push_str("option.set_autocomplete(true);");
+    }
+    for (label, value) in &attributes.choices {
+        match value {
+            Lit::Str(s) => tweaks.push_str(&format!(
+                "option.add_string_choice({label:?}, {:?});",
+                s.value()
+            )),
+            Lit::Int(i) => tweaks.push_str(&format!("option.add_int_choice({label:?}, {i});")),
+            Lit::Float(f) => tweaks.push_str(&format!("option.add_number_choice({label:?}, {f});")),
+            _ => panic!("Choice {} must be a string, an integer or a float", label),
+        }
+    }
+    tweaks
+}
+
+fn parse_attributes(attrs: &[Attribute]) -> OptionAttributes {
+    let mut attributes = OptionAttributes::default();
+    for attr in attrs.iter().filter(|a| a.path.is_ident("option")) {
+        let list = match attr.parse_meta() {
+            Ok(Meta::List(list)) => list,
+            _ => panic!("Expected #[option(...)]"),
+        };
+        for nested in list.nested {
+            match nested {
+                NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("name") => match nv.lit {
+                    Lit::Str(s) => attributes.name = Some(s.value()),
+                    _ => panic!("Option name must be a string"),
+                },
+                NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("min") => {
+                    attributes.min = Some(nv.lit)
+                }
+                NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("max") => {
+                    attributes.max = Some(nv.lit)
+                }
+                NestedMeta::Meta(Meta::Path(p)) if p.is_ident("autocomplete") => {
+                    attributes.autocomplete = true
+                }
+                NestedMeta::Meta(Meta::List(choice)) if choice.path.is_ident("choice") => {
+                    let mut args = choice.nested.into_iter();
+                    match (args.next(), args.next(), args.next()) {
+                        (
+                            Some(NestedMeta::Lit(Lit::Str(label))),
+                            Some(NestedMeta::Lit(value)),
+                            None,
+                        ) => attributes.choices.push((label.value(), value)),
+                        _ => panic!("Expected choice(\"label\", value)"),
+                    }
+                }
+                _ => panic!(
+                    "Unknown option attribute, expected name, min, max, autocomplete or choice"
+                ),
+            }
+        }
+    }
+    attributes
+}
+
+/// Discord requires a description for every option, the doc comment is used for it
+fn description(attrs: &[Attribute], name: &str) -> String {
+    let lines = attrs
+        .iter()
+        .filter(|a| a.path.is_ident("doc"))
+        .filter_map(|a| match a.parse_meta() {
+            Ok(Meta::NameValue(nv)) => match nv.lit {
+                Lit::Str(s) => Some(s.value().trim().to_string()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    if lines.is_empty() {
+        panic!(
+            "Option {} needs a doc comment to use as its description",
+            name
+        );
+    }
+    lines.join(" ")
+}
+
+/// The `T` in `Option<T>`
+fn option_inner(ty: &Type) -> Option<&Type> {
+    let segment = match ty {
+        Type::Path(path) => path.path.segments.last()?,
+        _ => return None,
+    };
+    if segment.ident != "Option" {
+        return None;
+    }
+    match &segment.arguments {
+        PathArguments::AngleBracketed(args) => match args.args.first()? {
+            GenericArgument::Type(inner) => Some(inner),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn snake_case(ident: &str) -> String {
+    let mut name = String::new();
+    for (i, c) in ident.chars().enumerate() {
+        if c.is_uppercase() {
+            if i != 0 {
+                name.push('_');
+            }
+            name.extend(c.to_lowercase());
+        } else {
+            name.push(c);
+        }
+    }
+    name
+}